@@ -66,6 +66,11 @@ pub fn impl_saveload(ast: &mut DeriveInput) -> TokenStream {
 
     let type_def = derive.type_def;
     let saveload_name = derive.saveload_name;
+    let transient = if ast.attrs.iter().any(attribute_is_transient) {
+        quote! { const TRANSIENT: bool = true; }
+    } else {
+        quote! {}
+    };
 
     let ser = derive.ser;
     let de = derive.de;
@@ -79,6 +84,8 @@ pub fn impl_saveload(ast: &mut DeriveInput) -> TokenStream {
             type Data = #saveload_name #saveload_ty_generics;
             type Error = std::convert::Infallible;
 
+            #transient
+
             fn convert_into<F>(&self, mut ids: F) -> Result<Self::Data, Self::Error>
             where
                 F: FnMut(Entity) -> Option<MA>
@@ -103,6 +110,7 @@ pub fn impl_saveload(ast: &mut DeriveInput) -> TokenStream {
 struct FieldMetaData {
     field: Field,
     skip_field: bool,
+    transient: bool,
 }
 
 /// Implements all elements of saveload common to structs of any type
@@ -188,7 +196,9 @@ fn saveload_named_struct(
         let field = &field_meta.field;
         let field_ident = &field.ident;
 
-        if field_meta.skip_field {
+        if field_meta.transient {
+            quote! { #field_ident: ::std::marker::PhantomData }
+        } else if field_meta.skip_field {
             quote! { #field_ident: self.#field_ident.clone() }
         } else {
             quote! { #field_ident: ConvertSaveload::convert_into(&self.#field_ident, &mut ids)? }
@@ -207,7 +217,9 @@ fn saveload_named_struct(
             let field = &field_meta.field;
             let field_ident = &field.ident;
 
-            if field_meta.skip_field {
+            if field_meta.transient {
+                quote! { #field_ident: Default::default() }
+            } else if field_meta.skip_field {
                 quote! { #field_ident: data.#field_ident }
             } else {
                 quote! { #field_ident: ConvertSaveload::convert_from(data.#field_ident, &mut ids)? }
@@ -282,7 +294,9 @@ fn saveload_tuple_struct(
         .iter()
         .zip(field_ids.iter())
         .map(|(field_meta, field_id)| {
-            if field_meta.skip_field {
+            if field_meta.transient {
+                quote! { ::std::marker::PhantomData }
+            } else if field_meta.skip_field {
                 quote! { self.#field_id.clone() }
             } else {
                 quote! { ConvertSaveload::convert_into(&self.#field_id, &mut ids)? }
@@ -300,7 +314,9 @@ fn saveload_tuple_struct(
         .iter()
         .zip(field_ids)
         .map(|(field_meta, field_id)| {
-            if field_meta.skip_field {
+            if field_meta.transient {
+                quote! { Default::default() }
+            } else if field_meta.skip_field {
                 quote! { data.#field_id }
             } else {
                 quote! { ConvertSaveload::convert_from(data.#field_id, &mut ids)? }
@@ -331,6 +347,7 @@ where
             FieldMetaData {
                 field: resolved,
                 skip_field: field_should_skip(&f),
+                transient: field_is_transient(f),
             }
         })
         .collect()
@@ -413,7 +430,9 @@ fn saveload_enum(data: &DataEnum, name: &Ident, generics: &Generics) -> Saveload
                 let field_ser = saveload_fields.iter().map(|field_meta| {
                     let field_ident = &field_meta.field.ident;
 
-                    if field_meta.skip_field {
+                    if field_meta.transient {
+                        quote!{ #field_ident: { let _ = #field_ident; ::std::marker::PhantomData } }
+                    } else if field_meta.skip_field {
                         quote!{ #field_ident: #field_ident.clone() }
                     } else {
                         quote!{ #field_ident: ConvertSaveload::convert_into(#field_ident, &mut ids)? }
@@ -434,7 +453,9 @@ fn saveload_enum(data: &DataEnum, name: &Ident, generics: &Generics) -> Saveload
                 let field_de = saveload_fields.iter().map(|field_meta| {
                     let field_ident = &field_meta.field.ident;
 
-                    if field_meta.skip_field {
+                    if field_meta.transient {
+                        quote!{ #field_ident: { let _ = #field_ident; Default::default() } }
+                    } else if field_meta.skip_field {
                         quote!{ #field_ident: #field_ident }
                     } else {
                         quote!{ #field_ident: ConvertSaveload::convert_from(#field_ident, &mut ids)? }
@@ -460,7 +481,9 @@ fn saveload_enum(data: &DataEnum, name: &Ident, generics: &Generics) -> Saveload
                     .iter()
                     .zip(field_ids.iter())
                     .map(|(field_meta, field_ident)| {
-                        if field_meta.skip_field {
+                        if field_meta.transient {
+                            quote! { { let _ = #field_ident; ::std::marker::PhantomData } }
+                        } else if field_meta.skip_field {
                             quote! { #field_ident.clone() }
                         } else {
                             quote! { ConvertSaveload::convert_into(#field_ident, &mut ids)? }
@@ -477,7 +500,9 @@ fn saveload_enum(data: &DataEnum, name: &Ident, generics: &Generics) -> Saveload
                     .iter()
                     .zip(field_ids.iter())
                     .map(|(field_meta, field_ident)| {
-                        if field_meta.skip_field {
+                        if field_meta.transient {
+                            quote! { { let _ = #field_ident; Default::default() } }
+                        } else if field_meta.skip_field {
                             quote! { #field_ident.clone() }
                         } else {
                             quote! { ConvertSaveload::convert_from(#field_ident, &mut ids)? }
@@ -555,8 +580,23 @@ fn field_should_skip(field: &Field) -> bool {
     field.attrs.iter().any(attribute_is_skip)
 }
 
+fn attribute_is_transient(attribute: &Attribute) -> bool {
+    attribute.path.is_ident("convert_save_load_transient")
+}
+
+fn field_is_transient(field: &Field) -> bool {
+    field.attrs.iter().any(attribute_is_transient)
+}
+
+/// Transient fields are kept as a skipped `PhantomData` of their type, so
+/// the positions of tuple fields and the use of type parameters don't
+/// change.
 fn replace_field(field: &mut Field) {
-    if !field_should_skip(field) {
+    if field_is_transient(field) {
+        let ty = field.ty.clone();
+        field.ty = parse_quote!(::std::marker::PhantomData<#ty>);
+        field.attrs.push(parse_quote!(#[serde(skip)]));
+    } else if !field_should_skip(field) {
         replace_entity_type(&mut field.ty);
     }
 
@@ -613,7 +653,9 @@ fn replace_attributes(attrs: &mut Vec<Attribute>) {
     let output_attrs = attrs
         .iter()
         .filter_map(|attr| {
-            if attr.path.is_ident("convert_save_load_skip_convert") {
+            if attr.path.is_ident("convert_save_load_skip_convert")
+                || attr.path.is_ident("convert_save_load_transient")
+            {
                 None
             } else if attr.path.is_ident("convert_save_load_attr") {
                 match attr.parse_args_with(single_parse_outer_from_args) {
@@ -0,0 +1,104 @@
+//! Contains the implementation of `#[derive(SoaComponent)]`.
+
+use proc_macro2::{Span, TokenStream};
+use syn::{Data, DeriveInput, Fields, Ident};
+
+/// Generates `<Name>Columns` with a `Vec` per field, `<Name>Slices` and
+/// `<Name>SlicesMut` with a slice per field, and the `SoaColumns`,
+/// `SoaColumnsMut` and `SoaComponent` impls.
+pub fn impl_soa(ast: &DeriveInput) -> TokenStream {
+    let name = &ast.ident;
+    let vis = &ast.vis;
+
+    if !ast.generics.params.is_empty() {
+        panic!("Generic types cannot derive `SoaComponent`");
+    }
+    let fields = match ast.data {
+        Data::Struct(ref data) => match data.fields {
+            Fields::Named(ref fields) if !fields.named.is_empty() => &fields.named,
+            _ => panic!("Only structs with named fields can derive `SoaComponent`"),
+        },
+        _ => panic!("Only structs with named fields can derive `SoaComponent`"),
+    };
+
+    let columns = Ident::new(&format!("{}Columns", name), Span::call_site());
+    let slices = Ident::new(&format!("{}Slices", name), Span::call_site());
+    let slices_mut = Ident::new(&format!("{}SlicesMut", name), Span::call_site());
+    let columns_doc = format!("The columns of `{}`, with a `Vec` per field.", name);
+    let slices_doc = format!("The columns of `{}` as slices.", name);
+    let slices_mut_doc = format!("The columns of `{}` as mutable slices.", name);
+
+    let names: Vec<_> = fields.iter().map(|f| f.ident.as_ref().unwrap()).collect();
+    let tys: Vec<_> = fields.iter().map(|f| &f.ty).collect();
+    let field_vis: Vec<_> = fields.iter().map(|f| &f.vis).collect();
+    let first = names[0];
+
+    quote! {
+        #[doc = #columns_doc]
+        #[derive(Default)]
+        #vis struct #columns {
+            #(#names: Vec<#tys>,)*
+        }
+
+        #[doc = #slices_doc]
+        #vis struct #slices<'a> {
+            #(
+                #[allow(missing_docs)]
+                #field_vis #names: &'a [#tys],
+            )*
+        }
+
+        #[doc = #slices_mut_doc]
+        #vis struct #slices_mut<'a> {
+            #(
+                #[allow(missing_docs)]
+                #field_vis #names: &'a mut [#tys],
+            )*
+        }
+
+        impl #columns {
+            /// Returns the columns as slices.
+            #vis fn slices(&self) -> #slices<'_> {
+                #slices {
+                    #(#names: &self.#names,)*
+                }
+            }
+        }
+
+        impl<'a> SoaColumnsMut<'a> for #columns {
+            type SlicesMut = #slices_mut<'a>;
+
+            fn slices_mut(&'a mut self) -> #slices_mut<'a> {
+                #slices_mut {
+                    #(#names: &mut self.#names,)*
+                }
+            }
+        }
+
+        impl SoaColumns<#name> for #columns {
+            fn len(&self) -> usize {
+                self.#first.len()
+            }
+
+            fn push(&mut self, value: #name) {
+                #(self.#names.push(value.#names);)*
+            }
+
+            fn swap_remove(&mut self, row: usize) -> #name {
+                #name {
+                    #(#names: self.#names.swap_remove(row),)*
+                }
+            }
+
+            fn replace(&mut self, row: usize, value: #name) -> #name {
+                #name {
+                    #(#names: ::std::mem::replace(&mut self.#names[row], value.#names),)*
+                }
+            }
+        }
+
+        impl SoaComponent for #name {
+            type Columns = #columns;
+        }
+    }
+}
@@ -1,5 +1,6 @@
-//! Implements the `#[derive(Component)]`, `#[derive(Saveload)]` macro and
-//! `#[component]` attribute for [Specs][sp].
+//! Implements the `#[derive(Component)]`, `#[derive(Saveload)]` and
+//! `#[derive(SoaComponent)]` macros and `#[component]` attribute for
+//! [Specs][sp].
 //!
 //! [sp]: https://slide-rs.github.io/specs-website/
 
@@ -19,6 +20,7 @@ use syn::{
 };
 
 mod impl_saveload;
+mod impl_soa;
 
 /// Custom derive macro for the `Component` trait.
 ///
@@ -31,46 +33,79 @@ mod impl_saveload;
 /// #[storage(VecStorage)] // This line is optional, defaults to `DenseVecStorage`
 /// struct Pos(f32, f32, f32);
 /// ```
-#[proc_macro_derive(Component, attributes(storage))]
+///
+/// The lifecycle hooks `Component::on_insert` and `Component::on_remove` can
+/// be wired up to functions taking `(&mut Self, Entity, &LazyUpdate)`.
+/// Requires `Entity` and `LazyUpdate` to be in scope.
+///
+/// ```rust,ignore
+/// use specs::{Entity, LazyUpdate};
+///
+/// #[derive(Component)]
+/// #[on_insert(Texture::upload)]
+/// #[on_remove(Texture::release)]
+/// struct Texture(Handle);
+/// ```
+#[proc_macro_derive(Component, attributes(storage, on_insert, on_remove))]
 pub fn component(input: TokenStream) -> TokenStream {
     let ast = syn::parse(input).unwrap();
     let gen = impl_component(&ast);
     gen.into()
 }
 
-struct StorageAttribute {
-    storage: Path,
+struct PathAttribute {
+    path: Path,
 }
 
-impl Parse for StorageAttribute {
+impl Parse for PathAttribute {
     fn parse(input: ParseStream) -> Result<Self> {
         let content;
         let _parenthesized_token = parenthesized!(content in input);
 
-        Ok(StorageAttribute {
-            storage: content.parse()?,
+        Ok(PathAttribute {
+            path: content.parse()?,
         })
     }
 }
 
-fn impl_component(ast: &DeriveInput) -> proc_macro2::TokenStream {
-    let name = &ast.ident;
-    let (impl_generics, ty_generics, where_clause) = ast.generics.split_for_impl();
-
-    let storage = ast
-        .attrs
+fn path_attribute(ast: &DeriveInput, name: &str) -> Option<Path> {
+    ast.attrs
         .iter()
-        .find(|attr| attr.path.segments[0].ident == "storage")
+        .find(|attr| attr.path.segments[0].ident == name)
         .map(|attr| {
-            syn::parse2::<StorageAttribute>(attr.tokens.clone())
+            syn::parse2::<PathAttribute>(attr.tokens.clone())
                 .unwrap()
-                .storage
+                .path
         })
-        .unwrap_or_else(|| parse_quote!(DenseVecStorage));
+}
+
+fn impl_component(ast: &DeriveInput) -> proc_macro2::TokenStream {
+    let name = &ast.ident;
+    let (impl_generics, ty_generics, where_clause) = ast.generics.split_for_impl();
+
+    let storage =
+        path_attribute(ast, "storage").unwrap_or_else(|| parse_quote!(DenseVecStorage));
+    let on_insert = path_attribute(ast, "on_insert").map(|hook| {
+        quote! {
+            fn on_insert(&mut self, entity: Entity, lazy: &LazyUpdate) {
+                #hook(self, entity, lazy)
+            }
+        }
+    });
+    let on_remove = path_attribute(ast, "on_remove").map(|hook| {
+        quote! {
+            fn on_remove(&mut self, entity: Entity, lazy: &LazyUpdate) {
+                #hook(self, entity, lazy)
+            }
+        }
+    });
 
     quote! {
         impl #impl_generics Component for #name #ty_generics #where_clause {
             type Storage = #storage<Self>;
+
+            #on_insert
+            #on_remove
         }
     }
 }
@@ -87,9 +122,28 @@ fn impl_component(ast: &DeriveInput) -> proc_macro2::TokenStream {
 /// #[derive(ConvertSaveload)]
 /// struct Target(Entity);
 /// ```
+///
+/// Fields marked with `#[convert_save_load_transient]` are not serialized
+/// and set to `Default::default()` when deserializing. Marking the type
+/// itself sets `ConvertSaveload::TRANSIENT`, which makes
+/// `SerializeComponents::serialize_filtered` skip it by default.
+///
+/// ```rust,ignore
+/// #[derive(ConvertSaveload)]
+/// #[convert_save_load_transient]
+/// struct Path {
+///     target: Entity,
+///     #[convert_save_load_transient]
+///     cached_waypoints: Vec<[f32; 2]>,
+/// }
+/// ```
 #[proc_macro_derive(
     ConvertSaveload,
-    attributes(convert_save_load_attr, convert_save_load_skip_convert)
+    attributes(
+        convert_save_load_attr,
+        convert_save_load_skip_convert,
+        convert_save_load_transient
+    )
 )]
 pub fn saveload(input: TokenStream) -> TokenStream {
     use impl_saveload::impl_saveload;
@@ -98,3 +152,30 @@ pub fn saveload(input: TokenStream) -> TokenStream {
     let gen = impl_saveload(&mut ast);
     gen.into()
 }
+
+/// Custom derive macro for the `SoaComponent` trait, splitting a struct
+/// into a column per field.
+///
+/// Requires `SoaComponent`, `SoaColumns` and `SoaColumnsMut` to be in
+/// scope. For a struct `Motion`, this generates `MotionColumns`,
+/// `MotionSlices` and `MotionSlicesMut`.
+///
+/// ## Example
+///
+/// ```rust,ignore
+/// use specs::storage::{SoaColumns, SoaColumnsMut, SoaComponent};
+///
+/// #[derive(SoaComponent)]
+/// struct Motion {
+///     pos: [f32; 2],
+///     vel: [f32; 2],
+/// }
+/// ```
+#[proc_macro_derive(SoaComponent)]
+pub fn soa_component(input: TokenStream) -> TokenStream {
+    use impl_soa::impl_soa;
+    let ast = syn::parse(input).unwrap();
+
+    let gen = impl_soa(&ast);
+    gen.into()
+}
@@ -113,6 +113,59 @@ impl Display for WrongGeneration {
 
 impl StdError for WrongGeneration {}
 
+/// Error of the type-erased (de)serialization methods of `AnyStorage`.
+#[cfg(feature = "reflect")]
+#[derive(Debug)]
+pub enum ReflectError {
+    /// The component type with the given name wasn't registered through
+    /// `WorldExt::register_reflect`.
+    NotReflectable(&'static str),
+    /// Serializing or deserializing the component failed.
+    Serde(serde_json::Error),
+    /// The entity wasn't alive.
+    WrongGeneration(WrongGeneration),
+}
+
+#[cfg(feature = "reflect")]
+impl Display for ReflectError {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        match *self {
+            ReflectError::NotReflectable(name) => write!(
+                f,
+                "Component `{}` wasn't registered with `register_reflect`",
+                name
+            ),
+            ReflectError::Serde(ref e) => write!(f, "Failed to (de)serialize component: {}", e),
+            ReflectError::WrongGeneration(ref e) => write!(f, "{}", e),
+        }
+    }
+}
+
+#[cfg(feature = "reflect")]
+impl StdError for ReflectError {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        match *self {
+            ReflectError::NotReflectable(_) => None,
+            ReflectError::Serde(ref e) => Some(e),
+            ReflectError::WrongGeneration(ref e) => Some(e),
+        }
+    }
+}
+
+#[cfg(feature = "reflect")]
+impl From<WrongGeneration> for ReflectError {
+    fn from(e: WrongGeneration) -> Self {
+        ReflectError::WrongGeneration(e)
+    }
+}
+
+#[cfg(feature = "reflect")]
+impl From<ReflectError> for Error {
+    fn from(e: ReflectError) -> Self {
+        Error::Custom(BoxedErr::new(e))
+    }
+}
+
 /// Reexport of `Infallible` for a smoother transition.
 #[deprecated = "Use std::convert::Infallible instead"]
 pub type NoError = Infallible;
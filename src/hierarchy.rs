@@ -0,0 +1,421 @@
+//! Parent-child relationships between entities.
+//!
+//! An entity becomes the child of another one by getting a [`Parent`]
+//! component. The [`Hierarchy`] resource listens to the `ComponentEvent`s of
+//! the `Parent` storage and keeps a parent -> children index up to date, so
+//! children don't have to be rebuilt by hand every frame.
+//!
+//! Once a `Hierarchy` has been inserted into the `World`, deleting an entity
+//! (either with `WorldExt::delete_entity` or with `EntitiesRes::delete`
+//! followed by `WorldExt::maintain`) also deletes all of its descendants.
+//!
+//! ## Examples
+//!
+//! ```
+//! use specs::{
+//!     hierarchy::{Hierarchy, Parent},
+//!     prelude::*,
+//! };
+//!
+//! let mut world = World::new();
+//! world.register::<Parent>();
+//! let hierarchy = Hierarchy::new(&mut world.write_storage());
+//! world.insert(hierarchy);
+//!
+//! let root = world.create_entity().build();
+//! let child = world.create_entity().with(Parent(root)).build();
+//! let grandchild = world.create_entity().with(Parent(child)).build();
+//!
+//! world.maintain();
+//!
+//! {
+//!     let hierarchy = world.read_resource::<Hierarchy>();
+//!     assert_eq!(hierarchy.children(root), &[child]);
+//!     assert_eq!(hierarchy.parent(grandchild), Some(child));
+//!     assert_eq!(
+//!         hierarchy.depth_first(root).collect::<Vec<_>>(),
+//!         vec![child, grandchild]
+//!     );
+//! }
+//!
+//! world.delete_entity(root).unwrap();
+//! assert!(!world.is_alive(grandchild));
+//! ```
+//!
+//! [`Parent`]: struct.Parent.html
+//! [`Hierarchy`]: struct.Hierarchy.html
+
+use std::collections::VecDeque;
+
+use hashbrown::HashMap;
+use hibitset::BitSet;
+use shrev::ReaderId;
+
+use crate::{
+    join::Join,
+    storage::{ComponentEvent, DenseVecStorage, FlaggedStorage, ReadStorage, WriteStorage},
+    world::{Component, Entity, Index, World, WorldExt},
+};
+
+/// Component which makes its entity a child of the wrapped `Entity`.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct Parent(pub Entity);
+
+impl Component for Parent {
+    type Storage = FlaggedStorage<Self, DenseVecStorage<Self>>;
+}
+
+/// Resource holding the parent -> children index built from `Parent`
+/// components.
+///
+/// The index is brought up to date by `Hierarchy::maintain`, which is also
+/// called by `WorldExt::maintain`. Changes made to `Parent` components in
+/// between are only visible after the next call to either of them.
+pub struct Hierarchy {
+    parents: HashMap<Index, Entity>,
+    children: HashMap<Index, Vec<Entity>>,
+    reader: ReaderId<ComponentEvent>,
+    changed: BitSet,
+}
+
+impl Hierarchy {
+    /// Creates a new `Hierarchy`, registering a reader for the events of the
+    /// `Parent` storage and indexing the components which already exist.
+    pub fn new(parents: &mut WriteStorage<Parent>) -> Self {
+        let reader = parents.register_reader();
+        let mut hierarchy = Hierarchy {
+            parents: HashMap::new(),
+            children: HashMap::new(),
+            reader,
+            changed: BitSet::new(),
+        };
+
+        let parents_with_disabled = (
+            parents.fetched_entities().with_disabled(),
+            parents.with_disabled(),
+        );
+        for (child, parent) in parents_with_disabled.join() {
+            hierarchy.link(child, parent.0);
+        }
+
+        hierarchy
+    }
+
+    /// Returns the parent of `child`, if it has one.
+    pub fn parent(&self, child: Entity) -> Option<Entity> {
+        self.parents.get(&child.id()).cloned()
+    }
+
+    /// Returns the direct children of `parent`, in the order they were added.
+    pub fn children(&self, parent: Entity) -> &[Entity] {
+        self.children
+            .get(&parent.id())
+            .map(Vec::as_slice)
+            .unwrap_or(&[])
+    }
+
+    /// Returns an iterator over all descendants of `root` in depth-first
+    /// order. `root` itself is not yielded.
+    pub fn depth_first(&self, root: Entity) -> DepthFirst {
+        let mut stack: Vec<Entity> = self.children(root).to_vec();
+        stack.reverse();
+        let mut visited = BitSet::new();
+        visited.add(root.id());
+
+        DepthFirst {
+            hierarchy: self,
+            stack,
+            visited,
+        }
+    }
+
+    /// Returns an iterator over all descendants of `root` in breadth-first
+    /// order. `root` itself is not yielded.
+    pub fn breadth_first(&self, root: Entity) -> BreadthFirst {
+        let mut visited = BitSet::new();
+        visited.add(root.id());
+
+        BreadthFirst {
+            hierarchy: self,
+            queue: self.children(root).iter().cloned().collect(),
+            visited,
+        }
+    }
+
+    /// Reads the pending events of the `Parent` storage and updates the
+    /// index accordingly.
+    pub fn maintain(&mut self, parents: &ReadStorage<Parent>) {
+        self.changed.clear();
+        for event in parents.channel().read(&mut self.reader) {
+            match *event {
+                ComponentEvent::Inserted(id)
+                | ComponentEvent::Modified(id)
+                | ComponentEvent::Removed(id) => {
+                    self.changed.add(id);
+                }
+            }
+        }
+
+        let changed = std::mem::replace(&mut self.changed, BitSet::new());
+        for id in &changed {
+            self.unlink(id);
+            let child = parents.fetched_entities().entity(id);
+            if let Some(parent) = parents.get(child) {
+                self.link(child, parent.0);
+            }
+        }
+        self.changed = changed;
+    }
+
+    fn link(&mut self, child: Entity, parent: Entity) {
+        self.parents.insert(child.id(), parent);
+        self.children
+            .entry(parent.id())
+            .or_default()
+            .push(child);
+    }
+
+    fn unlink(&mut self, child: Index) {
+        if let Some(parent) = self.parents.remove(&child) {
+            let now_empty = match self.children.get_mut(&parent.id()) {
+                Some(children) => {
+                    children.retain(|e| e.id() != child);
+                    children.is_empty()
+                }
+                None => false,
+            };
+            if now_empty {
+                self.children.remove(&parent.id());
+            }
+        }
+    }
+}
+
+/// Depth-first iterator over the descendants of an entity.
+///
+/// Returned from `Hierarchy::depth_first`.
+pub struct DepthFirst<'a> {
+    hierarchy: &'a Hierarchy,
+    stack: Vec<Entity>,
+    visited: BitSet,
+}
+
+impl<'a> Iterator for DepthFirst<'a> {
+    type Item = Entity;
+
+    fn next(&mut self) -> Option<Entity> {
+        while let Some(entity) = self.stack.pop() {
+            // Guards against cycles in the `Parent` components.
+            if self.visited.add(entity.id()) {
+                continue;
+            }
+            self.stack
+                .extend(self.hierarchy.children(entity).iter().rev().cloned());
+
+            return Some(entity);
+        }
+
+        None
+    }
+}
+
+/// Breadth-first iterator over the descendants of an entity.
+///
+/// Returned from `Hierarchy::breadth_first`.
+pub struct BreadthFirst<'a> {
+    hierarchy: &'a Hierarchy,
+    queue: VecDeque<Entity>,
+    visited: BitSet,
+}
+
+impl<'a> Iterator for BreadthFirst<'a> {
+    type Item = Entity;
+
+    fn next(&mut self) -> Option<Entity> {
+        while let Some(entity) = self.queue.pop_front() {
+            // Guards against cycles in the `Parent` components.
+            if self.visited.add(entity.id()) {
+                continue;
+            }
+            self.queue
+                .extend(self.hierarchy.children(entity).iter().cloned());
+
+            return Some(entity);
+        }
+
+        None
+    }
+}
+
+/// Updates the `Hierarchy` of `world`, if there is one.
+pub(crate) fn maintain(world: &World) {
+    if let Some(mut hierarchy) = world.try_fetch_mut::<Hierarchy>() {
+        hierarchy.maintain(&world.read_storage::<Parent>());
+    }
+}
+
+/// Returns all descendants of `roots` which are not part of `roots`
+/// themselves. Returns an empty `Vec` if `world` has no `Hierarchy`.
+pub(crate) fn descendants(world: &World, roots: &[Entity]) -> Vec<Entity> {
+    let mut hierarchy = match world.try_fetch_mut::<Hierarchy>() {
+        Some(hierarchy) => hierarchy,
+        None => return vec![],
+    };
+    hierarchy.maintain(&world.read_storage::<Parent>());
+
+    let mut seen: BitSet = roots.iter().map(|e| e.id()).collect();
+    let mut descendants = vec![];
+    for &root in roots {
+        for entity in hierarchy.depth_first(root) {
+            if !seen.add(entity.id()) {
+                descendants.push(entity);
+            }
+        }
+    }
+
+    descendants
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::world::Builder;
+
+    fn create_world() -> World {
+        let mut world = World::new();
+        world.register::<Parent>();
+        let hierarchy = Hierarchy::new(&mut world.write_storage());
+        world.insert(hierarchy);
+
+        world
+    }
+
+    #[test]
+    fn indexes_children() {
+        let mut world = create_world();
+
+        let root = world.create_entity().build();
+        let a = world.create_entity().with(Parent(root)).build();
+        let b = world.create_entity().with(Parent(root)).build();
+        let c = world.create_entity().with(Parent(a)).build();
+        world.maintain();
+
+        let hierarchy = world.read_resource::<Hierarchy>();
+        assert_eq!(hierarchy.children(root), &[a, b]);
+        assert_eq!(hierarchy.children(a), &[c]);
+        assert!(hierarchy.children(c).is_empty());
+        assert_eq!(hierarchy.parent(c), Some(a));
+        assert_eq!(hierarchy.parent(root), None);
+    }
+
+    #[test]
+    fn indexes_existing_components() {
+        let mut world = World::new();
+        world.register::<Parent>();
+
+        let root = world.create_entity().build();
+        let child = world.create_entity().with(Parent(root)).build();
+
+        let hierarchy = Hierarchy::new(&mut world.write_storage());
+        assert_eq!(hierarchy.children(root), &[child]);
+    }
+
+    #[test]
+    fn traversal_order() {
+        let mut world = create_world();
+
+        let root = world.create_entity().build();
+        let a = world.create_entity().with(Parent(root)).build();
+        let b = world.create_entity().with(Parent(root)).build();
+        let a1 = world.create_entity().with(Parent(a)).build();
+        let b1 = world.create_entity().with(Parent(b)).build();
+        world.maintain();
+
+        let hierarchy = world.read_resource::<Hierarchy>();
+        assert_eq!(
+            hierarchy.depth_first(root).collect::<Vec<_>>(),
+            vec![a, a1, b, b1]
+        );
+        assert_eq!(
+            hierarchy.breadth_first(root).collect::<Vec<_>>(),
+            vec![a, b, a1, b1]
+        );
+    }
+
+    #[test]
+    fn reparenting() {
+        let mut world = create_world();
+
+        let a = world.create_entity().build();
+        let b = world.create_entity().build();
+        let child = world.create_entity().with(Parent(a)).build();
+        world.maintain();
+
+        world
+            .write_storage::<Parent>()
+            .insert(child, Parent(b))
+            .unwrap();
+        world.maintain();
+
+        let hierarchy = world.read_resource::<Hierarchy>();
+        assert!(hierarchy.children(a).is_empty());
+        assert_eq!(hierarchy.children(b), &[child]);
+        assert_eq!(hierarchy.parent(child), Some(b));
+    }
+
+    #[test]
+    fn cycles_terminate() {
+        let mut world = create_world();
+
+        let a = world.create_entity().build();
+        let b = world.create_entity().with(Parent(a)).build();
+        world.write_storage::<Parent>().insert(a, Parent(b)).unwrap();
+        world.maintain();
+
+        let hierarchy = world.read_resource::<Hierarchy>();
+        assert_eq!(hierarchy.depth_first(a).collect::<Vec<_>>(), vec![b]);
+        assert_eq!(hierarchy.breadth_first(a).collect::<Vec<_>>(), vec![b]);
+    }
+
+    #[test]
+    fn delete_entity_cascades() {
+        let mut world = create_world();
+
+        let root = world.create_entity().build();
+        let child = world.create_entity().with(Parent(root)).build();
+        let grandchild = world.create_entity().with(Parent(child)).build();
+        let other = world.create_entity().build();
+
+        // The index doesn't need to be maintained before deleting.
+        world.delete_entity(root).unwrap();
+
+        assert!(!world.is_alive(root));
+        assert!(!world.is_alive(child));
+        assert!(!world.is_alive(grandchild));
+        assert!(world.is_alive(other));
+
+        world.maintain();
+        let hierarchy = world.read_resource::<Hierarchy>();
+        assert!(hierarchy.children(root).is_empty());
+        assert!(hierarchy.children(child).is_empty());
+    }
+
+    #[test]
+    fn atomic_delete_cascades() {
+        let mut world = create_world();
+
+        let root = world.create_entity().build();
+        let child = world.create_entity().with(Parent(root)).build();
+        let grandchild = world.create_entity().with(Parent(child)).build();
+        world.maintain();
+
+        world.entities().delete(child).unwrap();
+        world.maintain();
+
+        assert!(world.is_alive(root));
+        assert!(!world.is_alive(child));
+        assert!(!world.is_alive(grandchild));
+        assert!(world.read_storage::<Parent>().is_empty());
+        assert!(world.read_resource::<Hierarchy>().children(root).is_empty());
+    }
+}
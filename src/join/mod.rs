@@ -2,7 +2,7 @@
 
 use std;
 
-use hibitset::{BitIter, BitSetAll, BitSetAnd, BitSetLike};
+use hibitset::{BitIter, BitSet, BitSetAll, BitSetAnd, BitSetLike};
 use shred::{Fetch, FetchMut, Read, ReadExpect, Resource, Write, WriteExpect};
 use std::ops::{Deref, DerefMut};
 use tuple_utils::Split;
@@ -11,9 +11,13 @@ use crate::world::{Entities, Entity, Index};
 
 #[cfg(feature = "parallel")]
 mod par_join;
+mod query;
 
 #[cfg(feature = "parallel")]
-pub use self::par_join::{JoinParIter, ParJoin};
+pub use self::par_join::{JoinParIter, ParJoin, ParJoinConfig};
+pub use self::query::{
+    Added, Changed, Query, QueryFilter, QueryFilters, Removed, With, Without,
+};
 
 /// `BitAnd` is a helper method to & bitsets together resulting in a tree.
 pub trait BitAnd {
@@ -411,6 +415,76 @@ impl<J: Join> std::iter::Iterator for JoinIter<J> {
     }
 }
 
+/// `OrderedJoinIter` is an `Iterator` over a group of `Storages`, visiting
+/// the indices in a given order instead of ascending order.
+///
+/// Indices which are not part of the join are skipped. It's usually
+/// created by `Storage::ordered_join`.
+#[must_use]
+pub struct OrderedJoinIter<'a, J: Join> {
+    order: std::slice::Iter<'a, Index>,
+    filter: Option<&'a BitSet>,
+    mask: J::Mask,
+    values: J::Value,
+}
+
+impl<'a, J: Join> OrderedJoinIter<'a, J> {
+    /// Create a new join iterator visiting the indices in `order`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `order` contains an index twice, since that would hand out
+    /// the same mutable component twice.
+    pub fn new(order: &'a [Index], j: J) -> Self {
+        Self::with_filter(order, None, j)
+    }
+
+    /// Like `new`, but additionally skips the indices not contained in
+    /// `filter`.
+    pub(crate) fn filtered(order: &'a [Index], filter: &'a BitSet, j: J) -> Self {
+        Self::with_filter(order, Some(filter), j)
+    }
+
+    fn with_filter(order: &'a [Index], filter: Option<&'a BitSet>, j: J) -> Self {
+        let mut seen = BitSet::new();
+        for &id in order {
+            if seen.add(id) {
+                panic!("Index {} is contained twice in the join order", id);
+            }
+        }
+
+        // SAFETY: We do not swap out the mask or the values, nor do we allow it by
+        // exposing them.
+        let (mask, values) = unsafe { j.open() };
+        OrderedJoinIter {
+            order: order.iter(),
+            filter,
+            mask,
+            values,
+        }
+    }
+}
+
+impl<'a, J: Join> std::iter::Iterator for OrderedJoinIter<'a, J> {
+    type Item = J::Type;
+
+    fn next(&mut self) -> Option<J::Type> {
+        let filter = self.filter;
+        let mask = &self.mask;
+        let values = &mut self.values;
+        let visit = |id| match filter {
+            Some(filter) => filter.contains(id) && mask.contains(id),
+            None => mask.contains(id),
+        };
+        self.order
+            .find(|&&id| visit(id))
+            .map(|&id| {
+                // SAFETY: `id` was checked to be part of the mask, as required by `get`.
+                unsafe { J::get(values, id) }
+            })
+    }
+}
+
 /// Clones the `JoinIter`.
 /// 
 /// # Examples
@@ -32,13 +32,99 @@ pub unsafe trait ParJoin: Join {
             );
         }
 
-        JoinParIter(self)
+        JoinParIter(self, ParJoinConfig::default())
+    }
+
+    /// Create a joined parallel iterator over the contents, which splits
+    /// the work according to `config`.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// # use specs::prelude::*;
+    /// # use specs::join::ParJoinConfig;
+    /// # struct Pos(f32); impl Component for Pos { type Storage = VecStorage<Self>; }
+    /// # let mut world = World::new();
+    /// # world.register::<Pos>();
+    /// let mut positions = world.write_storage::<Pos>();
+    /// (&mut positions)
+    ///     .par_join_with(ParJoinConfig::new().min_batch(4096))
+    ///     .for_each(|pos| pos.0 += 1.0);
+    /// ```
+    fn par_join_with(self, config: ParJoinConfig) -> JoinParIter<Self>
+    where
+        Self: Sized,
+    {
+        let mut iter = self.par_join();
+        iter.1 = config;
+
+        iter
+    }
+}
+
+/// Configures how a `JoinParIter` splits the work, see
+/// `ParJoin::par_join_with`.
+///
+/// The entity ids are split along the layers of the joined `BitSet`s, so
+/// the smallest batch of ids processed by a single task is either 64,
+/// 4096 or 262144 ids, depending on the number of layers that may be
+/// split. The default splits all three layers, while `0` processes all
+/// ids in a single task.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct ParJoinConfig {
+    layers: u8,
+}
+
+impl Default for ParJoinConfig {
+    fn default() -> Self {
+        ParJoinConfig { layers: 3 }
+    }
+}
+
+impl ParJoinConfig {
+    /// Creates the default configuration.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Sets the number of layers that may be split, from the top.
+    ///
+    /// `0` disables splitting altogether, so the join runs on a single
+    /// thread, `1` splits down to batches of 262144 ids and `3` down to
+    /// batches of 64 ids.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `layers` is greater than 3.
+    pub fn split_layers(mut self, layers: u8) -> Self {
+        assert!(layers <= 3, "Only three layers can be split");
+        self.layers = layers;
+
+        self
+    }
+
+    /// Sets the layers to split such that a batch spans at least `ids`
+    /// entity ids.
+    pub fn min_batch(self, ids: usize) -> Self {
+        let layers = match ids {
+            0..=64 => 3,
+            65..=4096 => 2,
+            4097..=262_144 => 1,
+            _ => 0,
+        };
+
+        self.split_layers(layers)
+    }
+
+    /// Returns the number of layers that may be split.
+    pub fn layers(&self) -> u8 {
+        self.layers
     }
 }
 
 /// `JoinParIter` is a `ParallelIterator` over a group of `Storages`.
 #[must_use]
-pub struct JoinParIter<J>(J);
+pub struct JoinParIter<J>(J, ParJoinConfig);
 
 impl<J> ParallelIterator for JoinParIter<J>
 where
@@ -55,7 +141,7 @@ where
     {
         let (keys, values) = unsafe { self.0.open() };
         // Create a bit producer which splits on up to three levels
-        let producer = BitProducer((&keys).iter(), 3);
+        let producer = BitProducer((&keys).iter(), self.1.layers);
         // HACK: use `UnsafeCell` to share `values` between threads;
         // this is the unspecified behavior referred to above.
         let values = UnsafeCell::new(values);
@@ -114,6 +200,12 @@ where
     type Item = J::Type;
 
     fn split(self) -> (Self, Option<Self>) {
+        // `BitProducer` always splits the top layer, even if it may split
+        // zero layers.
+        if self.keys.1 == 0 {
+            return (self, None);
+        }
+
         let (cur, other) = self.keys.split();
         let values = self.values;
         let first = JoinProducer::new(cur, values);
@@ -139,3 +231,41 @@ where
         folder.consume_iter(iter)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::cell::UnsafeCell;
+
+    use hibitset::{BitProducer, BitSet, BitSetLike};
+    use rayon::iter::plumbing::UnindexedProducer;
+
+    use super::JoinProducer;
+
+    fn count_batches(producer: JoinProducer<&BitSet>) -> usize {
+        match producer.split() {
+            (first, Some(second)) => count_batches(first) + count_batches(second),
+            (_, None) => 1,
+        }
+    }
+
+    #[test]
+    fn split_layers() {
+        let mut set = BitSet::new();
+        for id in (0..1_000_000).step_by(1000) {
+            set.add(id);
+        }
+        let mask = &set;
+        let values = UnsafeCell::new(());
+
+        let batches = |layers| {
+            let keys = BitProducer((&mask).iter(), layers);
+            count_batches(JoinProducer::<&BitSet>::new(keys, &values))
+        };
+
+        assert_eq!(batches(0), 1);
+        // The ids span four blocks of the top layer.
+        assert_eq!(batches(1), 4);
+        assert!(batches(2) > batches(1));
+        assert_eq!(batches(3), 1000);
+    }
+}
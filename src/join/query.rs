@@ -0,0 +1,317 @@
+//! Composable filters for joins.
+
+use std::{marker::PhantomData, ops::Deref};
+
+use hibitset::{BitSet, BitSetAll, BitSetAnd, BitSetLike, BitSetNot};
+use shrev::ReaderId;
+
+use crate::{
+    join::Join,
+    storage::{ComponentEvent, MaskedStorage, Storage, Tracked},
+    world::{Component, Index},
+};
+
+#[cfg(feature = "parallel")]
+use crate::join::ParJoin;
+
+/// A filter which can be added to a `Query`.
+pub trait QueryFilter {
+    /// The mask of indices passing this filter.
+    type Mask: BitSetLike;
+
+    /// Converts this filter into its mask.
+    fn into_mask(self) -> Self::Mask;
+
+    /// Returns `true` if the mask of this filter usually contains all
+    /// indices, like the one of `Without`. See `Join::is_unconstrained`.
+    #[inline]
+    fn is_unconstrained() -> bool {
+        false
+    }
+}
+
+/// The list of filters added to a `Query`, which is `()` for a query
+/// without filters.
+#[doc(hidden)]
+pub trait QueryFilters {
+    /// Returns `true` if none of the filters constrains the query.
+    fn is_unconstrained() -> bool;
+}
+
+impl QueryFilters for () {
+    #[inline]
+    fn is_unconstrained() -> bool {
+        true
+    }
+}
+
+impl<L, F> QueryFilters for (L, F)
+where
+    L: QueryFilters,
+    F: QueryFilter,
+{
+    #[inline]
+    fn is_unconstrained() -> bool {
+        L::is_unconstrained() && F::is_unconstrained()
+    }
+}
+
+/// Composes several `QueryFilter`s into a single `BitSetLike` mask.
+///
+/// A `Query` is a `Join` yielding the indices passing all of its filters,
+/// so it is usually joined together with the storages it filters, either
+/// with `join()` or with `par_join()`.
+///
+/// Note that a `Query` without any filter matches all indices, and so does
+/// a `Query` consisting only of `Without` filters; join it with
+/// `Entities` or a storage in that case. Such queries are reported by
+/// `Join::is_unconstrained`, so joining them alone logs a warning.
+///
+/// ## Example
+///
+/// ```
+/// # use specs::prelude::*;
+/// # use specs::join::{Changed, Query, With, Without};
+/// # #[derive(Debug, PartialEq)]
+/// # struct Pos(i32); impl Component for Pos { type Storage = VecStorage<Self>; }
+/// # #[derive(Debug, PartialEq)]
+/// # struct Vel(i32); impl Component for Vel { type Storage = FlaggedStorage<Self>; }
+/// # struct Frozen; impl Component for Frozen { type Storage = NullStorage<Self>; }
+/// # impl Default for Frozen { fn default() -> Self { Frozen } }
+/// let mut world = World::new();
+/// world.register::<Pos>();
+/// world.register::<Vel>();
+/// world.register::<Frozen>();
+///
+/// let mut reader = world.write_storage::<Vel>().register_reader();
+///
+/// let a = world.create_entity().with(Pos(0)).with(Vel(1)).build();
+/// world.create_entity().with(Pos(0)).with(Vel(1)).with(Frozen).build();
+/// world.create_entity().with(Vel(1)).build();
+///
+/// let entities = world.entities();
+/// let pos = world.read_storage::<Pos>();
+/// let vel = world.read_storage::<Vel>();
+/// let frozen = world.read_storage::<Frozen>();
+///
+/// let query = Query::new()
+///     .filter(With::new(&pos))
+///     .filter(Without::new(&frozen))
+///     .filter(Changed::new(&vel, &mut reader));
+///
+/// let moving: Vec<_> = (&entities, &vel, query).join().map(|(e, _, _)| e).collect();
+/// assert_eq!(moving, vec![a]);
+/// ```
+pub struct Query<M = BitSetAll, L = ()> {
+    mask: M,
+    filters: PhantomData<fn() -> L>,
+}
+
+impl Query {
+    /// Creates a new query without any filters.
+    pub fn new() -> Self {
+        Query {
+            mask: BitSetAll,
+            filters: PhantomData,
+        }
+    }
+}
+
+impl Default for Query {
+    fn default() -> Self {
+        Query::new()
+    }
+}
+
+impl<M, L> Query<M, L>
+where
+    M: BitSetLike,
+{
+    /// Adds a filter to this query; an index needs to pass all filters in
+    /// order to be part of the query.
+    pub fn filter<F>(self, filter: F) -> Query<BitSetAnd<M, F::Mask>, (L, F)>
+    where
+        F: QueryFilter,
+    {
+        Query {
+            mask: BitSetAnd(self.mask, filter.into_mask()),
+            filters: PhantomData,
+        }
+    }
+
+    /// Returns the composed mask of all filters.
+    pub fn mask(&self) -> &M {
+        &self.mask
+    }
+
+    /// Converts this query into its composed mask.
+    pub fn into_mask(self) -> M {
+        self.mask
+    }
+}
+
+impl<M, L> Join for Query<M, L>
+where
+    M: BitSetLike,
+    L: QueryFilters,
+{
+    type Mask = M;
+    type Type = Index;
+    type Value = ();
+
+    // SAFETY: This just moves the mask; invariants of `Join` are fulfilled, since
+    // `Self::Value` cannot be mutated.
+    unsafe fn open(self) -> (Self::Mask, Self::Value) {
+        (self.mask, ())
+    }
+
+    // SAFETY: No unsafe code and no invariants to meet.
+    unsafe fn get(_: &mut Self::Value, id: Index) -> Self::Type {
+        id
+    }
+
+    #[inline]
+    fn is_unconstrained() -> bool {
+        L::is_unconstrained()
+    }
+}
+
+// SAFETY: `get` does not access any memory.
+#[cfg(feature = "parallel")]
+unsafe impl<M, L> ParJoin for Query<M, L>
+where
+    M: BitSetLike,
+    L: QueryFilters,
+{
+}
+
+/// Matches all indices which have a component of type `T`.
+pub struct With<'a, T> {
+    mask: &'a BitSet,
+    phantom: PhantomData<T>,
+}
+
+impl<'a, T> With<'a, T>
+where
+    T: Component,
+{
+    /// Creates a new filter from the storage of `T`.
+    pub fn new<D>(storage: &'a Storage<T, D>) -> Self
+    where
+        D: Deref<Target = MaskedStorage<T>>,
+    {
+        With {
+            mask: storage.mask(),
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<'a, T> QueryFilter for With<'a, T> {
+    type Mask = &'a BitSet;
+
+    fn into_mask(self) -> Self::Mask {
+        self.mask
+    }
+}
+
+/// Matches all indices which don't have a component of type `T`.
+pub struct Without<'a, T> {
+    mask: &'a BitSet,
+    phantom: PhantomData<T>,
+}
+
+impl<'a, T> Without<'a, T>
+where
+    T: Component,
+{
+    /// Creates a new filter from the storage of `T`.
+    pub fn new<D>(storage: &'a Storage<T, D>) -> Self
+    where
+        D: Deref<Target = MaskedStorage<T>>,
+    {
+        Without {
+            mask: storage.mask(),
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<'a, T> QueryFilter for Without<'a, T> {
+    type Mask = BitSetNot<&'a BitSet>;
+
+    fn into_mask(self) -> Self::Mask {
+        BitSetNot(self.mask)
+    }
+
+    #[inline]
+    fn is_unconstrained() -> bool {
+        true
+    }
+}
+
+macro_rules! event_filter {
+    ($(#[$attr:meta])* $name:ident, $($event:ident)|+) => {
+        $(#[$attr])*
+        ///
+        /// Creating this filter reads all pending events of `reader`.
+        pub struct $name<T> {
+            mask: BitSet,
+            phantom: PhantomData<T>,
+        }
+
+        impl<T> $name<T>
+        where
+            T: Component,
+            T::Storage: Tracked,
+        {
+            /// Creates a new filter from the events of the storage of `T`.
+            pub fn new<D>(storage: &Storage<T, D>, reader: &mut ReaderId<ComponentEvent>) -> Self
+            where
+                D: Deref<Target = MaskedStorage<T>>,
+            {
+                let mut mask = BitSet::new();
+                for event in storage.channel().read(reader) {
+                    match *event {
+                        $(ComponentEvent::$event(id))|+ => {
+                            mask.add(id);
+                        }
+                        _ => {}
+                    }
+                }
+
+                $name {
+                    mask,
+                    phantom: PhantomData,
+                }
+            }
+        }
+
+        impl<T> QueryFilter for $name<T> {
+            type Mask = BitSet;
+
+            fn into_mask(self) -> Self::Mask {
+                self.mask
+            }
+        }
+    };
+}
+
+event_filter!(
+    /// Matches all indices whose component of type `T` was inserted since
+    /// the events were last read.
+    Added,
+    Inserted
+);
+event_filter!(
+    /// Matches all indices whose component of type `T` was inserted or
+    /// modified since the events were last read.
+    Changed,
+    Inserted | Modified
+);
+event_filter!(
+    /// Matches all indices whose component of type `T` was removed since
+    /// the events were last read.
+    Removed,
+    Removed
+);
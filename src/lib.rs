@@ -199,8 +199,16 @@ pub mod saveload;
 mod bitset;
 pub mod changeset;
 pub mod error;
+pub mod hierarchy;
 pub mod join;
+pub mod mailbox;
+pub mod named;
 pub mod prelude;
+#[cfg(feature = "serde")]
+pub mod prefab;
+pub mod relation;
+#[cfg(feature = "serde")]
+pub mod replication;
 pub mod storage;
 pub mod world;
 
@@ -216,7 +224,7 @@ pub use shrev::ReaderId;
 pub use shred::AsyncDispatcher;
 
 #[cfg(feature = "specs-derive")]
-pub use specs_derive::{Component, ConvertSaveload};
+pub use specs_derive::{Component, ConvertSaveload, SoaComponent};
 
 #[cfg(feature = "parallel")]
 pub use crate::join::ParJoin;
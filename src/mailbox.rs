@@ -0,0 +1,326 @@
+//! Events addressed to single entities.
+//!
+//! An `EventChannel` broadcasts every event to every reader, so sending an
+//! event to one entity means every reader has to filter. A [`Mailbox`]
+//! instead keeps the events per entity: systems send events to an `Entity`
+//! through a shared reference, and another system drains them with a join,
+//! only visiting the entities which actually received something.
+//!
+//! Mailboxes registered with `WorldExt::register_mailbox` are cleared by
+//! every call to `WorldExt::maintain`, so events have to be drained in the
+//! tick they were sent in.
+//!
+//! ## Examples
+//!
+//! ```
+//! use specs::{mailbox::Mailbox, prelude::*};
+//!
+//! struct Damage(u32);
+//!
+//! struct Health(u32);
+//!
+//! impl Component for Health {
+//!     type Storage = VecStorage<Self>;
+//! }
+//!
+//! let mut world = World::new();
+//! world.register::<Health>();
+//! world.register_mailbox::<Damage>();
+//!
+//! let target = world.create_entity().with(Health(100)).build();
+//!
+//! // `send` only needs a shared reference, so it can be called from
+//! // parallel systems.
+//! world.read_resource::<Mailbox<Damage>>().send(target, Damage(30));
+//! world.read_resource::<Mailbox<Damage>>().send(target, Damage(20));
+//!
+//! world.exec(
+//!     |(entities, mut healths, mut mailbox): (
+//!         Entities,
+//!         WriteStorage<Health>,
+//!         Write<Mailbox<Damage>>,
+//!     )| {
+//!         let mail = mailbox.drain(&entities);
+//!         for (_, health, damage) in (&entities, &mut healths, mail).join() {
+//!             for Damage(amount) in damage {
+//!                 health.0 -= amount;
+//!             }
+//!         }
+//!     },
+//! );
+//!
+//! assert_eq!(world.read_storage::<Health>().get(target).unwrap().0, 50);
+//! ```
+//!
+//! [`Mailbox`]: struct.Mailbox.html
+
+use std::any::TypeId;
+
+use crossbeam_queue::SegQueue;
+use hashbrown::HashMap;
+use hibitset::BitSet;
+use shred::Resource;
+
+use crate::{
+    join::Join,
+    world::{EntitiesRes, Entity, Index, World},
+};
+
+/// Resource holding the events of type `E` sent to entities.
+///
+/// Events are sent with `Mailbox::send` and received by joining over
+/// `Mailbox::drain`, which yields all events sent to an entity at once, in
+/// the order they were sent in.
+pub struct Mailbox<E> {
+    queue: SegQueue<(Entity, E)>,
+    mask: BitSet,
+    inboxes: HashMap<Index, (Entity, Vec<E>)>,
+}
+
+impl<E> Default for Mailbox<E> {
+    fn default() -> Self {
+        Mailbox {
+            queue: SegQueue::new(),
+            mask: BitSet::new(),
+            inboxes: HashMap::new(),
+        }
+    }
+}
+
+impl<E> Mailbox<E> {
+    /// Creates a new, empty `Mailbox`.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Sends `event` to `target`.
+    ///
+    /// This only needs a shared reference, so events can be sent from any
+    /// number of systems running in parallel.
+    pub fn send(&self, target: Entity, event: E) {
+        self.queue.push((target, event));
+    }
+
+    /// Returns `true` if there are no events which weren't drained yet.
+    pub fn is_empty(&self) -> bool {
+        self.queue.is_empty() && self.inboxes.is_empty()
+    }
+
+    /// Delivers the events sent so far and returns a `Join`-able structure
+    /// yielding the events of every entity as a `Vec`, removing them from
+    /// the mailbox.
+    ///
+    /// Events sent to entities which aren't alive anymore are dropped.
+    /// Events of entities which aren't part of the join are kept, either
+    /// for another drain or until the mailbox is cleared, unless the entity
+    /// dies in the meantime.
+    pub fn drain(&mut self, entities: &EntitiesRes) -> DrainMail<'_, E> {
+        // Drop the kept events of dead entities, so they aren't handed to a
+        // new entity reusing the index.
+        let mask = &mut self.mask;
+        self.inboxes.retain(|&id, &mut (target, _)| {
+            let alive = entities.is_alive(target);
+            if !alive {
+                mask.remove(id);
+            }
+
+            alive
+        });
+
+        while let Ok((target, event)) = self.queue.pop() {
+            if entities.is_alive(target) {
+                self.mask.add(target.id());
+                self.inboxes
+                    .entry(target.id())
+                    .or_insert_with(|| (target, Vec::new()))
+                    .1
+                    .push(event);
+            }
+        }
+
+        DrainMail { mailbox: self }
+    }
+
+    /// Drops all events, including the ones which weren't delivered yet.
+    pub fn clear(&mut self) {
+        while self.queue.pop().is_ok() {}
+        self.mask.clear();
+        self.inboxes.clear();
+    }
+}
+
+/// A `Join` over the events of a `Mailbox`, created by `Mailbox::drain`.
+pub struct DrainMail<'a, E> {
+    mailbox: &'a mut Mailbox<E>,
+}
+
+impl<'a, E> Join for DrainMail<'a, E> {
+    type Mask = BitSet;
+    type Type = Vec<E>;
+    type Value = &'a mut Mailbox<E>;
+
+    // SAFETY: No invariants to meet and no unsafe code.
+    unsafe fn open(self) -> (Self::Mask, Self::Value) {
+        let mask = self.mailbox.mask.clone();
+
+        (mask, self.mailbox)
+    }
+
+    // SAFETY: No invariants to meet and no unsafe code.
+    unsafe fn get(mailbox: &mut Self::Value, id: Index) -> Vec<E> {
+        mailbox.mask.remove(id);
+        let (_, events) = mailbox
+            .inboxes
+            .remove(&id)
+            .expect("Tried to access same index twice");
+
+        events
+    }
+}
+
+/// The type of a registered mailbox and the function clearing it.
+type MailboxClearing = (TypeId, fn(&World));
+
+/// Resource holding the clearing functions of the mailboxes registered with
+/// `WorldExt::register_mailbox`.
+#[derive(Default)]
+pub(crate) struct MailboxRegistry {
+    mailboxes: Vec<MailboxClearing>,
+}
+
+impl MailboxRegistry {
+    pub(crate) fn register<E>(&mut self)
+    where
+        Mailbox<E>: Resource,
+    {
+        if self
+            .mailboxes
+            .iter()
+            .all(|&(mailbox, _)| mailbox != TypeId::of::<Mailbox<E>>())
+        {
+            self.mailboxes
+                .push((TypeId::of::<Mailbox<E>>(), clear::<E>));
+        }
+    }
+}
+
+fn clear<E>(world: &World)
+where
+    Mailbox<E>: Resource,
+{
+    world.fetch_mut::<Mailbox<E>>().clear();
+}
+
+/// Clears all mailboxes registered in `world`.
+pub(crate) fn maintain(world: &World) {
+    if let Some(registry) = world.try_fetch::<MailboxRegistry>() {
+        for &(_, clear) in &registry.mailboxes {
+            clear(world);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::world::{Builder, WorldExt};
+
+    #[test]
+    fn drain_per_entity() {
+        let mut world = World::new();
+        world.register_mailbox::<u32>();
+
+        let a = world.create_entity().build();
+        let b = world.create_entity().build();
+        let c = world.create_entity().build();
+
+        {
+            let mailbox = world.read_resource::<Mailbox<u32>>();
+            mailbox.send(a, 1);
+            mailbox.send(c, 2);
+            mailbox.send(a, 3);
+        }
+
+        let entities = world.entities();
+        let mut mailbox = world.write_resource::<Mailbox<u32>>();
+
+        let received: Vec<_> = (&entities, mailbox.drain(&entities)).join().collect();
+        assert_eq!(received, vec![(a, vec![1, 3]), (c, vec![2])]);
+        assert!(mailbox.is_empty());
+
+        mailbox.send(b, 4);
+        assert!(!mailbox.is_empty());
+        let received: Vec<_> = mailbox.drain(&entities).join().collect();
+        assert_eq!(received, vec![vec![4]]);
+    }
+
+    #[test]
+    fn drops_events_of_dead_entities() {
+        let mut world = World::new();
+        world.register_mailbox::<u32>();
+
+        let a = world.create_entity().build();
+        world.delete_entity(a).unwrap();
+        world.read_resource::<Mailbox<u32>>().send(a, 1);
+        // Possibly reuses the index of `a` with a new generation.
+        world.create_entity().build();
+
+        let entities = world.entities();
+        let mut mailbox = world.write_resource::<Mailbox<u32>>();
+        assert_eq!(mailbox.drain(&entities).join().count(), 0);
+        assert!(mailbox.is_empty());
+    }
+
+    #[test]
+    fn drops_kept_events_of_dead_entities() {
+        let mut world = World::new();
+        world.register_mailbox::<u32>();
+
+        let a = world.create_entity().build();
+        {
+            let entities = world.entities();
+            let mut mailbox = world.write_resource::<Mailbox<u32>>();
+            mailbox.send(a, 1);
+            // Delivers the event of `a` without draining it.
+            assert_eq!((&BitSet::new(), mailbox.drain(&entities)).join().count(), 0);
+        }
+
+        world.delete_entity(a).unwrap();
+        let b = world.create_entity().build();
+        assert_eq!(a.id(), b.id());
+
+        let entities = world.entities();
+        let mut mailbox = world.write_resource::<Mailbox<u32>>();
+        mailbox.send(b, 2);
+        let received: Vec<_> = (&entities, mailbox.drain(&entities)).join().collect();
+        assert_eq!(received, vec![(b, vec![2])]);
+        assert!(mailbox.is_empty());
+    }
+
+    #[test]
+    fn cleared_on_maintain() {
+        let mut world = World::new();
+        world.register_mailbox::<u32>();
+
+        let a = world.create_entity().build();
+        let b = world.create_entity().build();
+        {
+            let entities = world.entities();
+            let mut mailbox = world.write_resource::<Mailbox<u32>>();
+            mailbox.send(a, 1);
+            mailbox.send(b, 2);
+            // Only drains the events of `a`.
+            let mut only_a = BitSet::new();
+            only_a.add(a.id());
+            assert_eq!((&only_a, mailbox.drain(&entities)).join().count(), 1);
+            mailbox.send(a, 3);
+        }
+
+        world.maintain();
+
+        let entities = world.entities();
+        let mut mailbox = world.write_resource::<Mailbox<u32>>();
+        assert!(mailbox.is_empty());
+        assert_eq!(mailbox.drain(&entities).join().count(), 0);
+    }
+}
@@ -0,0 +1,428 @@
+//! Names and tags for finding entities without a join.
+//!
+//! A [`Name`] identifies a single entity, like "player" or "camera", while a
+//! [`Tag`] groups entities, either by a string or by a value of any other
+//! type, e.g. an enum. The [`NameIndex`] and [`TagIndex`] resources listen to
+//! the `ComponentEvent`s of the `Name` and `Tag` storages and keep an index
+//! up to date, which `WorldExt::maintain` brings up to date once they are
+//! registered with `WorldExt::register_names` and `WorldExt::register_tags`.
+//!
+//! ## Examples
+//!
+//! ```
+//! use specs::{
+//!     named::{Name, NameIndex, Tag, TagIndex},
+//!     prelude::*,
+//! };
+//!
+//! #[derive(Clone, Debug, Eq, Hash, PartialEq)]
+//! enum Team {
+//!     Red,
+//!     Blue,
+//! }
+//!
+//! let mut world = World::new();
+//! world.register_names();
+//! world.register_tags::<Team>();
+//!
+//! let player = world
+//!     .create_entity()
+//!     .with(Name::new("player"))
+//!     .with(Tag(Team::Red))
+//!     .build();
+//! let enemy = world.create_entity().with(Tag(Team::Blue)).build();
+//!
+//! world.maintain();
+//!
+//! assert_eq!(world.read_resource::<NameIndex>().lookup("player"), Some(player));
+//! let tags = world.read_resource::<TagIndex<Team>>();
+//! assert!(tags.entities_with_tag(&Team::Blue).contains(enemy.id()));
+//! assert!(!tags.entities_with_tag(&Team::Blue).contains(player.id()));
+//! ```
+//!
+//! [`Name`]: struct.Name.html
+//! [`Tag`]: struct.Tag.html
+//! [`NameIndex`]: struct.NameIndex.html
+//! [`TagIndex`]: struct.TagIndex.html
+
+use std::{any::TypeId, hash::Hash};
+
+use hashbrown::HashMap;
+use hibitset::{BitSet, BitSetLike};
+use shrev::ReaderId;
+
+use crate::{
+    join::Join,
+    storage::{ComponentEvent, DenseVecStorage, FlaggedStorage, ReadStorage, WriteStorage},
+    world::{Component, Entity, Index, World, WorldExt},
+};
+
+/// Component giving its entity a name which can be looked up in the
+/// `NameIndex`. Names are expected to be unique; if several entities have
+/// the same name, `NameIndex::lookup` returns one of them.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct Name(pub String);
+
+impl Name {
+    /// Creates a new `Name`.
+    pub fn new<S: Into<String>>(name: S) -> Self {
+        Name(name.into())
+    }
+
+    /// Returns the name as a string slice.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Component for Name {
+    type Storage = FlaggedStorage<Self, DenseVecStorage<Self>>;
+}
+
+/// Component tagging its entity with a value, so all entities with that tag
+/// can be found in the `TagIndex<T>`.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct Tag<T = String>(pub T);
+
+impl<T> Component for Tag<T>
+where
+    T: Clone + Eq + Hash + Send + Sync + 'static,
+{
+    type Storage = FlaggedStorage<Self, DenseVecStorage<Self>>;
+}
+
+/// Resource mapping names to the entities with the corresponding `Name`.
+///
+/// The index is brought up to date by `NameIndex::maintain`, which is also
+/// called by `WorldExt::maintain` once the index was registered with
+/// `WorldExt::register_names`. Changes made to `Name` components in between
+/// are only visible after the next call to either of them.
+pub struct NameIndex {
+    /// All entities with a name, in the order they were indexed.
+    entities: HashMap<String, Vec<Entity>>,
+    names: HashMap<Index, String>,
+    reader: ReaderId<ComponentEvent>,
+}
+
+impl NameIndex {
+    /// Creates a new `NameIndex`, registering a reader for the events of the
+    /// `Name` storage and indexing the components which already exist.
+    pub fn new(names: &mut WriteStorage<Name>) -> Self {
+        let reader = names.register_reader();
+        let mut index = NameIndex {
+            entities: HashMap::new(),
+            names: HashMap::new(),
+            reader,
+        };
+
+        let named = (
+            names.fetched_entities().with_disabled(),
+            names.with_disabled(),
+        );
+        for (entity, name) in named.join() {
+            index.link(entity, &name.0);
+        }
+
+        index
+    }
+
+    /// Returns the entity named `name`. If several entities have the same
+    /// name, the one indexed last is returned; once it loses the name, the
+    /// one indexed before it is returned.
+    pub fn lookup(&self, name: &str) -> Option<Entity> {
+        self.entities
+            .get(name)
+            .and_then(|entities| entities.last())
+            .cloned()
+    }
+
+    /// Reads the pending events of the `Name` storage and updates the index
+    /// accordingly.
+    pub fn maintain(&mut self, names: &ReadStorage<Name>) {
+        let mut changed = BitSet::new();
+        for event in names.channel().read(&mut self.reader) {
+            match *event {
+                ComponentEvent::Inserted(id)
+                | ComponentEvent::Modified(id)
+                | ComponentEvent::Removed(id) => {
+                    changed.add(id);
+                }
+            }
+        }
+
+        for id in &changed {
+            self.unlink(id);
+            let entity = names.fetched_entities().entity(id);
+            if let Some(name) = names.get(entity) {
+                self.link(entity, &name.0);
+            }
+        }
+    }
+
+    fn link(&mut self, entity: Entity, name: &str) {
+        self.entities
+            .entry(name.to_owned())
+            .or_default()
+            .push(entity);
+        self.names.insert(entity.id(), name.to_owned());
+    }
+
+    fn unlink(&mut self, id: Index) {
+        if let Some(name) = self.names.remove(&id) {
+            let now_empty = match self.entities.get_mut(&name) {
+                Some(entities) => {
+                    entities.retain(|e| e.id() != id);
+                    entities.is_empty()
+                }
+                None => false,
+            };
+            if now_empty {
+                self.entities.remove(&name);
+            }
+        }
+    }
+}
+
+/// Resource mapping tags to the entities with the corresponding `Tag<T>`.
+///
+/// Like the `NameIndex`, it's brought up to date by `TagIndex::maintain` and
+/// by `WorldExt::maintain` once registered with `WorldExt::register_tags`.
+pub struct TagIndex<T = String> {
+    tagged: HashMap<T, BitSet>,
+    tags: HashMap<Index, T>,
+    reader: ReaderId<ComponentEvent>,
+    empty: BitSet,
+}
+
+impl<T> TagIndex<T>
+where
+    T: Clone + Eq + Hash + Send + Sync + 'static,
+{
+    /// Creates a new `TagIndex`, registering a reader for the events of the
+    /// `Tag<T>` storage and indexing the components which already exist.
+    pub fn new(tags: &mut WriteStorage<Tag<T>>) -> Self {
+        let reader = tags.register_reader();
+        let mut index = TagIndex {
+            tagged: HashMap::new(),
+            tags: HashMap::new(),
+            reader,
+            empty: BitSet::new(),
+        };
+
+        let tagged = (
+            tags.fetched_entities().with_disabled(),
+            tags.with_disabled(),
+        );
+        for (entity, tag) in tagged.join() {
+            index.link(entity.id(), &tag.0);
+        }
+
+        index
+    }
+
+    /// Returns the ids of all entities tagged with `tag`.
+    pub fn entities_with_tag(&self, tag: &T) -> &BitSet {
+        self.tagged.get(tag).unwrap_or(&self.empty)
+    }
+
+    /// Reads the pending events of the `Tag<T>` storage and updates the index
+    /// accordingly.
+    pub fn maintain(&mut self, tags: &ReadStorage<Tag<T>>) {
+        let mut changed = BitSet::new();
+        for event in tags.channel().read(&mut self.reader) {
+            match *event {
+                ComponentEvent::Inserted(id)
+                | ComponentEvent::Modified(id)
+                | ComponentEvent::Removed(id) => {
+                    changed.add(id);
+                }
+            }
+        }
+
+        for id in &changed {
+            self.unlink(id);
+            let entity = tags.fetched_entities().entity(id);
+            if let Some(tag) = tags.get(entity) {
+                self.link(id, &tag.0);
+            }
+        }
+    }
+
+    fn link(&mut self, id: Index, tag: &T) {
+        self.tagged.entry(tag.clone()).or_default().add(id);
+        self.tags.insert(id, tag.clone());
+    }
+
+    fn unlink(&mut self, id: Index) {
+        if let Some(tag) = self.tags.remove(&id) {
+            let now_empty = match self.tagged.get_mut(&tag) {
+                Some(tagged) => {
+                    tagged.remove(id);
+                    tagged.is_empty()
+                }
+                None => false,
+            };
+            if now_empty {
+                self.tagged.remove(&tag);
+            }
+        }
+    }
+}
+
+/// The type of a registered index and the function maintaining it.
+type IndexMaintenance = (TypeId, fn(&World));
+
+/// Resource holding the maintenance functions of the registered indexes,
+/// inserted by `WorldExt::register_names` and `WorldExt::register_tags`.
+#[derive(Default)]
+pub(crate) struct IndexRegistry {
+    indexes: Vec<IndexMaintenance>,
+}
+
+impl IndexRegistry {
+    pub(crate) fn register<I: 'static>(&mut self, maintain: fn(&World)) {
+        if self
+            .indexes
+            .iter()
+            .all(|&(index, _)| index != TypeId::of::<I>())
+        {
+            self.indexes.push((TypeId::of::<I>(), maintain));
+        }
+    }
+}
+
+pub(crate) fn maintain_names(world: &World) {
+    world
+        .fetch_mut::<NameIndex>()
+        .maintain(&world.read_storage::<Name>());
+}
+
+pub(crate) fn maintain_tags<T>(world: &World)
+where
+    T: Clone + Eq + Hash + Send + Sync + 'static,
+{
+    world
+        .fetch_mut::<TagIndex<T>>()
+        .maintain(&world.read_storage::<Tag<T>>());
+}
+
+/// Updates all indexes registered in `world`.
+pub(crate) fn maintain(world: &World) {
+    if let Some(registry) = world.try_fetch::<IndexRegistry>() {
+        for &(_, maintain) in &registry.indexes {
+            maintain(world);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::world::Builder;
+
+    #[test]
+    fn lookup_names() {
+        let mut world = World::new();
+        world.register_names();
+
+        let player = world.create_entity().with(Name::new("player")).build();
+        let camera = world.create_entity().with(Name::new("camera")).build();
+        world.maintain();
+
+        {
+            let index = world.read_resource::<NameIndex>();
+            assert_eq!(index.lookup("player"), Some(player));
+            assert_eq!(index.lookup("camera"), Some(camera));
+            assert_eq!(index.lookup("enemy"), None);
+        }
+
+        world
+            .write_storage::<Name>()
+            .insert(camera, Name::new("main camera"))
+            .unwrap();
+        world.delete_entity(player).unwrap();
+        world.maintain();
+
+        let index = world.read_resource::<NameIndex>();
+        assert_eq!(index.lookup("player"), None);
+        assert_eq!(index.lookup("camera"), None);
+        assert_eq!(index.lookup("main camera"), Some(camera));
+    }
+
+    #[test]
+    fn duplicate_names() {
+        let mut world = World::new();
+        world.register_names();
+
+        let a = world.create_entity().with(Name::new("enemy")).build();
+        let b = world.create_entity().with(Name::new("enemy")).build();
+        world.maintain();
+        assert_eq!(world.read_resource::<NameIndex>().lookup("enemy"), Some(b));
+
+        // The other holder of the name can still be found.
+        world.delete_entity(b).unwrap();
+        world.maintain();
+        assert_eq!(world.read_resource::<NameIndex>().lookup("enemy"), Some(a));
+
+        world.write_storage::<Name>().remove(a);
+        world.maintain();
+        assert_eq!(world.read_resource::<NameIndex>().lookup("enemy"), None);
+    }
+
+    #[test]
+    fn indexes_existing_components() {
+        let mut world = World::new();
+        world.register::<Name>();
+        world.register::<Tag>();
+
+        let player = world
+            .create_entity()
+            .with(Name::new("player"))
+            .with(Tag("hero".to_owned()))
+            .build();
+        world.register_names();
+        world.register_tags::<String>();
+
+        assert_eq!(
+            world.read_resource::<NameIndex>().lookup("player"),
+            Some(player)
+        );
+        assert!(
+            world
+                .read_resource::<TagIndex>()
+                .entities_with_tag(&"hero".to_owned())
+                .contains(player.id())
+        );
+    }
+
+    #[test]
+    fn entities_with_tag() {
+        let mut world = World::new();
+        world.register_tags::<String>();
+
+        let tag = |s: &str| Tag(s.to_owned());
+        let a = world.create_entity().with(tag("enemy")).build();
+        let b = world.create_entity().with(tag("enemy")).build();
+        let c = world.create_entity().with(tag("ally")).build();
+        world.maintain();
+
+        {
+            let index = world.read_resource::<TagIndex>();
+            let enemies: Vec<_> = index
+                .entities_with_tag(&"enemy".to_owned())
+                .iter()
+                .collect();
+            assert_eq!(enemies, vec![a.id(), b.id()]);
+            assert!(index.entities_with_tag(&"none".to_owned()).is_empty());
+        }
+
+        world.write_storage().insert(a, tag("ally")).unwrap();
+        world.write_storage::<Tag>().remove(b);
+        world.maintain();
+
+        let index = world.read_resource::<TagIndex>();
+        assert!(index.entities_with_tag(&"enemy".to_owned()).is_empty());
+        let allies: Vec<_> = index.entities_with_tag(&"ally".to_owned()).iter().collect();
+        assert_eq!(allies, vec![a.id(), c.id()]);
+    }
+}
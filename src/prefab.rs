@@ -0,0 +1,654 @@
+//! Entity templates which are deserialized with serde and instantiated any
+//! number of times.
+//!
+//! A [`Prefab`] is a named tree of entities, each with a set of components
+//! and optionally a local `id`. Components are converted with
+//! `ConvertSaveload` using [`PrefabRef`] as marker, so an `Entity` field of a
+//! component is written as the `id` of another entity of the same prefab
+//! and is resolved to the corresponding new entity on instantiation.
+//! Children are linked to their parent with the `hierarchy::Parent`
+//! component, which therefore has to be registered for prefabs with
+//! children.
+//!
+//! `Prefab::instantiate` builds the entities directly in a `World`, while
+//! `Prefab::instantiate_lazy` creates them atomically through `Entities`
+//! and inserts the components through `LazyUpdate`, so it can be used from
+//! within a system.
+//!
+//! ## Examples
+//!
+//! ```
+//! use serde::{Deserialize, Serialize};
+//! use specs::{hierarchy::Parent, prefab::Prefab, prelude::*};
+//! use std::convert::Infallible;
+//!
+//! #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+//! struct Health(u32);
+//!
+//! impl Component for Health {
+//!     type Storage = VecStorage<Self>;
+//! }
+//!
+//! let mut world = World::new();
+//! world.register::<Health>();
+//! world.register::<Parent>();
+//!
+//! let json = r#"{
+//!     "name": "squad",
+//!     "root": {
+//!         "components": [100],
+//!         "children": [
+//!             { "components": [10] },
+//!             { "components": [20] }
+//!         ]
+//!     }
+//! }"#;
+//! let prefab: Prefab<(Option<Health>,)> = serde_json::from_str(json).unwrap();
+//!
+//! let squads = prefab
+//!     .instantiate::<Infallible, (Health,)>(&mut world, 3)
+//!     .unwrap();
+//! assert_eq!(squads.len(), 3);
+//! assert_eq!(world.read_storage::<Health>().count(), 9);
+//! assert_eq!(world.read_storage::<Parent>().count(), 6);
+//! ```
+//!
+//! [`Prefab`]: struct.Prefab.html
+//! [`PrefabRef`]: type.PrefabRef.html
+
+use std::collections::HashMap;
+
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::{
+    hierarchy::Parent,
+    saveload::{ConvertSaveload, Marker, SimpleMarker},
+    world::{Component, EntitiesRes, Entity, LazyUpdate, World, WorldExt},
+};
+
+/// Tag type of the markers used for entity references within prefabs.
+pub enum PrefabTag {}
+
+/// The marker used for entity references within a prefab; its id is the
+/// `id` of the referenced `PrefabEntity`.
+pub type PrefabRef = SimpleMarker<PrefabTag>;
+
+/// A named template of entities.
+///
+/// `D` is the component data of a single entity, usually the
+/// `PrefabComponents::Data` of a tuple of component types.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Prefab<D> {
+    /// The name of this prefab.
+    pub name: String,
+    /// The root entity of the template.
+    pub root: PrefabEntity<D>,
+}
+
+/// A single entity of a `Prefab`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PrefabEntity<D> {
+    /// The id other entities of the prefab use to reference this one.
+    #[serde(default)]
+    pub id: Option<u64>,
+    /// The components of this entity.
+    pub components: D,
+    /// Child entities, which get a `Parent` component pointing to this
+    /// entity.
+    #[serde(default = "Vec::new")]
+    pub children: Vec<PrefabEntity<D>>,
+}
+
+impl<D> Prefab<D> {
+    /// Instantiates this prefab `count` times, returning the root entities.
+    ///
+    /// If converting a component fails, all entities created by this call
+    /// are deleted again before the error is returned.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a component references an id which isn't defined in this
+    /// prefab, if one of the components isn't registered or if the prefab
+    /// has children and `Parent` isn't registered.
+    pub fn instantiate<E, C>(&self, world: &mut World, count: usize) -> Result<Vec<Entity>, E>
+    where
+        C: PrefabComponents<E, Data = D>,
+    {
+        let result = {
+            let world = &*world;
+
+            self.instantiate_with(
+                count,
+                || world.entities_mut().alloc.allocate(),
+                |entity, parent| {
+                    world
+                        .write_storage::<Parent>()
+                        .insert(entity, Parent(parent))
+                        .expect("Prefab entity was just created");
+                },
+                |entity, data, ids| C::insert(world, entity, data, ids),
+            )
+        };
+
+        result.map_err(|(e, created)| {
+            world
+                .delete_entities(&created)
+                .expect("Prefab entities were just created");
+
+            e
+        })
+    }
+
+    /// Instantiates this prefab `count` times, creating the entities
+    /// atomically and inserting the components through `lazy`. Returns the
+    /// root entities.
+    ///
+    /// The components are added on the next `World::maintain`. If
+    /// converting a component fails, all entities created by this call are
+    /// deleted on the next `World::maintain` as well, after the components
+    /// which were already queued are inserted.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a component references an id which isn't defined in this
+    /// prefab. Unregistered components make the `LazyUpdate` panic on
+    /// `World::maintain`.
+    pub fn instantiate_lazy<E, C>(
+        &self,
+        entities: &EntitiesRes,
+        lazy: &LazyUpdate,
+        count: usize,
+    ) -> Result<Vec<Entity>, E>
+    where
+        C: PrefabComponents<E, Data = D>,
+    {
+        self.instantiate_with(
+            count,
+            || entities.create(),
+            |entity, parent| lazy.insert(entity, Parent(parent)),
+            |entity, data, ids| C::insert_lazy(lazy, entity, data, ids),
+        )
+        .map_err(|(e, created)| {
+            lazy.exec_mut(move |world| {
+                if world.delete_entities(&created).is_err() {
+                    log::warn!("Failed to delete the entities of a failed prefab instantiation.");
+                }
+            });
+
+            e
+        })
+    }
+
+    fn instantiate_with<E, A, P, I>(
+        &self,
+        count: usize,
+        mut create: A,
+        mut set_parent: P,
+        mut insert: I,
+    ) -> Result<Vec<Entity>, (E, Vec<Entity>)>
+    where
+        A: FnMut() -> Entity,
+        P: FnMut(Entity, Entity),
+        I: FnMut(Entity, &D, &mut dyn FnMut(PrefabRef) -> Option<Entity>) -> Result<(), E>,
+    {
+        // The entities of the template in pre-order, with the position of
+        // their parent.
+        let mut nodes = vec![];
+        flatten(&self.root, None, &mut nodes);
+
+        // All entities created so far, which are returned on failure so the
+        // caller can delete them.
+        let mut all = Vec::with_capacity(count * nodes.len());
+        let mut roots = Vec::with_capacity(count);
+        for _ in 0..count {
+            let created: Vec<Entity> = nodes.iter().map(|_| create()).collect();
+            all.extend_from_slice(&created);
+            let local: HashMap<u64, Entity> = nodes
+                .iter()
+                .zip(&created)
+                .filter_map(|(&(node, _), &entity)| node.id.map(|id| (id, entity)))
+                .collect();
+
+            for (&(node, parent), &entity) in nodes.iter().zip(&created) {
+                if let Some(parent) = parent {
+                    set_parent(entity, created[parent]);
+                }
+                let inserted = insert(entity, &node.components, &mut |marker: PrefabRef| {
+                    local.get(&marker.id()).cloned()
+                });
+                if let Err(e) = inserted {
+                    return Err((e, all));
+                }
+            }
+
+            roots.push(created[0]);
+        }
+
+        Ok(roots)
+    }
+}
+
+fn flatten<'a, D>(
+    node: &'a PrefabEntity<D>,
+    parent: Option<usize>,
+    nodes: &mut Vec<(&'a PrefabEntity<D>, Option<usize>)>,
+) {
+    let position = nodes.len();
+    nodes.push((node, parent));
+    for child in &node.children {
+        flatten(child, Some(position), nodes);
+    }
+}
+
+/// A collection of prefabs, accessible by name.
+#[derive(Debug)]
+pub struct Prefabs<D> {
+    prefabs: HashMap<String, Prefab<D>>,
+}
+
+impl<D> Default for Prefabs<D> {
+    fn default() -> Self {
+        Prefabs {
+            prefabs: HashMap::new(),
+        }
+    }
+}
+
+impl<D> Prefabs<D> {
+    /// Creates an empty collection.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Adds a prefab, returning the prefab previously stored under the same
+    /// name.
+    pub fn insert(&mut self, prefab: Prefab<D>) -> Option<Prefab<D>> {
+        self.prefabs.insert(prefab.name.clone(), prefab)
+    }
+
+    /// Returns the prefab with the given name.
+    pub fn get(&self, name: &str) -> Option<&Prefab<D>> {
+        self.prefabs.get(name)
+    }
+
+    /// Removes the prefab with the given name.
+    pub fn remove(&mut self, name: &str) -> Option<Prefab<D>> {
+        self.prefabs.remove(name)
+    }
+}
+
+/// Components which can be inserted through `LazyUpdate`, i.e. which are
+/// `Send + Sync` if the `parallel` feature is enabled.
+#[cfg(feature = "parallel")]
+pub trait PrefabComponent: Component + Send + Sync {}
+
+#[cfg(feature = "parallel")]
+impl<T> PrefabComponent for T where T: Component + Send + Sync {}
+
+/// Components which can be inserted through `LazyUpdate`, i.e. which are
+/// `Send + Sync` if the `parallel` feature is enabled.
+#[cfg(not(feature = "parallel"))]
+pub trait PrefabComponent: Component {}
+
+#[cfg(not(feature = "parallel"))]
+impl<T> PrefabComponent for T where T: Component {}
+
+/// A group of component types which can be part of a prefab.
+///
+/// This is implemented for tuples of up to 16 components. Every component
+/// is optional in the data of an entity.
+pub trait PrefabComponents<E> {
+    /// The serialized components of a single entity.
+    type Data: Serialize + DeserializeOwned;
+
+    /// Inserts the components in `data` into `entity`.
+    fn insert<F>(world: &World, entity: Entity, data: &Self::Data, ids: F) -> Result<(), E>
+    where
+        F: FnMut(PrefabRef) -> Option<Entity>;
+
+    /// Inserts the components in `data` into `entity` through `lazy`.
+    fn insert_lazy<F>(lazy: &LazyUpdate, entity: Entity, data: &Self::Data, ids: F) -> Result<(), E>
+    where
+        F: FnMut(PrefabRef) -> Option<Entity>;
+}
+
+macro_rules! prefab_components {
+    ($($comp:ident,)*) => {
+        impl<E, $($comp,)*> PrefabComponents<E> for ($($comp,)*)
+        where
+            $(
+                $comp: ConvertSaveload<PrefabRef> + PrefabComponent,
+                <$comp as ConvertSaveload<PrefabRef>>::Data: Clone,
+                E: From<<$comp as ConvertSaveload<PrefabRef>>::Error>,
+            )*
+        {
+            type Data = ($(Option<<$comp as ConvertSaveload<PrefabRef>>::Data>,)*);
+
+            #[allow(unused)]
+            fn insert<F>(
+                world: &World,
+                entity: Entity,
+                data: &Self::Data,
+                mut ids: F,
+            ) -> Result<(), E>
+            where
+                F: FnMut(PrefabRef) -> Option<Entity>,
+            {
+                #[allow(bad_style)]
+                let ($(ref $comp,)*) = *data;
+                $(
+                    if let Some(ref data) = *$comp {
+                        let component =
+                            <$comp as ConvertSaveload<PrefabRef>>::convert_from(data.clone(), &mut ids)?;
+                        world
+                            .write_storage::<$comp>()
+                            .insert(entity, component)
+                            .expect("Prefab entity was just created");
+                    }
+                )*
+
+                Ok(())
+            }
+
+            #[allow(unused)]
+            fn insert_lazy<F>(
+                lazy: &LazyUpdate,
+                entity: Entity,
+                data: &Self::Data,
+                mut ids: F,
+            ) -> Result<(), E>
+            where
+                F: FnMut(PrefabRef) -> Option<Entity>,
+            {
+                #[allow(bad_style)]
+                let ($(ref $comp,)*) = *data;
+                $(
+                    if let Some(ref data) = *$comp {
+                        let component =
+                            <$comp as ConvertSaveload<PrefabRef>>::convert_from(data.clone(), &mut ids)?;
+                        lazy.insert(entity, component);
+                    }
+                )*
+
+                Ok(())
+            }
+        }
+
+        prefab_components!(@pop $($comp,)*);
+    };
+    (@pop) => {};
+    (@pop $head:ident, $($tail:ident,)*) => {
+        prefab_components!($($tail,)*);
+    };
+}
+
+prefab_components!(CA, CB, CC, CD, CE, CF, CG, CH, CI, CJ, CK, CL, CM, CN, CO, CP,);
+
+#[cfg(test)]
+mod tests {
+    use std::convert::Infallible;
+
+    use super::*;
+    use crate::{
+        join::Join,
+        saveload::ConvertSaveload,
+        storage::{DenseVecStorage, VecStorage},
+        world::Builder,
+    };
+
+    #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+    struct Name(String);
+
+    impl Component for Name {
+        type Storage = VecStorage<Self>;
+    }
+
+    /// Points to another entity of the prefab.
+    #[derive(Clone, Debug, PartialEq)]
+    struct Follow(Entity);
+
+    impl Component for Follow {
+        type Storage = DenseVecStorage<Self>;
+    }
+
+    impl<M> ConvertSaveload<M> for Follow
+    where
+        M: Serialize + DeserializeOwned,
+    {
+        type Data = M;
+        type Error = Infallible;
+
+        fn convert_into<F>(&self, ids: F) -> Result<M, Infallible>
+        where
+            F: FnMut(Entity) -> Option<M>,
+        {
+            <Entity as ConvertSaveload<M>>::convert_into(&self.0, ids)
+        }
+
+        fn convert_from<F>(data: M, ids: F) -> Result<Self, Infallible>
+        where
+            F: FnMut(M) -> Option<Entity>,
+        {
+            <Entity as ConvertSaveload<M>>::convert_from(data, ids).map(Follow)
+        }
+    }
+
+    type Comps = (Name, Follow);
+    type Data = <Comps as PrefabComponents<Infallible>>::Data;
+
+    fn create_world() -> World {
+        let mut world = World::new();
+        world.register::<Name>();
+        world.register::<Follow>();
+        world.register::<Parent>();
+
+        world
+    }
+
+    fn prefab() -> Prefab<Data> {
+        let json = r#"{
+            "name": "convoy",
+            "root": {
+                "id": 1,
+                "components": ["leader", null],
+                "children": [
+                    {
+                        "id": 2,
+                        "components": ["first", [1]]
+                    },
+                    {
+                        "components": ["second", [2]],
+                        "children": [{ "components": ["scout", null] }]
+                    }
+                ]
+            }
+        }"#;
+
+        serde_json::from_str(json).unwrap()
+    }
+
+    fn check(world: &World, root: Entity) {
+        let entities = world.entities();
+        let names = world.read_storage::<Name>();
+        let follows = world.read_storage::<Follow>();
+        let parents = world.read_storage::<Parent>();
+
+        let find = |name: &str| {
+            (&entities, &names)
+                .join()
+                .find(|(e, n)| n.0 == name && is_descendant(&parents, *e, root))
+                .map(|(e, _)| e)
+                .unwrap()
+        };
+        let leader = find("leader");
+        let first = find("first");
+        let second = find("second");
+        let scout = find("scout");
+
+        assert_eq!(leader, root);
+        assert_eq!(follows.get(first), Some(&Follow(leader)));
+        assert_eq!(follows.get(second), Some(&Follow(first)));
+        assert_eq!(follows.get(scout), None);
+        assert_eq!(parents.get(scout), Some(&Parent(second)));
+        assert_eq!(parents.get(first), Some(&Parent(leader)));
+    }
+
+    fn is_descendant(
+        parents: &crate::storage::ReadStorage<Parent>,
+        mut entity: Entity,
+        root: Entity,
+    ) -> bool {
+        loop {
+            if entity == root {
+                return true;
+            }
+            match parents.get(entity) {
+                Some(parent) => entity = parent.0,
+                None => return false,
+            }
+        }
+    }
+
+    #[test]
+    fn instantiate() {
+        let mut world = create_world();
+        world.create_entity().with(Name("other".into())).build();
+
+        let roots = prefab()
+            .instantiate::<Infallible, Comps>(&mut world, 2)
+            .unwrap();
+
+        assert_eq!(roots.len(), 2);
+        assert_ne!(roots[0], roots[1]);
+        assert_eq!(world.read_storage::<Name>().count(), 9);
+        for &root in &roots {
+            check(&world, root);
+        }
+    }
+
+    #[test]
+    fn instantiate_lazy() {
+        let mut world = create_world();
+
+        let roots = prefab()
+            .instantiate_lazy::<Infallible, Comps>(
+                &world.entities(),
+                &world.read_resource::<LazyUpdate>(),
+                3,
+            )
+            .unwrap();
+        assert_eq!(world.read_storage::<Name>().count(), 0);
+
+        world.maintain();
+
+        assert_eq!(world.read_storage::<Name>().count(), 12);
+        for &root in &roots {
+            check(&world, root);
+        }
+    }
+
+    /// Fails to convert once the conversions left in `BUDGET` run out.
+    #[derive(Clone, Debug, PartialEq)]
+    struct Budgeted;
+
+    thread_local! {
+        static BUDGET: std::cell::Cell<usize> = std::cell::Cell::new(0);
+    }
+
+    #[derive(Debug, PartialEq)]
+    struct OutOfBudget;
+
+    impl From<Infallible> for OutOfBudget {
+        fn from(e: Infallible) -> Self {
+            match e {}
+        }
+    }
+
+    impl Component for Budgeted {
+        type Storage = VecStorage<Self>;
+    }
+
+    impl<M> ConvertSaveload<M> for Budgeted {
+        type Data = u32;
+        type Error = OutOfBudget;
+
+        fn convert_into<F>(&self, _ids: F) -> Result<u32, OutOfBudget>
+        where
+            F: FnMut(Entity) -> Option<M>,
+        {
+            Ok(0)
+        }
+
+        fn convert_from<F>(_data: u32, _ids: F) -> Result<Self, OutOfBudget>
+        where
+            F: FnMut(M) -> Option<Entity>,
+        {
+            BUDGET.with(|budget| match budget.get() {
+                0 => Err(OutOfBudget),
+                left => {
+                    budget.set(left - 1);
+                    Ok(Budgeted)
+                }
+            })
+        }
+    }
+
+    type Failing = (Name, Budgeted);
+
+    fn failing_prefab() -> Prefab<<Failing as PrefabComponents<OutOfBudget>>::Data> {
+        let json = r#"{
+            "name": "pair",
+            "root": {
+                "components": ["root", null],
+                "children": [{ "components": ["child", 0] }]
+            }
+        }"#;
+
+        serde_json::from_str(json).unwrap()
+    }
+
+    #[test]
+    fn failed_instantiate_deletes_entities() {
+        let mut world = create_world();
+        world.register::<Budgeted>();
+        let other = world.create_entity().with(Name("other".into())).build();
+
+        // The first instance succeeds, the second fails at its child.
+        BUDGET.with(|budget| budget.set(1));
+        let result = failing_prefab().instantiate::<OutOfBudget, Failing>(&mut world, 2);
+        assert_eq!(result, Err(OutOfBudget));
+
+        let entities: Vec<_> = world.entities().join().collect();
+        assert_eq!(entities, vec![other]);
+        assert_eq!(world.read_storage::<Name>().count(), 1);
+        assert_eq!(world.read_storage::<Parent>().count(), 0);
+    }
+
+    #[test]
+    fn failed_instantiate_lazy_deletes_entities() {
+        let mut world = create_world();
+        world.register::<Budgeted>();
+
+        BUDGET.with(|budget| budget.set(1));
+        let result = failing_prefab().instantiate_lazy::<OutOfBudget, Failing>(
+            &world.entities(),
+            &world.read_resource::<LazyUpdate>(),
+            2,
+        );
+        assert_eq!(result, Err(OutOfBudget));
+
+        world.maintain();
+
+        assert_eq!(world.entities().join().count(), 0);
+        assert_eq!(world.read_storage::<Name>().count(), 0);
+        assert_eq!(world.read_storage::<Budgeted>().count(), 0);
+    }
+
+    #[test]
+    fn library() {
+        let mut prefabs = Prefabs::new();
+        assert!(prefabs.insert(prefab()).is_none());
+        assert!(prefabs.get("convoy").is_some());
+        assert!(prefabs.get("other").is_none());
+    }
+}
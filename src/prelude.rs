@@ -21,7 +21,9 @@ pub use crate::{
     changeset::ChangeSet,
     storage::{
         ComponentEvent, DefaultVecStorage, DenseVecStorage, FlaggedStorage, HashMapStorage,
-        NullStorage, ReadStorage, Storage, Tracked, VecStorage, WriteStorage,
+        NullStorage, ReadStorage, SparseSetStorage, Storage, Tracked, VecStorage, WriteStorage,
+    },
+    world::{
+        Builder, CommandBuffer, Component, Entities, Entity, EntityBuilder, LazyUpdate, WorldExt,
     },
-    world::{Builder, Component, Entities, Entity, EntityBuilder, LazyUpdate, WorldExt},
 };
@@ -0,0 +1,406 @@
+//! Entity references which are invalidated once their target is deleted.
+//!
+//! Components can point to other entities by storing an [`EntityRef`].
+//! Once such a component is registered with
+//! `WorldExt::register_entity_refs`, deleting the referenced entity
+//! (either through `WorldExt::delete_entity` or during `WorldExt::maintain`)
+//! applies the [`RefPolicy`] of the component to every component pointing
+//! to it:
+//!
+//! * `RefPolicy::Nullify` clears the dangling `EntityRef`s,
+//! * `RefPolicy::Remove` removes the referencing component and
+//! * `RefPolicy::Cascade` deletes the referencing entity as well.
+//!
+//! ## Examples
+//!
+//! ```
+//! use specs::{
+//!     prelude::*,
+//!     relation::{EntityRef, EntityRefs, RefPolicy},
+//! };
+//!
+//! struct Target(EntityRef);
+//!
+//! impl Component for Target {
+//!     type Storage = DenseVecStorage<Self>;
+//! }
+//!
+//! impl EntityRefs for Target {
+//!     fn visit_refs<F: FnMut(&EntityRef)>(&self, f: F) {
+//!         self.0.visit_refs(f);
+//!     }
+//!
+//!     fn visit_refs_mut<F: FnMut(&mut EntityRef)>(&mut self, f: F) {
+//!         self.0.visit_refs_mut(f);
+//!     }
+//! }
+//!
+//! let mut world = World::new();
+//! world.register::<Target>();
+//! world.register_entity_refs::<Target>(RefPolicy::Nullify);
+//!
+//! let enemy = world.create_entity().build();
+//! let hunter = world
+//!     .create_entity()
+//!     .with(Target(EntityRef::new(enemy)))
+//!     .build();
+//!
+//! world.delete_entity(enemy).unwrap();
+//!
+//! let targets = world.read_storage::<Target>();
+//! assert_eq!(targets.get(hunter).unwrap().0.get(), None);
+//! ```
+//!
+//! [`EntityRef`]: struct.EntityRef.html
+//! [`RefPolicy`]: enum.RefPolicy.html
+
+use std::any::TypeId;
+
+use crate::{
+    join::Join,
+    world::{Component, Entity, World, WorldExt},
+};
+
+/// A reference to an entity, which may be cleared once the entity is
+/// deleted.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub struct EntityRef(Option<Entity>);
+
+impl EntityRef {
+    /// Creates a reference to `entity`.
+    pub fn new(entity: Entity) -> Self {
+        EntityRef(Some(entity))
+    }
+
+    /// Creates a reference which doesn't point to any entity.
+    pub fn null() -> Self {
+        EntityRef(None)
+    }
+
+    /// Returns the referenced entity, unless the reference was cleared.
+    pub fn get(&self) -> Option<Entity> {
+        self.0
+    }
+
+    /// Returns `true` if this reference doesn't point to any entity.
+    pub fn is_null(&self) -> bool {
+        self.0.is_none()
+    }
+
+    /// Points this reference to `entity`.
+    pub fn set(&mut self, entity: Entity) {
+        self.0 = Some(entity);
+    }
+
+    /// Clears this reference.
+    pub fn clear(&mut self) {
+        self.0 = None;
+    }
+}
+
+impl From<Entity> for EntityRef {
+    fn from(entity: Entity) -> Self {
+        EntityRef::new(entity)
+    }
+}
+
+/// Types containing `EntityRef`s, most notably components which are
+/// registered with `WorldExt::register_entity_refs`.
+pub trait EntityRefs {
+    /// Calls `f` with every entity reference of `self`.
+    fn visit_refs<F: FnMut(&EntityRef)>(&self, f: F);
+
+    /// Calls `f` with a mutable borrow of every entity reference of `self`.
+    fn visit_refs_mut<F: FnMut(&mut EntityRef)>(&mut self, f: F);
+}
+
+impl EntityRefs for EntityRef {
+    fn visit_refs<F: FnMut(&EntityRef)>(&self, mut f: F) {
+        f(self);
+    }
+
+    fn visit_refs_mut<F: FnMut(&mut EntityRef)>(&mut self, mut f: F) {
+        f(self);
+    }
+}
+
+impl<T: EntityRefs> EntityRefs for Option<T> {
+    fn visit_refs<F: FnMut(&EntityRef)>(&self, f: F) {
+        if let Some(ref refs) = *self {
+            refs.visit_refs(f);
+        }
+    }
+
+    fn visit_refs_mut<F: FnMut(&mut EntityRef)>(&mut self, f: F) {
+        if let Some(ref mut refs) = *self {
+            refs.visit_refs_mut(f);
+        }
+    }
+}
+
+impl<T: EntityRefs> EntityRefs for Vec<T> {
+    fn visit_refs<F: FnMut(&EntityRef)>(&self, mut f: F) {
+        for refs in self {
+            refs.visit_refs(&mut f);
+        }
+    }
+
+    fn visit_refs_mut<F: FnMut(&mut EntityRef)>(&mut self, mut f: F) {
+        for refs in self {
+            refs.visit_refs_mut(&mut f);
+        }
+    }
+}
+
+/// What happens to a component once an entity it references is deleted.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum RefPolicy {
+    /// Clear the dangling references, keeping the component.
+    Nullify,
+    /// Remove the component from its entity.
+    Remove,
+    /// Delete the entity owning the component.
+    Cascade,
+}
+
+struct Registration {
+    component: TypeId,
+    policy: RefPolicy,
+    invalidate: fn(&World, RefPolicy) -> Vec<Entity>,
+}
+
+/// Resource keeping track of the components containing entity references,
+/// inserted by `WorldExt::register_entity_refs`.
+#[derive(Default)]
+pub struct RefRegistry {
+    registrations: Vec<Registration>,
+}
+
+impl RefRegistry {
+    /// Returns the policy of the component `T`, if it was registered.
+    pub fn policy<T: 'static>(&self) -> Option<RefPolicy> {
+        self.registrations
+            .iter()
+            .find(|r| r.component == TypeId::of::<T>())
+            .map(|r| r.policy)
+    }
+
+    pub(crate) fn register<T>(&mut self, policy: RefPolicy)
+    where
+        T: Component + EntityRefs,
+    {
+        let registration = Registration {
+            component: TypeId::of::<T>(),
+            policy,
+            invalidate: invalidate::<T>,
+        };
+
+        match self
+            .registrations
+            .iter_mut()
+            .find(|r| r.component == registration.component)
+        {
+            Some(r) => *r = registration,
+            None => self.registrations.push(registration),
+        }
+    }
+}
+
+/// Applies the policies of all registered components which reference dead
+/// entities, returning the alive entities which have to be deleted because
+/// of `RefPolicy::Cascade`.
+pub(crate) fn invalidate_all(world: &World) -> Vec<Entity> {
+    let registry = match world.try_fetch::<RefRegistry>() {
+        Some(registry) => registry,
+        None => return vec![],
+    };
+
+    let mut cascade = vec![];
+    for registration in &registry.registrations {
+        cascade.extend((registration.invalidate)(world, registration.policy));
+    }
+    cascade.sort();
+    cascade.dedup();
+
+    cascade
+}
+
+fn invalidate<T>(world: &World, policy: RefPolicy) -> Vec<Entity>
+where
+    T: Component + EntityRefs,
+{
+    let entities = world.entities();
+    let mut storage = world.write_storage::<T>();
+
+    let dangling: Vec<Entity> = (entities.with_disabled(), storage.with_disabled())
+        .join()
+        .filter(|(_, component)| {
+            let mut dangling = false;
+            component.visit_refs(|r| {
+                dangling |= r.get().is_some_and(|e| !entities.is_alive(e));
+            });
+            dangling
+        })
+        .map(|(entity, _)| entity)
+        .collect();
+
+    match policy {
+        RefPolicy::Nullify => {
+            for &entity in &dangling {
+                if let Some(component) = storage.get_mut(entity) {
+                    component.visit_refs_mut(|r| {
+                        if r.get().is_some_and(|e| !entities.is_alive(e)) {
+                            r.clear();
+                        }
+                    });
+                }
+            }
+
+            vec![]
+        }
+        RefPolicy::Remove => {
+            for &entity in &dangling {
+                storage.remove(entity);
+            }
+
+            vec![]
+        }
+        RefPolicy::Cascade => dangling,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        hierarchy::{Hierarchy, Parent},
+        storage::DenseVecStorage,
+        world::Builder,
+    };
+
+    #[derive(Debug, PartialEq)]
+    struct Follow(EntityRef);
+
+    impl Component for Follow {
+        type Storage = DenseVecStorage<Self>;
+    }
+
+    impl EntityRefs for Follow {
+        fn visit_refs<F: FnMut(&EntityRef)>(&self, f: F) {
+            self.0.visit_refs(f);
+        }
+
+        fn visit_refs_mut<F: FnMut(&mut EntityRef)>(&mut self, f: F) {
+            self.0.visit_refs_mut(f);
+        }
+    }
+
+    struct Links(Vec<EntityRef>);
+
+    impl Component for Links {
+        type Storage = DenseVecStorage<Self>;
+    }
+
+    impl EntityRefs for Links {
+        fn visit_refs<F: FnMut(&EntityRef)>(&self, f: F) {
+            self.0.visit_refs(f);
+        }
+
+        fn visit_refs_mut<F: FnMut(&mut EntityRef)>(&mut self, f: F) {
+            self.0.visit_refs_mut(f);
+        }
+    }
+
+    fn create_world(policy: RefPolicy) -> World {
+        let mut world = World::new();
+        world.register::<Follow>();
+        world.register::<Links>();
+        world.register_entity_refs::<Follow>(policy);
+        world.register_entity_refs::<Links>(RefPolicy::Nullify);
+
+        world
+    }
+
+    fn follower(world: &mut World, target: Entity) -> Entity {
+        world
+            .create_entity()
+            .with(Follow(EntityRef::new(target)))
+            .build()
+    }
+
+    #[test]
+    fn nullify() {
+        let mut world = create_world(RefPolicy::Nullify);
+        let a = world.create_entity().build();
+        let b = world.create_entity().build();
+        let c = follower(&mut world, a);
+        let d = world
+            .create_entity()
+            .with(Links(vec![a.into(), b.into()]))
+            .build();
+
+        world.entities().delete(a).unwrap();
+        world.maintain();
+
+        assert_eq!(
+            world.read_storage::<Follow>().get(c),
+            Some(&Follow(EntityRef::null()))
+        );
+        let links = world.read_storage::<Links>();
+        assert_eq!(links.get(d).unwrap().0, vec![EntityRef::null(), b.into()]);
+    }
+
+    #[test]
+    fn remove() {
+        let mut world = create_world(RefPolicy::Remove);
+        let a = world.create_entity().build();
+        let b = follower(&mut world, a);
+
+        world.delete_entity(a).unwrap();
+
+        assert!(world.is_alive(b));
+        assert_eq!(world.read_storage::<Follow>().get(b), None);
+    }
+
+    #[test]
+    fn cascade() {
+        let mut world = create_world(RefPolicy::Cascade);
+        let a = world.create_entity().build();
+        let b = follower(&mut world, a);
+        let c = follower(&mut world, b);
+        let d = world.create_entity().build();
+        let e = follower(&mut world, d);
+
+        world.delete_entity(a).unwrap();
+
+        assert!(!world.is_alive(b));
+        assert!(!world.is_alive(c));
+        assert!(world.is_alive(e));
+        assert_eq!(world.read_storage::<Follow>().count(), 1);
+
+        world.entities().delete(d).unwrap();
+        world.maintain();
+
+        assert!(!world.is_alive(e));
+        assert_eq!(world.read_storage::<Follow>().count(), 0);
+    }
+
+    #[test]
+    fn cascade_with_hierarchy() {
+        let mut world = create_world(RefPolicy::Cascade);
+        world.register::<Parent>();
+        let hierarchy = Hierarchy::new(&mut world.write_storage());
+        world.insert(hierarchy);
+
+        let a = world.create_entity().build();
+        let b = follower(&mut world, a);
+        let child = world.create_entity().with(Parent(b)).build();
+        let c = follower(&mut world, child);
+
+        world.delete_entity(a).unwrap();
+
+        assert!(!world.is_alive(b));
+        assert!(!world.is_alive(child));
+        assert!(!world.is_alive(c));
+    }
+}
@@ -0,0 +1,533 @@
+//! Deterministic world diffs for network replication.
+//!
+//! A [`DeltaTracker`] remembers which marked entities have already been
+//! replicated and reads the `ComponentEvent`s of a group of tracked
+//! components (for example `FlaggedStorage`s). Calling `DeltaTracker::delta`
+//! yields a serializable [`WorldDelta`] containing the entities that were
+//! created, deleted or modified since the previous call, which is applied to
+//! another `World` with `WorldDelta::apply`.
+//!
+//! Entities are identified by their `Marker`, just like for
+//! `SerializeComponents` and `DeserializeComponents`, and components are
+//! converted with `ConvertSaveload`, so components pointing to other
+//! entities are replicated correctly.
+//!
+//! ## Examples
+//!
+//! ```
+//! # extern crate serde;
+//! use serde::{Deserialize, Serialize};
+//! use specs::{
+//!     prelude::*,
+//!     replication::DeltaTracker,
+//!     saveload::{MarkedBuilder, SimpleMarker, SimpleMarkerAllocator},
+//! };
+//! use std::convert::Infallible;
+//!
+//! #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+//! struct Pos(i32);
+//!
+//! impl Component for Pos {
+//!     type Storage = FlaggedStorage<Self>;
+//! }
+//!
+//! struct Net;
+//!
+//! fn create_world() -> World {
+//!     let mut world = World::new();
+//!     world.register::<Pos>();
+//!     world.register::<SimpleMarker<Net>>();
+//!     world.insert(SimpleMarkerAllocator::<Net>::new());
+//!     world
+//! }
+//!
+//! let mut server = create_world();
+//! let mut client = create_world();
+//! let mut tracker = DeltaTracker::<SimpleMarker<Net>, (Pos,)>::new(&server);
+//!
+//! server
+//!     .create_entity()
+//!     .with(Pos(3))
+//!     .marked::<SimpleMarker<Net>>()
+//!     .build();
+//!
+//! let delta = tracker.delta::<Infallible>(&server).unwrap();
+//! delta.apply::<Infallible, (Pos,)>(&client).unwrap();
+//! client.maintain();
+//!
+//! let positions: Vec<Pos> = client.read_storage::<Pos>().join().cloned().collect();
+//! assert_eq!(positions, vec![Pos(3)]);
+//! ```
+//!
+//! [`DeltaTracker`]: struct.DeltaTracker.html
+//! [`WorldDelta`]: struct.WorldDelta.html
+
+use std::{collections::BTreeMap, marker::PhantomData};
+
+use hibitset::BitSet;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use shrev::ReaderId;
+
+use crate::{
+    join::Join,
+    saveload::{ConvertSaveload, EntityData, Marker, MarkerAllocator},
+    storage::{ComponentEvent, ReadStorage, Tracked},
+    world::{Component, Entity, Index, World, WorldExt},
+};
+
+/// The change of a single component of an entity.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum ComponentDelta<D> {
+    /// The component was inserted or modified and now has this value.
+    Set(D),
+    /// The component was removed.
+    Removed,
+}
+
+/// The changes between two states of a `World`, created by
+/// `DeltaTracker::delta`.
+///
+/// All entities are listed in the order of their ids, so the same changes
+/// always result in the same delta.
+#[derive(Serialize, Deserialize)]
+pub struct WorldDelta<M, D> {
+    /// Entities which were marked since the last delta, with all of their
+    /// components.
+    pub created: Vec<EntityData<M, D>>,
+    /// Entities which were already replicated and had some of their
+    /// components changed. Unchanged components are `None`.
+    pub modified: Vec<EntityData<M, D>>,
+    /// Markers of entities which were deleted or lost their marker.
+    pub deleted: Vec<M>,
+}
+
+impl<M, D> WorldDelta<M, D> {
+    /// Returns `true` if there are no changes in this delta.
+    pub fn is_empty(&self) -> bool {
+        self.created.is_empty() && self.modified.is_empty() && self.deleted.is_empty()
+    }
+}
+
+impl<M, D> WorldDelta<M, D>
+where
+    M: Marker,
+{
+    /// Applies the changes to `world`, mapping markers to local entities
+    /// with `MarkerAllocator::retrieve_entity`.
+    ///
+    /// Entities are created and deleted atomically, so `World::maintain`
+    /// should be called afterwards.
+    ///
+    /// # Panics
+    ///
+    /// Panics if one of the components or the marker storage is already
+    /// borrowed, or if the marker storage is part of `C`.
+    pub fn apply<E, C>(self, world: &World) -> Result<(), E>
+    where
+        C: DeltaComponents<E, M, Data = D>,
+    {
+        let entities = world.entities();
+        let mut markers = world.write_storage::<M>();
+        let mut allocator = world.write_resource::<M::Allocator>();
+
+        for marker in self.deleted {
+            if let Some(entity) = allocator.retrieve_entity_internal(marker.id()) {
+                // The entity might already be gone, which is fine.
+                let _ = entities.delete(entity);
+            }
+        }
+
+        for data in self.created.into_iter().chain(self.modified) {
+            let entity = allocator.retrieve_entity(data.marker, &mut markers, &entities);
+            let ids = |marker: M| Some(allocator.retrieve_entity(marker, &mut markers, &entities));
+            C::patch_entity(world, entity, data.components, ids)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Keeps track of the replicated state of a `World` in order to compute
+/// `WorldDelta`s.
+///
+/// `M` is the marker identifying replicated entities and `C` is a tuple of
+/// the replicated component types, which need tracked storages.
+pub struct DeltaTracker<M, C> {
+    readers: Vec<ReaderId<ComponentEvent>>,
+    replicated: BTreeMap<Index, (Entity, M)>,
+    phantom: PhantomData<C>,
+}
+
+impl<M, C> DeltaTracker<M, C>
+where
+    M: Marker,
+    C: TrackComponents,
+{
+    /// Creates a new tracker, registering event readers for all component
+    /// storages of `C`.
+    ///
+    /// The first delta contains all marked entities.
+    pub fn new(world: &World) -> Self {
+        DeltaTracker {
+            readers: C::register_readers(world),
+            replicated: BTreeMap::new(),
+            phantom: PhantomData,
+        }
+    }
+
+    /// Computes the changes since the last call and marks them as
+    /// replicated.
+    pub fn delta<E>(&mut self, world: &World) -> Result<WorldDelta<M, C::Data>, E>
+    where
+        C: DeltaComponents<E, M>,
+    {
+        let changed = C::read_changes(world, &mut self.readers);
+        let entities = world.entities();
+        let markers = world.read_storage::<M>();
+        let ids = |entity| markers.get(entity).cloned();
+
+        let mut delta = WorldDelta {
+            created: vec![],
+            modified: vec![],
+            deleted: vec![],
+        };
+        let mut replicated = BTreeMap::new();
+        for (entity, marker) in (entities.with_disabled(), markers.with_disabled()).join() {
+            match self.replicated.remove(&entity.id()) {
+                Some((old_entity, ref old_marker))
+                    if old_entity == entity && old_marker == marker =>
+                {
+                    if changed.iter().any(|c| c.contains(entity.id())) {
+                        delta.modified.push(EntityData {
+                            marker: marker.clone(),
+                            components: C::diff_entity(world, entity, Some(&changed), &ids)?,
+                        });
+                    }
+                }
+                old => {
+                    if let Some((_, old_marker)) = old {
+                        delta.deleted.push(old_marker);
+                    }
+                    delta.created.push(EntityData {
+                        marker: marker.clone(),
+                        components: C::diff_entity(world, entity, None, &ids)?,
+                    });
+                }
+            }
+            replicated.insert(entity.id(), (entity, marker.clone()));
+        }
+
+        let deleted = std::mem::replace(&mut self.replicated, replicated);
+        delta
+            .deleted
+            .extend(deleted.into_iter().map(|(_, (_, marker))| marker));
+
+        Ok(delta)
+    }
+}
+
+/// A group of component types with tracked storages.
+///
+/// This is implemented for tuples of up to 16 components.
+pub trait TrackComponents {
+    /// Registers one event reader per component storage.
+    fn register_readers(world: &World) -> Vec<ReaderId<ComponentEvent>>;
+
+    /// Reads the pending events of each storage, returning one `BitSet` of
+    /// changed indices per component.
+    fn read_changes(world: &World, readers: &mut [ReaderId<ComponentEvent>]) -> Vec<BitSet>;
+}
+
+/// A group of component types which can be diffed and patched.
+///
+/// This is implemented for tuples of up to 16 components.
+pub trait DeltaComponents<E, M>: TrackComponents
+where
+    M: Marker,
+{
+    /// The data representation of the changes to the components of a
+    /// single entity.
+    type Data: Serialize + DeserializeOwned;
+
+    /// Returns the changes of the components of `entity`.
+    ///
+    /// If `changed` is `None` all existing components are included,
+    /// otherwise only those whose index is part of the respective
+    /// `BitSet`.
+    fn diff_entity<F>(
+        world: &World,
+        entity: Entity,
+        changed: Option<&[BitSet]>,
+        ids: F,
+    ) -> Result<Self::Data, E>
+    where
+        F: FnMut(Entity) -> Option<M>;
+
+    /// Applies the changes in `data` to the components of `entity`.
+    fn patch_entity<F>(world: &World, entity: Entity, data: Self::Data, ids: F) -> Result<(), E>
+    where
+        F: FnMut(M) -> Option<Entity>;
+}
+
+fn changed_indices<C>(storage: &ReadStorage<C>, reader: &mut ReaderId<ComponentEvent>) -> BitSet
+where
+    C: Component,
+    C::Storage: Tracked,
+{
+    let mut changed = BitSet::new();
+    for event in storage.channel().read(reader) {
+        match *event {
+            ComponentEvent::Inserted(id)
+            | ComponentEvent::Modified(id)
+            | ComponentEvent::Removed(id) => {
+                changed.add(id);
+            }
+        }
+    }
+
+    changed
+}
+
+macro_rules! delta_components {
+    ($($comp:ident,)*) => {
+        impl<$($comp,)*> TrackComponents for ($($comp,)*)
+        where
+            $(
+                $comp: Component,
+                <$comp as Component>::Storage: Tracked,
+            )*
+        {
+            #[allow(unused)]
+            fn register_readers(world: &World) -> Vec<ReaderId<ComponentEvent>> {
+                vec![$(world.write_storage::<$comp>().register_reader(),)*]
+            }
+
+            #[allow(unused)]
+            fn read_changes(
+                world: &World,
+                readers: &mut [ReaderId<ComponentEvent>],
+            ) -> Vec<BitSet> {
+                let mut readers = readers.iter_mut();
+
+                vec![$(
+                    changed_indices(
+                        &world.read_storage::<$comp>(),
+                        readers.next().expect("Missing event reader for component"),
+                    ),
+                )*]
+            }
+        }
+
+        impl<E, M, $($comp,)*> DeltaComponents<E, M> for ($($comp,)*)
+        where
+            M: Marker,
+            $(
+                $comp: ConvertSaveload<M> + Component,
+                <$comp as Component>::Storage: Tracked,
+                E: From<<$comp as ConvertSaveload<M>>::Error>,
+            )*
+        {
+            type Data = ($(Option<ComponentDelta<<$comp as ConvertSaveload<M>>::Data>>,)*);
+
+            #[allow(unused)]
+            fn diff_entity<F>(
+                world: &World,
+                entity: Entity,
+                changed: Option<&[BitSet]>,
+                mut ids: F,
+            ) -> Result<Self::Data, E>
+            where
+                F: FnMut(Entity) -> Option<M>,
+            {
+                let mut changed = changed.map(|changed| changed.iter());
+
+                Ok(($({
+                    let storage = world.read_storage::<$comp>();
+                    match changed.as_mut() {
+                        Some(changed) => {
+                            let changed = changed
+                                .next()
+                                .expect("Missing changed indices for component");
+                            if changed.contains(entity.id()) {
+                                match storage.get(entity) {
+                                    Some(c) => Some(ComponentDelta::Set(c.convert_into(&mut ids)?)),
+                                    None => Some(ComponentDelta::Removed),
+                                }
+                            } else {
+                                None
+                            }
+                        }
+                        None => match storage.get(entity) {
+                            Some(c) => Some(ComponentDelta::Set(c.convert_into(&mut ids)?)),
+                            None => None,
+                        },
+                    }
+                },)*))
+            }
+
+            #[allow(unused)]
+            fn patch_entity<F>(
+                world: &World,
+                entity: Entity,
+                data: Self::Data,
+                mut ids: F,
+            ) -> Result<(), E>
+            where
+                F: FnMut(M) -> Option<Entity>,
+            {
+                #[allow(bad_style)]
+                let ($($comp,)*) = data;
+                $(
+                    match $comp {
+                        Some(ComponentDelta::Set(data)) => {
+                            let component =
+                                <$comp as ConvertSaveload<M>>::convert_from(data, &mut ids)?;
+                            if world.write_storage::<$comp>().insert(entity, component).is_err() {
+                                log::warn!(
+                                    "Replicated component was dropped because {:?} was dead.",
+                                    entity
+                                );
+                            }
+                        }
+                        Some(ComponentDelta::Removed) => {
+                            world.write_storage::<$comp>().remove(entity);
+                        }
+                        None => {}
+                    }
+                )*
+
+                Ok(())
+            }
+        }
+
+        delta_components!(@pop $($comp,)*);
+    };
+    (@pop) => {};
+    (@pop $head:ident, $($tail:ident,)*) => {
+        delta_components!($($tail,)*);
+    };
+}
+
+delta_components!(CA, CB, CC, CD, CE, CF, CG, CH, CI, CJ, CK, CL, CM, CN, CO, CP,);
+
+#[cfg(test)]
+mod tests {
+    use std::convert::Infallible;
+
+    use serde::{Deserialize, Serialize};
+
+    use super::*;
+    use crate::{
+        saveload::{MarkedBuilder, SimpleMarker, SimpleMarkerAllocator},
+        storage::FlaggedStorage,
+        world::Builder,
+    };
+
+    #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+    struct Pos(i32);
+
+    impl Component for Pos {
+        type Storage = FlaggedStorage<Self>;
+    }
+
+    #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+    struct Vel(i32);
+
+    impl Component for Vel {
+        type Storage = FlaggedStorage<Self>;
+    }
+
+    struct Net;
+
+    type NetMarker = SimpleMarker<Net>;
+    type Comps = (Pos, Vel);
+    type Delta = WorldDelta<NetMarker, <Comps as DeltaComponents<Infallible, NetMarker>>::Data>;
+
+    fn create_world() -> World {
+        let mut world = World::new();
+        world.register::<Pos>();
+        world.register::<Vel>();
+        world.register::<NetMarker>();
+        world.insert(SimpleMarkerAllocator::<Net>::new());
+
+        world
+    }
+
+    fn sync(
+        tracker: &mut DeltaTracker<NetMarker, Comps>,
+        server: &World,
+        client: &mut World,
+    ) -> Delta {
+        let delta: Delta = tracker.delta::<Infallible>(server).unwrap();
+        let json = serde_json::to_string(&delta).unwrap();
+        serde_json::from_str::<Delta>(&json)
+            .unwrap()
+            .apply::<Infallible, Comps>(client)
+            .unwrap();
+        client.maintain();
+
+        delta
+    }
+
+    fn components(world: &World) -> Vec<(Option<Pos>, Option<Vel>)> {
+        let entities = world.entities();
+        let markers = world.read_storage::<NetMarker>();
+        let pos = world.read_storage::<Pos>();
+        let vel = world.read_storage::<Vel>();
+
+        (&entities, &markers)
+            .join()
+            .map(|(e, _)| (pos.get(e).cloned(), vel.get(e).cloned()))
+            .collect()
+    }
+
+    #[test]
+    fn replicates_changes() {
+        let mut server = create_world();
+        let mut client = create_world();
+        let mut tracker = DeltaTracker::<NetMarker, Comps>::new(&server);
+
+        let a = server
+            .create_entity()
+            .with(Pos(1))
+            .with(Vel(2))
+            .marked::<NetMarker>()
+            .build();
+        let b = server.create_entity().with(Pos(3)).marked::<NetMarker>().build();
+        // Unmarked entities are not replicated.
+        server.create_entity().with(Pos(5)).build();
+
+        let delta = sync(&mut tracker, &server, &mut client);
+        assert_eq!(delta.created.len(), 2);
+        assert_eq!(components(&client), components(&server));
+
+        let delta = sync(&mut tracker, &server, &mut client);
+        assert!(delta.is_empty());
+
+        server.write_storage::<Pos>().get_mut(a).unwrap().0 = 10;
+        server.write_storage::<Vel>().insert(b, Vel(4)).unwrap();
+        server.write_storage::<Vel>().remove(a);
+
+        let delta = sync(&mut tracker, &server, &mut client);
+        assert!(delta.created.is_empty());
+        assert_eq!(delta.modified.len(), 2);
+        assert_eq!(
+            delta.modified[0].components,
+            (
+                Some(ComponentDelta::Set(Pos(10))),
+                Some(ComponentDelta::Removed)
+            )
+        );
+        assert_eq!(
+            delta.modified[1].components,
+            (None, Some(ComponentDelta::Set(Vel(4))))
+        );
+        assert_eq!(components(&client), components(&server));
+
+        server.delete_entity(a).unwrap();
+
+        let delta = sync(&mut tracker, &server, &mut client);
+        assert_eq!(delta.deleted.len(), 1);
+        assert_eq!(components(&client), vec![(Some(Pos(3)), Some(Vel(4)))]);
+    }
+}
@@ -11,7 +11,7 @@ use super::ConvertSaveload;
 use crate::{
     saveload::{
         marker::{Marker, MarkerAllocator},
-        EntityData,
+        EntityData, SerializeFilter,
     },
     storage::{GenericWriteStorage, WriteStorage},
     world::{Component, EntitiesRes, Entity},
@@ -37,6 +37,25 @@ where
     where
         F: FnMut(M) -> Option<Entity>;
 
+    /// Loads `Component`s to entity from `Data` deserializable representation,
+    /// keeping the components which aren't included by `filter` instead of
+    /// removing them.
+    ///
+    /// Defaults to `deserialize_entity`, ignoring `filter`; the
+    /// implementations for tuples of storages respect it.
+    fn deserialize_entity_filtered<F>(
+        &mut self,
+        entity: Entity,
+        components: Self::Data,
+        ids: F,
+        _filter: &SerializeFilter,
+    ) -> Result<(), E>
+    where
+        F: FnMut(M) -> Option<Entity>,
+    {
+        self.deserialize_entity(entity, components, ids)
+    }
+
     /// Deserialize entities according to markers.
     fn deserialize<'a: 'b, 'b, 'de, D>(
         &'b mut self,
@@ -53,6 +72,34 @@ where
             entities,
             markers,
             storages: self,
+            filter: None,
+            pd: PhantomData,
+        })
+    }
+
+    /// Deserialize entities written by
+    /// `SerializeComponents::serialize_filtered` according to markers.
+    ///
+    /// In contrast to `deserialize`, which removes every component missing
+    /// from the data, components which aren't included by `filter` are kept
+    /// as they are. The entities of `filter` aren't taken into account.
+    fn deserialize_filtered<'a: 'b, 'b, 'de, D>(
+        &'b mut self,
+        entities: &'b EntitiesRes,
+        markers: &'b mut WriteStorage<'a, M>,
+        allocator: &'b mut M::Allocator,
+        filter: &'b SerializeFilter<'b>,
+        deserializer: D,
+    ) -> Result<(), D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_seq(VisitEntities::<E, M, Self> {
+            allocator,
+            entities,
+            markers,
+            storages: self,
+            filter: Some(filter),
             pd: PhantomData,
         })
     }
@@ -65,6 +112,7 @@ struct DeserializeEntity<'a: 'b, 'b, E, M: Marker, S: 'b> {
     entities: &'b EntitiesRes,
     storages: &'b mut S,
     markers: &'b mut WriteStorage<'a, M>,
+    filter: Option<&'b SerializeFilter<'b>>,
     pd: PhantomData<E>,
 }
 
@@ -85,15 +133,18 @@ where
             storages,
             markers,
             allocator,
+            filter,
             ..
         } = self;
         let data = EntityData::<M, S::Data>::deserialize(deserializer)?;
         let entity = allocator.retrieve_entity(data.marker, markers, entities);
         let ids = |marker: M| Some(allocator.retrieve_entity(marker, markers, entities));
 
-        storages
-            .deserialize_entity(entity, data.components, ids)
-            .map_err(de::Error::custom)
+        match filter {
+            Some(filter) => storages.deserialize_entity_filtered(entity, data.components, ids, filter),
+            None => storages.deserialize_entity(entity, data.components, ids),
+        }
+        .map_err(de::Error::custom)
     }
 }
 
@@ -104,6 +155,7 @@ struct VisitEntities<'a: 'b, 'b, E, M: Marker, S: 'b> {
     entities: &'b EntitiesRes,
     markers: &'b mut WriteStorage<'a, M>,
     storages: &'b mut S,
+    filter: Option<&'b SerializeFilter<'b>>,
     pd: PhantomData<E>,
 }
 
@@ -129,6 +181,7 @@ where
                 storages: self.storages,
                 markers: self.markers,
                 allocator: self.allocator,
+                filter: self.filter,
                 pd: self.pd,
             })?;
 
@@ -182,6 +235,33 @@ macro_rules! deserialize_components {
                 )*
                 Ok(())
             }
+
+            #[allow(unused)]
+            fn deserialize_entity_filtered<F>(
+                &mut self,
+                entity: Entity,
+                components: Self::Data,
+                mut ids: F,
+                filter: &SerializeFilter,
+            ) -> Result<(), E>
+            where
+                F: FnMut(M) -> Option<Entity>
+            {
+                #[allow(bad_style)]
+                let ($(ref mut $sto,)*) = *self;
+                #[allow(bad_style)]
+                let ($($comp,)*) = components;
+                $(
+                    if let Some(component) = $comp {
+                        $sto.insert(entity, ConvertSaveload::<M>::convert_from(component, &mut ids)?);
+                    } else if filter.includes_component::<<$sto as GenericWriteStorage>::Component>(
+                        <<$sto as GenericWriteStorage>::Component as ConvertSaveload<M>>::TRANSIENT,
+                    ) {
+                        $sto.remove(entity);
+                    }
+                )*
+                Ok(())
+            }
         }
 
         deserialize_components!(@pop $($comp => $sto,)*);
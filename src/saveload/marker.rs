@@ -210,7 +210,7 @@ impl<'a> EntityResBuilder<'a> {
 ///     }
 ///
 ///     fn maintain(&mut self, entities: &EntitiesRes, storage: &ReadStorage<NetMarker>) {
-///         self.mapping = (entities, storage)
+///         self.mapping = (entities.with_disabled(), storage.with_disabled())
 ///             .join()
 ///             .map(|(e, m)| (m.id(), e))
 ///             .collect();
@@ -490,7 +490,7 @@ where
 
     fn maintain(&mut self, entities: &EntitiesRes, storage: &ReadStorage<SimpleMarker<T>>) {
         // FIXME: may be too slow
-        self.mapping = (entities, storage)
+        self.mapping = (entities.with_disabled(), storage.with_disabled())
             .join()
             .map(|(e, m)| (m.id(), e))
             .collect();
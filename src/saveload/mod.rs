@@ -23,6 +23,23 @@
 //! of these ids is what `MarkerAllocator`s are responsible for. For an example,
 //! see the docs for the `Marker` trait.
 //!
+//! For games where several peers create entities independently,
+//! `PeerMarkerAllocator` hands out `PeerMarker` ids which are partitioned per
+//! peer, so they are deterministic and never collide.
+//!
+//! ## Streaming
+//!
+//! For very large worlds, `Batches` splits the marked entities of a region
+//! into batches which are serialized one at a time, and a `ChunkLoader`
+//! loads a serialized chunk into a live world a few entities at a time.
+//!
+//! ## Versioned saves
+//!
+//! With the `versioned_saveload` feature, `SerializeVersioned` and
+//! `DeserializeVersioned` write and read a `VersionedSave`, which records the
+//! version of every component so saves of earlier builds can be migrated
+//! while loading. See the `Versioned` trait and `Migrations`.
+//!
 
 use std::convert::Infallible;
 
@@ -32,18 +49,29 @@ use crate::world::Entity;
 
 mod de;
 mod marker;
+mod peer;
 mod ser;
+mod stream;
 #[cfg(test)]
 mod tests;
 #[cfg(feature = "uuid_entity")]
 mod uuid;
+#[cfg(feature = "versioned_saveload")]
+mod versioned;
 
 #[cfg(feature = "uuid_entity")]
 pub use self::uuid::{UuidMarker, UuidMarkerAllocator};
+#[cfg(feature = "versioned_saveload")]
+pub use self::versioned::{
+    hash_schema, ComponentSchema, DeserializeVersioned, Migrations, Payload, SaveHeader,
+    SerializeVersioned, VersionedSave, Versioned, FORMAT_VERSION,
+};
 pub use self::{
     de::DeserializeComponents,
     marker::{MarkedBuilder, Marker, MarkerAllocator, SimpleMarker, SimpleMarkerAllocator},
-    ser::SerializeComponents,
+    peer::{PeerMarker, PeerMarkerAllocator},
+    ser::{SerializeComponents, SerializeFilter},
+    stream::{Batches, ChunkLoader},
 };
 
 /// A struct used for deserializing entity data.
@@ -134,6 +162,14 @@ pub trait ConvertSaveload<M>: Sized {
     /// Error may occur during serialization or deserialization of component
     type Error;
 
+    /// Whether the type is transient, which makes
+    /// `SerializeComponents::serialize_filtered` skip it unless
+    /// `SerializeFilter::include_transient` is set.
+    ///
+    /// Defaults to `false`; `#[derive(ConvertSaveload)]` sets it for types
+    /// marked with `#[convert_save_load_transient]`.
+    const TRANSIENT: bool = false;
+
     /// Convert this data from a deserializable form (`Data`) using
     /// entity to marker mapping function
     fn convert_from<F>(data: Self::Data, ids: F) -> Result<Self, Self::Error>
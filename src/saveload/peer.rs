@@ -0,0 +1,253 @@
+//! Provides a `MarkerAllocator` partitioning the id space per peer
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt::{self, Debug},
+    hash::{Hash, Hasher},
+    marker::PhantomData,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    join::Join,
+    saveload::{Marker, MarkerAllocator},
+    storage::{DenseVecStorage, ReadStorage},
+    world::{Component, EntitiesRes, Entity},
+};
+
+/// The number of bits of a `PeerMarker` id used for the peer-local index.
+const INDEX_BITS: u32 = 48;
+
+/// The largest peer-local index of a `PeerMarker`.
+const MAX_INDEX: u64 = (1 << INDEX_BITS) - 1;
+
+/// Marker whose `u64` id is partitioned per peer: the upper 16 bits are the
+/// id of the peer which allocated it, the lower 48 bits a counter local to
+/// that peer.
+///
+/// Peers with distinct peer ids never allocate the same id, without any
+/// coordination; see `PeerMarkerAllocator`.
+#[derive(Serialize, Deserialize)]
+#[repr(transparent)]
+pub struct PeerMarker<T: ?Sized>(u64, #[serde(skip)] PhantomData<T>);
+
+impl<T: ?Sized> PeerMarker<T> {
+    /// Returns the id of the peer which allocated this marker.
+    pub fn peer(&self) -> u16 {
+        (self.0 >> INDEX_BITS) as u16
+    }
+
+    /// Returns the index of this marker local to its peer.
+    pub fn index(&self) -> u64 {
+        self.0 & MAX_INDEX
+    }
+}
+
+impl<T: ?Sized> Clone for PeerMarker<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T: ?Sized> Copy for PeerMarker<T> {}
+
+impl<T: ?Sized> PartialEq for PeerMarker<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.0 == other.0
+    }
+}
+
+impl<T: ?Sized> Eq for PeerMarker<T> {}
+
+impl<T: ?Sized> Hash for PeerMarker<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.0.hash(state);
+    }
+}
+
+impl<T: ?Sized> Debug for PeerMarker<T> {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt.debug_struct("PeerMarker")
+            .field("peer", &self.peer())
+            .field("index", &self.index())
+            .finish()
+    }
+}
+
+impl<T> Component for PeerMarker<T>
+where
+    T: 'static + ?Sized + Send + Sync,
+{
+    type Storage = DenseVecStorage<Self>;
+}
+
+impl<T> Marker for PeerMarker<T>
+where
+    T: 'static + ?Sized + Send + Sync,
+{
+    type Allocator = PeerMarkerAllocator<T>;
+    type Identifier = u64;
+
+    fn id(&self) -> u64 {
+        self.0
+    }
+}
+
+/// Marker allocator for peer-to-peer games, handing out `PeerMarker`s
+/// whose ids consist of the peer id and a local counter.
+///
+/// Allocation is deterministic: a peer allocates the same ids in the same
+/// order on every run. Every peer needs a distinct peer id, which is up to
+/// the game to assign.
+///
+/// Besides its own counter, the allocator records the highest index seen
+/// from every other peer when markers are deserialized. This state (but not
+/// the entity mapping, which is rebuilt by `maintain`) can be serialized,
+/// and the states of several allocators can be combined with `merge`.
+///
+/// ## Example
+///
+/// ```
+/// # use specs::{prelude::*, saveload::{MarkedBuilder, PeerMarker, PeerMarkerAllocator}};
+/// struct Net;
+///
+/// let mut world = World::new();
+/// world.register::<PeerMarker<Net>>();
+/// world.insert(PeerMarkerAllocator::<Net>::new(3));
+///
+/// let e = world.create_entity().marked::<PeerMarker<Net>>().build();
+///
+/// let markers = world.read_storage::<PeerMarker<Net>>();
+/// assert_eq!(markers.get(e).unwrap().peer(), 3);
+/// assert_eq!(markers.get(e).unwrap().index(), 0);
+/// ```
+#[derive(Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct PeerMarkerAllocator<T: ?Sized> {
+    peer: u16,
+    /// The next index of every known peer, including this one.
+    next: BTreeMap<u16, u64>,
+    #[serde(skip)]
+    mapping: HashMap<u64, Entity>,
+    #[serde(skip)]
+    _phantom_data: PhantomData<T>,
+}
+
+impl<T: ?Sized> Debug for PeerMarkerAllocator<T> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_struct("PeerMarkerAllocator")
+            .field("peer", &self.peer)
+            .field("next", &self.next)
+            .field("mapping", &self.mapping)
+            .finish()
+    }
+}
+
+impl<T: ?Sized> Clone for PeerMarkerAllocator<T> {
+    fn clone(&self) -> Self {
+        Self {
+            peer: self.peer,
+            next: self.next.clone(),
+            mapping: self.mapping.clone(),
+            _phantom_data: PhantomData,
+        }
+    }
+}
+
+impl<T: ?Sized> Default for PeerMarkerAllocator<T> {
+    /// Creates an allocator for the peer with id `0`.
+    fn default() -> Self {
+        Self::new(0)
+    }
+}
+
+impl<T: ?Sized> PeerMarkerAllocator<T> {
+    /// Creates a new `PeerMarkerAllocator` for the peer with id `peer`,
+    /// which will yield `PeerMarker`s with indices starting with `0`.
+    pub fn new(peer: u16) -> Self {
+        let mut next = BTreeMap::new();
+        next.insert(peer, 0);
+
+        Self {
+            peer,
+            next,
+            mapping: HashMap::new(),
+            _phantom_data: PhantomData,
+        }
+    }
+
+    /// Returns the id of the peer this allocator allocates markers for.
+    pub fn peer(&self) -> u16 {
+        self.peer
+    }
+
+    /// Returns the index following the highest one allocated by or seen
+    /// from `peer`, which is `0` for unknown peers.
+    pub fn next_index(&self, peer: u16) -> u64 {
+        self.next.get(&peer).cloned().unwrap_or(0)
+    }
+
+    /// Merges the allocation state of `other` into this one, so that for
+    /// every peer the higher next index is kept. The peer id and the entity
+    /// mapping of this allocator are kept.
+    ///
+    /// Merging the state of another allocator for the same peer, for
+    /// example one restored from a save, makes sure no index is allocated
+    /// twice.
+    pub fn merge(&mut self, other: &Self) {
+        for (&peer, &next) in &other.next {
+            self.observe(peer, next);
+        }
+    }
+
+    fn observe(&mut self, peer: u16, next: u64) {
+        let known = self.next.entry(peer).or_insert(0);
+        if next > *known {
+            *known = next;
+        }
+    }
+}
+
+impl<T> MarkerAllocator<PeerMarker<T>> for PeerMarkerAllocator<T>
+where
+    T: 'static + ?Sized + Send + Sync,
+{
+    /// # Panics
+    ///
+    /// Panics if the 48 bits of local indices of this peer are exhausted.
+    fn allocate(&mut self, entity: Entity, id: Option<u64>) -> PeerMarker<T> {
+        let marker = if let Some(id) = id {
+            let marker = PeerMarker(id, PhantomData);
+            self.observe(marker.peer(), marker.index() + 1);
+
+            marker
+        } else {
+            let next = self.next.entry(self.peer).or_insert(0);
+            assert!(
+                *next <= MAX_INDEX,
+                "Marker indices of peer {} are exhausted",
+                self.peer
+            );
+            let id = (u64::from(self.peer) << INDEX_BITS) | *next;
+            *next += 1;
+
+            PeerMarker(id, PhantomData)
+        };
+        self.mapping.insert(marker.id(), entity);
+
+        marker
+    }
+
+    fn retrieve_entity_internal(&self, id: u64) -> Option<Entity> {
+        self.mapping.get(&id).cloned()
+    }
+
+    fn maintain(&mut self, entities: &EntitiesRes, storage: &ReadStorage<PeerMarker<T>>) {
+        // FIXME: may be too slow
+        self.mapping = (entities.with_disabled(), storage.with_disabled())
+            .join()
+            .map(|(e, m)| (m.id(), e))
+            .collect();
+    }
+}
@@ -1,5 +1,6 @@
-use std::fmt::Display;
+use std::{any::TypeId, collections::VecDeque, fmt::Display};
 
+use hibitset::BitSet;
 use serde::ser::{self, Serialize, SerializeSeq, Serializer};
 
 use super::ConvertSaveload;
@@ -13,6 +14,89 @@ use crate::{
     world::{Component, EntitiesRes, Entity},
 };
 
+/// Restricts the entities and components serialized by
+/// `SerializeComponents::serialize_filtered`, so the same tuple of storages
+/// can produce a full save as well as a lightweight snapshot.
+///
+/// By default, all marked entities and all components except transient ones
+/// are serialized. Skipped components are written like missing ones, so
+/// `DeserializeComponents::deserialize` removes them, while
+/// `DeserializeComponents::deserialize_filtered` with the same filter keeps
+/// them.
+///
+/// ## Example
+///
+/// ```
+/// # use std::any::TypeId;
+/// # use specs::{prelude::*, saveload::SerializeFilter};
+/// # struct Pos; impl Component for Pos { type Storage = VecStorage<Self>; }
+/// # struct Inventory; impl Component for Inventory { type Storage = VecStorage<Self>; }
+/// let mut nearby = BitSet::new();
+/// nearby.add(3);
+///
+/// let snapshot = SerializeFilter::new()
+///     .entities(&nearby)
+///     .components(|ty| ty != TypeId::of::<Inventory>());
+/// assert!(snapshot.includes_component::<Pos>(false));
+/// assert!(!snapshot.includes_component::<Inventory>(false));
+/// ```
+#[derive(Default)]
+pub struct SerializeFilter<'a> {
+    entities: Option<&'a BitSet>,
+    components: Option<Box<dyn Fn(TypeId) -> bool + 'a>>,
+    include_transient: bool,
+}
+
+impl<'a> SerializeFilter<'a> {
+    /// Creates a filter including all entities and all components which
+    /// aren't transient.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Only includes the entities whose ids are in `mask`.
+    pub fn entities(mut self, mask: &'a BitSet) -> Self {
+        self.entities = Some(mask);
+
+        self
+    }
+
+    /// Only includes the components for whose `TypeId` `predicate` returns
+    /// `true`.
+    pub fn components<F>(mut self, predicate: F) -> Self
+    where
+        F: Fn(TypeId) -> bool + 'a,
+    {
+        self.components = Some(Box::new(predicate));
+
+        self
+    }
+
+    /// Sets whether components marked as transient through
+    /// `ConvertSaveload::TRANSIENT` are included, which they aren't by
+    /// default.
+    pub fn include_transient(mut self, include: bool) -> Self {
+        self.include_transient = include;
+
+        self
+    }
+
+    /// Returns `true` if `entity` is included.
+    pub fn includes_entity(&self, entity: Entity) -> bool {
+        self.entities.is_none_or(|mask| mask.contains(entity.id()))
+    }
+
+    /// Returns `true` if the component `C` is included, given whether it is
+    /// `transient`.
+    pub fn includes_component<C: Component>(&self, transient: bool) -> bool {
+        (self.include_transient || !transient)
+            && self
+                .components
+                .as_ref()
+                .is_none_or(|predicate| predicate(TypeId::of::<C>()))
+    }
+}
+
 /// A trait which allows to serialize entities and their components.
 pub trait SerializeComponents<E, M>
 where
@@ -27,6 +111,24 @@ where
     where
         F: FnMut(Entity) -> Option<M>;
 
+    /// Serialize the components of a single entity which are included by
+    /// `filter`, using a entity -> marker mapping. Components which aren't
+    /// included are serialized as missing.
+    ///
+    /// Defaults to `serialize_entity`, ignoring `filter`; the implementations
+    /// for tuples of storages respect it.
+    fn serialize_entity_filtered<F>(
+        &self,
+        entity: Entity,
+        ids: F,
+        _filter: &SerializeFilter,
+    ) -> Result<Self::Data, E>
+    where
+        F: FnMut(Entity) -> Option<M>,
+    {
+        self.serialize_entity(entity, ids)
+    }
+
     /// Serialize components from specified storages
     /// of all marked entities with provided serializer.
     /// When the component gets serialized the closure passed
@@ -44,10 +146,10 @@ where
         E: Display,
         S: Serializer,
     {
-        let count = (entities, markers).join().count();
+        let count = (entities.with_disabled(), markers.with_disabled()).join().count();
         let mut serseq = serializer.serialize_seq(Some(count))?;
         let ids = |entity| -> Option<M> { markers.get(entity).cloned() };
-        for (entity, marker) in (entities, markers).join() {
+        for (entity, marker) in (entities.with_disabled(), markers.with_disabled()).join() {
             serseq.serialize_element(&EntityData::<M, Self::Data> {
                 marker: marker.clone(),
                 components: self
@@ -58,6 +160,76 @@ where
         serseq.end()
     }
 
+    /// Serialize components from specified storages of the marked entities
+    /// included by `filter` with provided serializer. The output has the
+    /// same format as the one of `serialize`, so it's deserialized the same
+    /// way.
+    ///
+    /// Like `serialize`, this doesn't recursively mark referenced entities.
+    fn serialize_filtered<S>(
+        &self,
+        entities: &EntitiesRes,
+        markers: &ReadStorage<M>,
+        filter: &SerializeFilter,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error>
+    where
+        E: Display,
+        S: Serializer,
+    {
+        let count = (entities.with_disabled(), markers.with_disabled())
+            .join()
+            .filter(|&(entity, _)| filter.includes_entity(entity))
+            .count();
+        let mut serseq = serializer.serialize_seq(Some(count))?;
+        let ids = |entity| -> Option<M> { markers.get(entity).cloned() };
+        for (entity, marker) in (entities.with_disabled(), markers.with_disabled()).join() {
+            if !filter.includes_entity(entity) {
+                continue;
+            }
+
+            serseq.serialize_element(&EntityData::<M, Self::Data> {
+                marker: marker.clone(),
+                components: self
+                    .serialize_entity_filtered(entity, &ids, filter)
+                    .map_err(ser::Error::custom)?,
+            })?;
+        }
+        serseq.end()
+    }
+
+    /// Serialize components from specified storages of the marked entities
+    /// in `batch` with provided serializer, skipping unmarked ones. The
+    /// output has the same format as the one of `serialize`.
+    ///
+    /// This is usually used together with `Batches`, to save a region of a
+    /// large world in bounded steps.
+    fn serialize_entities<S>(
+        &self,
+        batch: &[Entity],
+        markers: &ReadStorage<M>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error>
+    where
+        E: Display,
+        S: Serializer,
+    {
+        let marked = batch.iter().filter(|&&e| markers.get(e).is_some()).count();
+        let mut serseq = serializer.serialize_seq(Some(marked))?;
+        let ids = |entity| -> Option<M> { markers.get(entity).cloned() };
+        for &entity in batch {
+            if let Some(marker) = markers.get(entity) {
+                serseq.serialize_element(&EntityData::<M, Self::Data> {
+                    marker: marker.clone(),
+                    components: self
+                        .serialize_entity(entity, &ids)
+                        .map_err(ser::Error::custom)?,
+                })?;
+            }
+        }
+        serseq.end()
+    }
+
     /// Serialize components from specified storages
     /// of all marked entities with provided serializer.
     /// When the component gets serialized the closure passed
@@ -76,37 +248,84 @@ where
         M: Marker,
         S: Serializer,
     {
-        let mut serseq = serializer.serialize_seq(None)?;
-        let mut to_serialize: Vec<(Entity, M)> = (entities, &*markers)
+        let roots = (entities.with_disabled(), markers.with_disabled())
             .join()
-            .map(|(e, m)| (e, m.clone()))
+            .map(|(e, _)| e)
             .collect();
-        while !to_serialize.is_empty() {
-            let mut add = vec![];
-            {
-                let mut ids = |entity| -> Option<M> {
-                    if let Some((marker, added)) = allocator.mark(entity, markers) {
-                        if added {
-                            add.push((entity, marker.clone()));
-                        }
-                        Some(marker.clone())
-                    } else {
-                        None
-                    }
-                };
-                for (entity, marker) in to_serialize {
-                    serseq.serialize_element(&EntityData::<M, Self::Data> {
-                        marker,
-                        components: self
-                            .serialize_entity(entity, &mut ids)
-                            .map_err(ser::Error::custom)?,
-                    })?;
-                }
-            }
-            to_serialize = add;
+        serialize_breadth_first(self, roots, markers, allocator, serializer)
+    }
+
+    /// Serialize components from specified storages of `roots` and all
+    /// entities referenced by them, directly or through other referenced
+    /// entities, with provided serializer. Entities are marked on demand,
+    /// and every entity is serialized once even if references form cycles.
+    ///
+    /// Unlike `serialize_recursive`, other marked entities are not
+    /// serialized, so this saves exactly what a subtree points to.
+    fn serialize_subtree<S>(
+        &self,
+        roots: &[Entity],
+        markers: &mut WriteStorage<M>,
+        allocator: &mut M::Allocator,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error>
+    where
+        E: Display,
+        M: Marker,
+        S: Serializer,
+    {
+        serialize_breadth_first(self, roots.to_vec(), markers, allocator, serializer)
+    }
+}
+
+/// Marks and serializes the components of `roots` and, breadth first, all
+/// entities referenced while converting them. Every entity is written to
+/// the sequence as soon as it's dequeued.
+fn serialize_breadth_first<E, M, T, S>(
+    storages: &T,
+    roots: Vec<Entity>,
+    markers: &mut WriteStorage<M>,
+    allocator: &mut M::Allocator,
+    serializer: S,
+) -> Result<S::Ok, S::Error>
+where
+    E: Display,
+    M: Marker,
+    T: SerializeComponents<E, M> + ?Sized,
+    S: Serializer,
+{
+    let mut visited = BitSet::new();
+    let mut queue = VecDeque::new();
+    for root in roots {
+        if !visited.add(root.id()) {
+            queue.push_back(root);
         }
-        serseq.end()
     }
+
+    let mut serseq = serializer.serialize_seq(None)?;
+    while let Some(entity) = queue.pop_front() {
+        let marker = match allocator.mark(entity, markers) {
+            Some((marker, _)) => marker.clone(),
+            // Dead entities can't be serialized.
+            None => continue,
+        };
+        let ids = |referenced: Entity| -> Option<M> {
+            let (marker, _) = allocator.mark(referenced, markers)?;
+            if !visited.add(referenced.id()) {
+                queue.push_back(referenced);
+            }
+
+            Some(marker.clone())
+        };
+
+        serseq.serialize_element(&EntityData::<M, T::Data> {
+            marker,
+            components: storages
+                .serialize_entity(entity, ids)
+                .map_err(ser::Error::custom)?,
+        })?;
+    }
+    serseq.end()
 }
 
 macro_rules! serialize_components {
@@ -134,6 +353,30 @@ macro_rules! serialize_components {
                     $comp.get(entity).map(|c| c.convert_into(&mut ids).map(Some)).unwrap_or(Ok(None))?,
                 )*))
             }
+
+            #[allow(unused)]
+            fn serialize_entity_filtered<F>(
+                &self,
+                entity: Entity,
+                mut ids: F,
+                filter: &SerializeFilter,
+            ) -> Result<Self::Data, E>
+            where
+                F: FnMut(Entity) -> Option<M>
+            {
+                #[allow(bad_style)]
+                let ($(ref $comp,)*) = *self;
+
+                Ok(($(
+                    if filter.includes_component::<$comp>(
+                        <$comp as ConvertSaveload<M>>::TRANSIENT,
+                    ) {
+                        $comp.get(entity).map(|c| c.convert_into(&mut ids).map(Some)).unwrap_or(Ok(None))?
+                    } else {
+                        None
+                    },
+                )*))
+            }
         }
 
         serialize_components!(@pop $($comp => $sto,)*);
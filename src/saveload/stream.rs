@@ -0,0 +1,289 @@
+use std::{collections::VecDeque, fmt::Display, ops::RangeBounds};
+
+use serde::de::{DeserializeOwned, Deserializer};
+
+use crate::{
+    join::Join,
+    saveload::{
+        marker::{Marker, MarkerAllocator},
+        DeserializeComponents, EntityData,
+    },
+    storage::{ReadStorage, WriteStorage},
+    world::{EntitiesRes, Entity},
+};
+
+/// The marked entities of a region, split into batches of bounded size
+/// which are serialized one at a time with
+/// `SerializeComponents::serialize_entities`.
+///
+/// Each batch is written as a sequence like `SerializeComponents::serialize`
+/// writes it, so it can be loaded with `DeserializeComponents::deserialize`
+/// or incrementally with a `ChunkLoader`.
+///
+/// The entities are collected when the batches are created; entities which
+/// are deleted or unmarked in the meantime are skipped.
+///
+/// ## Example
+///
+/// ```
+/// # use std::convert::Infallible;
+/// # use specs::{prelude::*, saveload::*};
+/// # use serde::{Deserialize, Serialize};
+/// # #[derive(Clone, Serialize, Deserialize)]
+/// # struct Pos(f32);
+/// # impl Component for Pos { type Storage = VecStorage<Self>; }
+/// struct Save;
+///
+/// let mut world = World::new();
+/// world.register::<Pos>();
+/// world.register::<SimpleMarker<Save>>();
+/// world.insert(SimpleMarkerAllocator::<Save>::new());
+/// for i in 0..10 {
+///     world
+///         .create_entity()
+///         .with(Pos(i as f32))
+///         .marked::<SimpleMarker<Save>>()
+///         .build();
+/// }
+///
+/// let entities = world.entities();
+/// let positions = world.read_storage::<Pos>();
+/// let markers = world.read_storage::<SimpleMarker<Save>>();
+///
+/// let mut batches = Batches::with_marker_range(&entities, &markers, 2..8, 4);
+/// let mut saved = vec![];
+/// while let Some(batch) = batches.next_batch() {
+///     let mut buf = Vec::new();
+///     SerializeComponents::<Infallible, _>::serialize_entities(
+///         &(&positions,),
+///         batch,
+///         &markers,
+///         &mut serde_json::Serializer::new(&mut buf),
+///     )
+///     .unwrap();
+///     saved.push(buf);
+/// }
+/// assert_eq!(saved.len(), 2);
+/// ```
+pub struct Batches {
+    entities: Vec<Entity>,
+    batch_size: usize,
+    position: usize,
+}
+
+impl Batches {
+    /// Collects the marked entities which are part of `region`, which is
+    /// joined with the markers, e.g. a `BitSet` of entity ids.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `batch_size` is zero.
+    pub fn new<M, B>(
+        entities: &EntitiesRes,
+        markers: &ReadStorage<M>,
+        region: B,
+        batch_size: usize,
+    ) -> Self
+    where
+        M: Marker,
+        B: Join,
+    {
+        let entities = (entities.with_disabled(), markers.with_disabled(), region)
+            .join()
+            .map(|(entity, _, _)| entity)
+            .collect();
+
+        Batches::from_entities(entities, batch_size)
+    }
+
+    /// Collects the marked entities whose marker ids are in `range`, in the
+    /// order of their marker ids.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `batch_size` is zero.
+    pub fn with_marker_range<M, R>(
+        entities: &EntitiesRes,
+        markers: &ReadStorage<M>,
+        range: R,
+        batch_size: usize,
+    ) -> Self
+    where
+        M: Marker,
+        M::Identifier: Ord,
+        R: RangeBounds<M::Identifier>,
+    {
+        let mut marked: Vec<_> = (entities.with_disabled(), markers.with_disabled())
+            .join()
+            .map(|(entity, marker)| (marker.id(), entity))
+            .filter(|(id, _)| range.contains(id))
+            .collect();
+        marked.sort_by(|a, b| a.0.cmp(&b.0));
+
+        Batches::from_entities(marked.into_iter().map(|(_, e)| e).collect(), batch_size)
+    }
+
+    fn from_entities(entities: Vec<Entity>, batch_size: usize) -> Self {
+        assert!(batch_size > 0, "The batch size must not be zero");
+
+        Batches {
+            entities,
+            batch_size,
+            position: 0,
+        }
+    }
+
+    /// Returns the number of entities collected.
+    pub fn len(&self) -> usize {
+        self.entities.len()
+    }
+
+    /// Returns `true` if no entities were collected.
+    pub fn is_empty(&self) -> bool {
+        self.entities.is_empty()
+    }
+
+    /// Returns the number of batches which weren't returned yet.
+    pub fn remaining(&self) -> usize {
+        let left = self.entities.len() - self.position;
+
+        left.div_ceil(self.batch_size)
+    }
+
+    /// Returns the entities of the next batch, or `None` once all batches
+    /// were returned.
+    pub fn next_batch(&mut self) -> Option<&[Entity]> {
+        if self.position == self.entities.len() {
+            return None;
+        }
+
+        let start = self.position;
+        self.position = self.entities.len().min(start + self.batch_size);
+
+        Some(&self.entities[start..self.position])
+    }
+}
+
+/// Loads a serialized chunk of entities into a live world a bounded number
+/// of entities at a time, so loading large areas can be spread over several
+/// frames.
+///
+/// Reading the chunk with `ChunkLoader::read` doesn't touch the world, so
+/// it can be done on another thread.
+///
+/// ## Example
+///
+/// ```
+/// # use std::convert::Infallible;
+/// # use specs::{prelude::*, saveload::*};
+/// # use serde::{Deserialize, Serialize};
+/// # #[derive(Clone, Serialize, Deserialize)]
+/// # struct Pos(f32);
+/// # impl Component for Pos { type Storage = VecStorage<Self>; }
+/// # struct Save;
+/// # let chunk = r#"[{"marker":[0],"components":[1.0]},{"marker":[1],"components":[2.0]}]"#;
+/// let mut world = World::new();
+/// world.register::<Pos>();
+/// world.register::<SimpleMarker<Save>>();
+/// world.insert(SimpleMarkerAllocator::<Save>::new());
+///
+/// let mut de = serde_json::Deserializer::from_str(chunk);
+/// let mut loader = ChunkLoader::read(&mut de).unwrap();
+///
+/// while !loader.is_done() {
+///     // Once per frame
+///     world.exec(
+///         |(entities, positions, mut markers, mut allocator): (
+///             Entities,
+///             WriteStorage<Pos>,
+///             WriteStorage<SimpleMarker<Save>>,
+///             Write<SimpleMarkerAllocator<Save>>,
+///         )| {
+///             let mut storages = (positions,);
+///             loader
+///                 .load::<Infallible, _>(
+///                     &mut storages,
+///                     &entities,
+///                     &mut markers,
+///                     &mut allocator,
+///                     1,
+///                 )
+///                 .unwrap();
+///         },
+///     );
+/// }
+///
+/// assert_eq!((&world.read_storage::<Pos>()).join().count(), 2);
+/// ```
+pub struct ChunkLoader<M, D> {
+    pending: VecDeque<EntityData<M, D>>,
+}
+
+impl<M, D> ChunkLoader<M, D>
+where
+    M: Marker,
+    D: DeserializeOwned,
+{
+    /// Reads a chunk written by `SerializeComponents::serialize` or
+    /// `SerializeComponents::serialize_entities`.
+    pub fn read<'de, DE>(deserializer: DE) -> Result<Self, DE::Error>
+    where
+        DE: Deserializer<'de>,
+    {
+        let pending = serde::Deserialize::deserialize(deserializer)?;
+
+        Ok(ChunkLoader { pending })
+    }
+
+    /// Returns the number of entities which weren't loaded yet.
+    pub fn remaining(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// Returns `true` once all entities were loaded.
+    pub fn is_done(&self) -> bool {
+        self.pending.is_empty()
+    }
+
+    /// Loads up to `max` entities into `storages`, returning the number of
+    /// entities loaded.
+    ///
+    /// Entities are retrieved by their markers like
+    /// `DeserializeComponents::deserialize` does, so reloading an area
+    /// updates the entities which are still alive.
+    ///
+    /// If loading an entity fails, the error is returned and only that
+    /// entity is dropped; the entities after it stay pending.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `max` is zero.
+    pub fn load<'a: 'b, 'b, E, S>(
+        &mut self,
+        storages: &'b mut S,
+        entities: &'b EntitiesRes,
+        markers: &'b mut WriteStorage<'a, M>,
+        allocator: &'b mut M::Allocator,
+        max: usize,
+    ) -> Result<usize, E>
+    where
+        E: Display,
+        S: DeserializeComponents<E, M, Data = D>,
+    {
+        assert!(max > 0, "The number of entities to load must not be zero");
+
+        let mut count = 0;
+        while count < max {
+            let data = match self.pending.pop_front() {
+                Some(data) => data,
+                None => break,
+            };
+            let entity = allocator.retrieve_entity(data.marker, markers, entities);
+            let ids = |marker: M| Some(allocator.retrieve_entity(marker, markers, entities));
+            storages.deserialize_entity(entity, data.components, ids)?;
+            count += 1;
+        }
+
+        Ok(count)
+    }
+}
@@ -30,6 +30,7 @@ mod marker_test {
     #[test]
     fn bumps_index_after_reload() {
         bumps_index_after_reload_internal::<SimpleMarker<NetworkSync>>(SimpleMarkerAllocator::new());
+        bumps_index_after_reload_internal::<PeerMarker<NetworkSync>>(PeerMarkerAllocator::new(1));
         #[cfg(feature = "uuid_entity")]
         bumps_index_after_reload_internal::<UuidMarker>(UuidMarkerAllocator::new());
     }
@@ -184,3 +185,784 @@ mod marker_test {
         });
     }
 }
+
+mod peer_test {
+    use super::*;
+    use std::{collections::HashSet, thread};
+
+    #[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+    struct A(u32);
+
+    impl Component for A {
+        type Storage = VecStorage<Self>;
+    }
+
+    struct Net;
+
+    type NetMarker = PeerMarker<Net>;
+
+    /// Creates `count` marked entities in a world of its own for `peer` and
+    /// returns them serialized together with the allocator.
+    fn create_on_peer(peer: u16, count: u32) -> (String, String) {
+        let mut world = World::new();
+        world.register::<A>();
+        world.register::<NetMarker>();
+        world.insert(PeerMarkerAllocator::<Net>::new(peer));
+
+        for i in 0..count {
+            world
+                .create_entity()
+                .with(A(u32::from(peer) * 1000 + i))
+                .marked::<NetMarker>()
+                .build();
+        }
+
+        world.exec(
+            |(ents, comp_a, markers, alloc): (
+                Entities,
+                ReadStorage<A>,
+                ReadStorage<NetMarker>,
+                Read<PeerMarkerAllocator<Net>>,
+            )| {
+                let mut ser = ron::ser::Serializer::new(None, false);
+                SerializeComponents::<Infallible, NetMarker>::serialize(
+                    &(&comp_a,),
+                    &ents,
+                    &markers,
+                    &mut ser,
+                )
+                .unwrap();
+
+                (ser.into_output_string(), ron::ser::to_string(&*alloc).unwrap())
+            },
+        )
+    }
+
+    fn load(world: &mut World, serial: &str) {
+        let mut de = ron::de::Deserializer::from_str(serial).unwrap();
+        world.exec(
+            |(ents, comp_a, mut markers, mut alloc): (
+                Entities,
+                WriteStorage<A>,
+                WriteStorage<NetMarker>,
+                Write<PeerMarkerAllocator<Net>>,
+            )| {
+                DeserializeComponents::<Error, _>::deserialize(
+                    &mut (comp_a,),
+                    &ents,
+                    &mut markers,
+                    &mut alloc,
+                    &mut de,
+                )
+                .unwrap();
+            },
+        );
+    }
+
+    #[test]
+    fn concurrent_peers_never_collide() {
+        let handles: Vec<_> = (1..=4)
+            .map(|peer| thread::spawn(move || create_on_peer(peer, 50)))
+            .collect();
+        let saves: Vec<_> = handles.into_iter().map(|h| h.join().unwrap()).collect();
+
+        let mut world = World::new();
+        world.register::<A>();
+        world.register::<NetMarker>();
+        world.insert(PeerMarkerAllocator::<Net>::new(0));
+        for (serial, _) in &saves {
+            load(&mut world, serial);
+        }
+
+        let local = world.create_entity().with(A(0)).marked::<NetMarker>().build();
+
+        let markers = world.read_storage::<NetMarker>();
+        let values = world.read_storage::<A>();
+        let mut ids = HashSet::new();
+        for (marker, value) in (&markers, &values).join() {
+            assert!(ids.insert(marker.id()));
+            assert_eq!(value.0, u32::from(marker.peer()) * 1000 + marker.index() as u32);
+        }
+        assert_eq!(ids.len(), 201);
+
+        let local = markers.get(local).unwrap();
+        assert_eq!((local.peer(), local.index()), (0, 0));
+
+        let alloc = world.read_resource::<PeerMarkerAllocator<Net>>();
+        for peer in 1..=4 {
+            assert_eq!(alloc.next_index(peer), 50);
+        }
+        assert_eq!(alloc.next_index(0), 1);
+    }
+
+    #[test]
+    fn deterministic_allocation() {
+        let (first, first_alloc) = create_on_peer(7, 10);
+        let (second, second_alloc) = create_on_peer(7, 10);
+
+        assert_eq!(first, second);
+        assert_eq!(first_alloc, second_alloc);
+    }
+
+    #[test]
+    fn restore_and_merge_state() {
+        let (_, saved) = create_on_peer(2, 5);
+        let mut restored: PeerMarkerAllocator<Net> = ron::de::from_str(&saved).unwrap();
+        assert_eq!(restored.peer(), 2);
+        assert_eq!(restored.next_index(2), 5);
+
+        let mut other = PeerMarkerAllocator::<Net>::new(3);
+        let (_, more) = create_on_peer(2, 8);
+        let more: PeerMarkerAllocator<Net> = ron::de::from_str(&more).unwrap();
+        other.merge(&more);
+        assert_eq!(other.peer(), 3);
+        assert_eq!(other.next_index(2), 8);
+
+        restored.merge(&other);
+        assert_eq!(restored.next_index(2), 8);
+        assert_eq!(restored.next_index(3), 0);
+
+        let mut world = World::new();
+        world.register::<NetMarker>();
+        world.insert(restored);
+        let e = world.create_entity().marked::<NetMarker>().build();
+
+        let markers = world.read_storage::<NetMarker>();
+        let marker = markers.get(e).unwrap();
+        assert_eq!((marker.peer(), marker.index()), (2, 8));
+    }
+}
+
+#[cfg(feature = "versioned_saveload")]
+mod versioned_test {
+    use super::*;
+
+    struct Save;
+
+    type SaveMarker = SimpleMarker<Save>;
+
+    /// `Health` as it was saved by an earlier build.
+    #[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+    struct OldHealth(u32);
+
+    impl Component for OldHealth {
+        type Storage = VecStorage<Self>;
+    }
+
+    impl Versioned for OldHealth {
+        const NAME: &'static str = "Health";
+        const VERSION: u32 = 1;
+
+        fn schema_hash() -> u64 {
+            hash_schema("u32")
+        }
+    }
+
+    #[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+    struct Health {
+        current: u32,
+        max: u32,
+    }
+
+    impl Component for Health {
+        type Storage = VecStorage<Self>;
+    }
+
+    impl Versioned for Health {
+        const NAME: &'static str = "Health";
+        const VERSION: u32 = 2;
+
+        fn schema_hash() -> u64 {
+            hash_schema("current: u32, max: u32")
+        }
+    }
+
+    fn save_old() -> Vec<u8> {
+        let mut world = World::new();
+        world.register::<OldHealth>();
+        world.register::<SaveMarker>();
+        world.insert(SimpleMarkerAllocator::<Save>::new());
+
+        world.create_entity().with(OldHealth(10)).marked::<SaveMarker>().build();
+        world.create_entity().marked::<SaveMarker>().build();
+
+        let mut buf = Vec::new();
+        world.exec(
+            |(ents, health, markers): (Entities, ReadStorage<OldHealth>, ReadStorage<SaveMarker>)| {
+                let mut ser = serde_json::Serializer::new(&mut buf);
+                SerializeVersioned::<Infallible, SaveMarker>::serialize_versioned(
+                    &(&health,),
+                    &ents,
+                    &markers,
+                    &mut ser,
+                )
+                .unwrap();
+            },
+        );
+
+        buf
+    }
+
+    fn load(world: &mut World, buf: &[u8], migrations: &Migrations) -> Result<(), String> {
+        world.exec(
+            |(ents, health, mut markers, mut alloc): (
+                Entities,
+                WriteStorage<Health>,
+                WriteStorage<SaveMarker>,
+                Write<SimpleMarkerAllocator<Save>>,
+            )| {
+                let mut de = serde_json::Deserializer::from_slice(buf);
+                DeserializeVersioned::<Error, _>::deserialize_versioned(
+                    &mut (health,),
+                    &ents,
+                    &mut markers,
+                    &mut alloc,
+                    migrations,
+                    &mut de,
+                )
+                .map_err(|e| e.to_string())
+            },
+        )
+    }
+
+    fn new_world() -> World {
+        let mut world = World::new();
+        world.register::<Health>();
+        world.register::<SaveMarker>();
+        world.insert(SimpleMarkerAllocator::<Save>::new());
+
+        world
+    }
+
+    #[test]
+    fn header() {
+        let save: VersionedSave<SaveMarker> = serde_json::from_slice(&save_old()).unwrap();
+
+        assert_eq!(save.header.format_version, FORMAT_VERSION);
+        assert_eq!(save.header.components, vec![ComponentSchema::of::<OldHealth>()]);
+        assert_eq!(save.header.components[0].hash, hash_schema("u32"));
+        assert_ne!(hash_schema("u32"), hash_schema("current: u32, max: u32"));
+        assert_eq!(save.entities.len(), 2);
+    }
+
+    #[test]
+    fn migrates_old_payloads() {
+        let mut migrations = Migrations::default();
+        migrations.add::<Health>(1, |old| serde_json::json!({ "current": old, "max": old }));
+
+        let mut world = new_world();
+        load(&mut world, &save_old(), &migrations).unwrap();
+
+        let health = world.read_storage::<Health>();
+        let loaded: Vec<_> = (&health).join().cloned().collect();
+        assert_eq!(loaded, vec![Health { current: 10, max: 10 }]);
+        assert_eq!((&world.read_storage::<SaveMarker>()).join().count(), 2);
+    }
+
+    /// Serializes to `null`, which must not be mistaken for an absent
+    /// component.
+    #[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+    struct Frozen;
+
+    impl Component for Frozen {
+        type Storage = VecStorage<Self>;
+    }
+
+    impl Versioned for Frozen {
+        const NAME: &'static str = "Frozen";
+        const VERSION: u32 = 1;
+
+        fn schema_hash() -> u64 {
+            hash_schema("()")
+        }
+    }
+
+    #[test]
+    fn unit_component_roundtrip() {
+        let mut world = World::new();
+        world.register::<Frozen>();
+        world.register::<SaveMarker>();
+        world.insert(SimpleMarkerAllocator::<Save>::new());
+        let frozen = world.create_entity().with(Frozen).marked::<SaveMarker>().build();
+        world.create_entity().marked::<SaveMarker>().build();
+
+        let mut buf = Vec::new();
+        world.exec(
+            |(ents, frozen, markers): (Entities, ReadStorage<Frozen>, ReadStorage<SaveMarker>)| {
+                let mut ser = serde_json::Serializer::new(&mut buf);
+                SerializeVersioned::<Infallible, SaveMarker>::serialize_versioned(
+                    &(&frozen,),
+                    &ents,
+                    &markers,
+                    &mut ser,
+                )
+                .unwrap();
+            },
+        );
+
+        let save: VersionedSave<SaveMarker> = serde_json::from_slice(&buf).unwrap();
+        let payloads: Vec<_> = save.entities.iter().map(|e| e.components[0].clone()).collect();
+        assert_eq!(
+            payloads,
+            vec![Payload::Json("null".to_owned()), Payload::Absent]
+        );
+
+        world.exec(
+            |(ents, frozen, mut markers, mut alloc): (
+                Entities,
+                WriteStorage<Frozen>,
+                WriteStorage<SaveMarker>,
+                Write<SimpleMarkerAllocator<Save>>,
+            )| {
+                let mut de = serde_json::Deserializer::from_slice(&buf);
+                DeserializeVersioned::<Error, _>::deserialize_versioned(
+                    &mut (frozen,),
+                    &ents,
+                    &mut markers,
+                    &mut alloc,
+                    &Migrations::default(),
+                    &mut de,
+                )
+                .unwrap();
+            },
+        );
+
+        let entities = world.entities();
+        let frozen_storage = world.read_storage::<Frozen>();
+        let loaded: Vec<_> = (&entities, &frozen_storage).join().map(|(e, _)| e).collect();
+        assert_eq!(loaded, vec![frozen]);
+    }
+
+    #[test]
+    fn missing_migration() {
+        let mut world = new_world();
+        let err = load(&mut world, &save_old(), &Migrations::default()).unwrap_err();
+
+        assert!(err.contains("No migration of component `Health` from version 1"));
+        assert_eq!((&world.read_storage::<Health>()).join().count(), 0);
+    }
+
+    /// `OldHealth` with a changed layout, but without a new version.
+    #[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+    struct SignedHealth(i64);
+
+    impl Component for SignedHealth {
+        type Storage = VecStorage<Self>;
+    }
+
+    impl Versioned for SignedHealth {
+        const NAME: &'static str = "Health";
+        const VERSION: u32 = 1;
+
+        fn schema_hash() -> u64 {
+            hash_schema("i64")
+        }
+    }
+
+    #[test]
+    fn schema_hash_mismatch() {
+        let mut world = World::new();
+        world.register::<SignedHealth>();
+        world.register::<SaveMarker>();
+        world.insert(SimpleMarkerAllocator::<Save>::new());
+
+        let buf = save_old();
+        let err = world.exec(
+            |(ents, health, mut markers, mut alloc): (
+                Entities,
+                WriteStorage<SignedHealth>,
+                WriteStorage<SaveMarker>,
+                Write<SimpleMarkerAllocator<Save>>,
+            )| {
+                let mut de = serde_json::Deserializer::from_slice(&buf);
+                DeserializeVersioned::<Error, _>::deserialize_versioned(
+                    &mut (health,),
+                    &ents,
+                    &mut markers,
+                    &mut alloc,
+                    &Migrations::default(),
+                    &mut de,
+                )
+                .unwrap_err()
+                .to_string()
+            },
+        );
+
+        assert!(err.contains("different schema hash"));
+    }
+}
+
+mod stream_test {
+    use super::*;
+    use hibitset::BitSet;
+
+    #[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+    struct A(i32);
+
+    impl Component for A {
+        type Storage = VecStorage<Self>;
+    }
+
+    struct Save;
+
+    type SaveMarker = SimpleMarker<Save>;
+
+    #[test]
+    fn batches_of_region() {
+        let mut world = World::new();
+        world.register::<A>();
+        world.register::<SaveMarker>();
+        world.insert(SimpleMarkerAllocator::<Save>::new());
+
+        let all: Vec<_> = (0..10)
+            .map(|i| world.create_entity().with(A(i)).marked::<SaveMarker>().build())
+            .collect();
+        // Unmarked entities are never part of a batch.
+        world.create_entity().with(A(-1)).build();
+
+        let region: BitSet = all.iter().map(|e| e.id()).filter(|id| id % 2 == 0).collect();
+        let mut chunks = vec![];
+        world.exec(
+            |(ents, comp, markers): (Entities, ReadStorage<A>, ReadStorage<SaveMarker>)| {
+                let mut batches = Batches::new(&ents, &markers, &region, 2);
+                assert_eq!(batches.len(), 5);
+                assert_eq!(batches.remaining(), 3);
+
+                while let Some(batch) = batches.next_batch() {
+                    assert!(batch.len() <= 2);
+                    let mut ser = ron::ser::Serializer::new(None, false);
+                    SerializeComponents::<Infallible, SaveMarker>::serialize_entities(
+                        &(&comp,),
+                        batch,
+                        &markers,
+                        &mut ser,
+                    )
+                    .unwrap();
+                    chunks.push(ser.into_output_string());
+                }
+                assert_eq!(batches.remaining(), 0);
+            },
+        );
+        assert_eq!(chunks.len(), 3);
+
+        let mut world = World::new();
+        world.register::<A>();
+        world.register::<SaveMarker>();
+        world.insert(SimpleMarkerAllocator::<Save>::new());
+
+        for chunk in &chunks {
+            let mut de = ron::de::Deserializer::from_str(chunk).unwrap();
+            let mut loader = ChunkLoader::read(&mut de).unwrap();
+            while !loader.is_done() {
+                world.exec(
+                    |(ents, comp, mut markers, mut alloc): (
+                        Entities,
+                        WriteStorage<A>,
+                        WriteStorage<SaveMarker>,
+                        Write<SimpleMarkerAllocator<Save>>,
+                    )| {
+                        let loaded = loader
+                            .load::<Error, _>(&mut (comp,), &ents, &mut markers, &mut alloc, 1)
+                            .unwrap();
+                        assert_eq!(loaded, 1);
+                    },
+                );
+            }
+        }
+
+        let mut loaded: Vec<i32> = (&world.read_storage::<A>()).join().map(|a| a.0).collect();
+        loaded.sort();
+        assert_eq!(loaded, vec![0, 2, 4, 6, 8]);
+    }
+
+    /// Fails to load negative values.
+    #[derive(Clone, Debug, PartialEq)]
+    struct Positive(i32);
+
+    impl Component for Positive {
+        type Storage = VecStorage<Self>;
+    }
+
+    impl<M: Marker> ConvertSaveload<M> for Positive {
+        type Data = i32;
+        type Error = String;
+
+        fn convert_into<F>(&self, _: F) -> Result<i32, String>
+        where
+            F: FnMut(Entity) -> Option<M>,
+        {
+            Ok(self.0)
+        }
+
+        fn convert_from<F>(data: i32, _: F) -> Result<Self, String>
+        where
+            F: FnMut(M) -> Option<Entity>,
+        {
+            if data < 0 {
+                Err(format!("{} is negative", data))
+            } else {
+                Ok(Positive(data))
+            }
+        }
+    }
+
+    #[test]
+    fn chunk_loader_keeps_rest_on_error() {
+        let mut world = World::new();
+        world.register::<Positive>();
+        world.register::<SaveMarker>();
+        world.insert(SimpleMarkerAllocator::<Save>::new());
+
+        let chunk = r#"[
+            {"marker":[0],"components":[1]},
+            {"marker":[1],"components":[-2]},
+            {"marker":[2],"components":[3]}
+        ]"#;
+        let mut de = serde_json::Deserializer::from_str(chunk);
+        let mut loader = ChunkLoader::read(&mut de).unwrap();
+
+        let mut load = |loader: &mut ChunkLoader<SaveMarker, _>| {
+            world.exec(
+                |(ents, comp, mut markers, mut alloc): (
+                    Entities,
+                    WriteStorage<Positive>,
+                    WriteStorage<SaveMarker>,
+                    Write<SimpleMarkerAllocator<Save>>,
+                )| {
+                    loader.load::<String, _>(&mut (comp,), &ents, &mut markers, &mut alloc, 10)
+                },
+            )
+        };
+        assert_eq!(load(&mut loader), Err("-2 is negative".to_owned()));
+        assert_eq!(loader.remaining(), 1);
+        assert_eq!(load(&mut loader), Ok(1));
+        assert!(loader.is_done());
+
+        let mut loaded: Vec<i32> = (&world.read_storage::<Positive>())
+            .join()
+            .map(|p| p.0)
+            .collect();
+        loaded.sort();
+        assert_eq!(loaded, vec![1, 3]);
+    }
+}
+
+mod filter_test {
+    use super::*;
+    use hibitset::BitSet;
+    use std::any::TypeId;
+
+    #[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+    struct A(i32);
+
+    impl Component for A {
+        type Storage = VecStorage<Self>;
+    }
+
+    #[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+    struct B(bool);
+
+    impl Component for B {
+        type Storage = VecStorage<Self>;
+    }
+
+    struct Save;
+
+    type SaveMarker = SimpleMarker<Save>;
+
+    fn serialize(world: &mut World, filter: &SerializeFilter) -> String {
+        world.exec(
+            |(ents, a, b, markers): (
+                Entities,
+                ReadStorage<A>,
+                ReadStorage<B>,
+                ReadStorage<SaveMarker>,
+            )| {
+                let mut ser = ron::ser::Serializer::new(None, false);
+                SerializeComponents::<Infallible, SaveMarker>::serialize_filtered(
+                    &(&a, &b),
+                    &ents,
+                    &markers,
+                    filter,
+                    &mut ser,
+                )
+                .unwrap();
+
+                ser.into_output_string()
+            },
+        )
+    }
+
+    #[test]
+    fn filtered() {
+        let mut world = World::new();
+        world.register::<A>();
+        world.register::<B>();
+        world.register::<SaveMarker>();
+        world.insert(SimpleMarkerAllocator::<Save>::new());
+
+        let entities: Vec<_> = (0..4)
+            .map(|i| {
+                world
+                    .create_entity()
+                    .with(A(i))
+                    .with(B(true))
+                    .marked::<SaveMarker>()
+                    .build()
+            })
+            .collect();
+
+        let full = serialize(&mut world, &SerializeFilter::new());
+        let mut de = ron::de::Deserializer::from_str(&full).unwrap();
+        let loader = ChunkLoader::<SaveMarker, (Option<A>, Option<B>)>::read(&mut de).unwrap();
+        assert_eq!(loader.remaining(), 4);
+
+        let mut region = BitSet::new();
+        region.add(entities[1].id());
+        region.add(entities[3].id());
+        let filter = SerializeFilter::new()
+            .entities(&region)
+            .components(|ty| ty != TypeId::of::<B>());
+        let snapshot = serialize(&mut world, &filter);
+
+        let mut de = ron::de::Deserializer::from_str(&snapshot).unwrap();
+        let data: Vec<EntityData<SaveMarker, (Option<A>, Option<B>)>> =
+            Deserialize::deserialize(&mut de).unwrap();
+        let components: Vec<_> = data.into_iter().map(|d| d.components).collect();
+        assert_eq!(components, vec![(Some(A(1)), None), (Some(A(3)), None)]);
+
+        // Loading with the same filter keeps the skipped components.
+        world.exec(
+            |(ents, a, b, mut markers, mut alloc): (
+                Entities,
+                WriteStorage<A>,
+                WriteStorage<B>,
+                WriteStorage<SaveMarker>,
+                Write<SimpleMarkerAllocator<Save>>,
+            )| {
+                let mut de = ron::de::Deserializer::from_str(&snapshot).unwrap();
+                DeserializeComponents::<Error, _>::deserialize_filtered(
+                    &mut (a, b),
+                    &ents,
+                    &mut markers,
+                    &mut alloc,
+                    &filter,
+                    &mut de,
+                )
+                .unwrap();
+            },
+        );
+        assert_eq!((&world.read_storage::<B>()).join().count(), 4);
+
+        world.exec(
+            |(ents, a, b, mut markers, mut alloc): (
+                Entities,
+                WriteStorage<A>,
+                WriteStorage<B>,
+                WriteStorage<SaveMarker>,
+                Write<SimpleMarkerAllocator<Save>>,
+            )| {
+                let mut de = ron::de::Deserializer::from_str(&snapshot).unwrap();
+                DeserializeComponents::<Error, _>::deserialize(
+                    &mut (a, b),
+                    &ents,
+                    &mut markers,
+                    &mut alloc,
+                    &mut de,
+                )
+                .unwrap();
+            },
+        );
+        let b = world.read_storage::<B>();
+        assert!(b.get(entities[0]).is_some());
+        assert!(b.get(entities[1]).is_none());
+    }
+}
+
+mod recursive_test {
+    use super::*;
+
+    #[derive(Clone, Debug, PartialEq)]
+    struct Follows(Entity);
+
+    impl Component for Follows {
+        type Storage = VecStorage<Self>;
+    }
+
+    impl<M: Marker> ConvertSaveload<M> for Follows {
+        type Data = M;
+        type Error = Infallible;
+
+        fn convert_into<F>(&self, mut ids: F) -> Result<M, Infallible>
+        where
+            F: FnMut(Entity) -> Option<M>,
+        {
+            Ok(ids(self.0).unwrap())
+        }
+
+        fn convert_from<F>(data: M, mut ids: F) -> Result<Self, Infallible>
+        where
+            F: FnMut(M) -> Option<Entity>,
+        {
+            Ok(Follows(ids(data).unwrap()))
+        }
+    }
+
+    struct Save;
+
+    type SaveMarker = SimpleMarker<Save>;
+
+    #[test]
+    fn subtree() {
+        let mut world = World::new();
+        world.register::<Follows>();
+        world.register::<SaveMarker>();
+        world.insert(SimpleMarkerAllocator::<Save>::new());
+
+        // `a -> b -> c -> a` form a cycle, `d` is marked but unrelated.
+        let a = world.create_entity().build();
+        let b = world.create_entity().build();
+        let c = world.create_entity().with(Follows(a)).build();
+        world.create_entity().with(Follows(c)).marked::<SaveMarker>().build();
+        {
+            let mut follows = world.write_storage::<Follows>();
+            follows.insert(a, Follows(b)).unwrap();
+            follows.insert(b, Follows(c)).unwrap();
+        }
+
+        let saved = world.exec(
+            |(follows, mut markers, mut alloc): (
+                ReadStorage<Follows>,
+                WriteStorage<SaveMarker>,
+                Write<SimpleMarkerAllocator<Save>>,
+            )| {
+                let mut ser = ron::ser::Serializer::new(None, false);
+                SerializeComponents::<Infallible, SaveMarker>::serialize_subtree(
+                    &(&follows,),
+                    &[a],
+                    &mut markers,
+                    &mut alloc,
+                    &mut ser,
+                )
+                .unwrap();
+
+                ser.into_output_string()
+            },
+        );
+
+        let mut de = ron::de::Deserializer::from_str(&saved).unwrap();
+        let data: Vec<EntityData<SaveMarker, (Option<SaveMarker>,)>> =
+            Deserialize::deserialize(&mut de).unwrap();
+        assert_eq!(data.len(), 3);
+        // Every referenced marker was serialized as well.
+        for entity in &data {
+            let target = (entity.components.0).unwrap();
+            assert!(data.iter().any(|e| e.marker == target));
+        }
+
+        let markers = world.read_storage::<SaveMarker>();
+        assert!(markers.get(a).is_some());
+        assert!(markers.get(b).is_some());
+        assert!(markers.get(c).is_some());
+    }
+}
@@ -86,7 +86,7 @@ impl MarkerAllocator<UuidMarker> for UuidMarkerAllocator {
 
     fn maintain(&mut self, entities: &EntitiesRes, storage: &ReadStorage<UuidMarker>) {
         // FIXME: may be too slow
-        self.mapping = (entities, storage)
+        self.mapping = (entities.with_disabled(), storage.with_disabled())
             .join()
             .map(|(e, m)| (m.uuid(), e))
             .collect();
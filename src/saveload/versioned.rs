@@ -0,0 +1,504 @@
+//! Versioned saves, which can be loaded by later builds after the
+//! serialized form of components changed.
+//!
+//! A `VersionedSave` starts with a `SaveHeader` recording the
+//! `FORMAT_VERSION` of the container and a `ComponentSchema` per component
+//! type.
+//!
+//! This is JSON-payload versioning: no matter which format the container
+//! is written in, every component payload is encoded as JSON text and
+//! stored as a string (`Payload::Json`). That way the `Migrations`
+//! registered for a component can transform payloads of older versions as
+//! `serde_json::Value`s before `ConvertSaveload::convert_from` runs, and
+//! the container doesn't rely on `deserialize_any`, so it can be written in
+//! formats which aren't self-describing, like bincode. The price is that
+//! the payloads within a binary container are JSON text rather than the
+//! compact encoding of the outer format.
+
+use std::{collections::HashMap, fmt::Display, mem};
+
+use serde::{
+    de::{self, Deserializer},
+    ser::{self, Serializer},
+    Deserialize, Serialize,
+};
+use serde_json::Value;
+
+use super::ConvertSaveload;
+use crate::{
+    join::Join,
+    saveload::{
+        marker::{Marker, MarkerAllocator},
+        EntityData,
+    },
+    storage::{GenericReadStorage, GenericWriteStorage, ReadStorage, WriteStorage},
+    world::{Component, EntitiesRes, Entity},
+};
+
+/// The version of the `VersionedSave` container written by this version of
+/// Specs.
+pub const FORMAT_VERSION: u32 = 1;
+
+/// A component whose serialized form is versioned.
+///
+/// ## Example
+///
+/// ```
+/// # use specs::{prelude::*, saveload::{hash_schema, Versioned}};
+/// # use serde::{Deserialize, Serialize};
+/// #[derive(Clone, Serialize, Deserialize)]
+/// struct Health {
+///     current: u32,
+///     max: u32,
+/// }
+///
+/// impl Component for Health {
+///     type Storage = VecStorage<Self>;
+/// }
+///
+/// impl Versioned for Health {
+///     const NAME: &'static str = "Health";
+///     // Version 1 only stored `current`.
+///     const VERSION: u32 = 2;
+///
+///     fn schema_hash() -> u64 {
+///         hash_schema("current: u32, max: u32")
+///     }
+/// }
+/// ```
+pub trait Versioned {
+    /// The name identifying the component in saves, which has to stay the
+    /// same across builds.
+    const NAME: &'static str;
+
+    /// The version of the serialized form, which has to be incremented
+    /// whenever it changes.
+    const VERSION: u32;
+
+    /// The hash of the serialized form at `VERSION`, usually
+    /// `hash_schema` of a description of the fields and their types.
+    ///
+    /// Saves with the current version but another hash are rejected, which
+    /// catches changes of the serialized form without an increment of
+    /// `VERSION`, as long as the description is updated along with it.
+    fn schema_hash() -> u64;
+}
+
+/// Hashes a description of the serialized form of a component with FNV-1a,
+/// which is stable across builds and platforms.
+pub fn hash_schema(layout: &str) -> u64 {
+    const PRIME: u64 = 0x0100_0000_01b3;
+
+    layout
+        .as_bytes()
+        .iter()
+        .fold(0xcbf2_9ce4_8422_2325, |hash, &byte| {
+            (hash ^ u64::from(byte)).wrapping_mul(PRIME)
+        })
+}
+
+/// The schema of a single component type, as recorded in a `SaveHeader`.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct ComponentSchema {
+    /// The `Versioned::NAME` of the component.
+    pub name: String,
+    /// The `Versioned::VERSION` of the component.
+    pub version: u32,
+    /// The `Versioned::schema_hash` of the component.
+    pub hash: u64,
+}
+
+impl ComponentSchema {
+    /// Returns the current schema of `C`.
+    pub fn of<C: Versioned>() -> Self {
+        ComponentSchema {
+            name: C::NAME.to_owned(),
+            version: C::VERSION,
+            hash: C::schema_hash(),
+        }
+    }
+}
+
+/// The header of a `VersionedSave`.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct SaveHeader {
+    /// The `FORMAT_VERSION` the save was written with.
+    pub format_version: u32,
+    /// The schemas of the saved components, in the order of the payloads
+    /// of each entity.
+    pub components: Vec<ComponentSchema>,
+}
+
+/// The payload of a single component of a saved entity.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub enum Payload {
+    /// The entity doesn't have the component, so loading removes it.
+    Absent,
+    /// The `ConvertSaveload::Data` of the component, encoded as JSON text
+    /// regardless of the format of the container.
+    Json(String),
+}
+
+/// The container written by `SerializeVersioned` and read by
+/// `DeserializeVersioned`.
+#[derive(Deserialize, Serialize)]
+pub struct VersionedSave<M> {
+    /// The format version and component schemas.
+    pub header: SaveHeader,
+    /// The marked entities with a payload per component in the header.
+    pub entities: Vec<EntityData<M, Vec<Payload>>>,
+}
+
+/// Resource holding the migrations of component payloads from older
+/// versions.
+///
+/// Migrations only transform payloads, so the migrated payload has to match
+/// the `ConvertSaveload::Data` of the component.
+///
+/// ## Example
+///
+/// ```
+/// # use specs::saveload::{Migrations, Versioned};
+/// # struct Health;
+/// # impl Versioned for Health {
+/// #     const NAME: &'static str = "Health";
+/// #     const VERSION: u32 = 2;
+/// #     fn schema_hash() -> u64 { 0 }
+/// # }
+/// use serde_json::json;
+///
+/// let mut migrations = Migrations::default();
+/// // Version 1 stored the current health as a plain number.
+/// migrations.add::<Health>(1, |old| json!({ "current": old, "max": old }));
+///
+/// let migrated = migrations.migrate("Health", 1, 2, json!(10)).unwrap();
+/// assert_eq!(migrated, json!({ "current": 10, "max": 10 }));
+/// ```
+#[derive(Default)]
+pub struct Migrations {
+    steps: HashMap<(String, u32), fn(Value) -> Value>,
+}
+
+impl Migrations {
+    /// Adds the migration of payloads of `C` from `from_version` to
+    /// `from_version + 1`, replacing an existing one.
+    pub fn add<C>(&mut self, from_version: u32, migrate: fn(Value) -> Value) -> &mut Self
+    where
+        C: Versioned,
+    {
+        self.steps.insert((C::NAME.to_owned(), from_version), migrate);
+
+        self
+    }
+
+    /// Migrates a payload of the component called `name` from version
+    /// `from` to version `to`, applying one migration per version. Returns
+    /// `None` if a migration is missing.
+    pub fn migrate(&self, name: &str, from: u32, to: u32, mut value: Value) -> Option<Value> {
+        for version in from..to {
+            let step = self.steps.get(&(name.to_owned(), version))?;
+            value = step(value);
+        }
+
+        Some(value)
+    }
+
+    /// Checks that a payload saved with `saved` can be loaded as `current`.
+    fn check(&self, saved: &ComponentSchema, current: &ComponentSchema) -> Result<(), String> {
+        if saved.version > current.version {
+            return Err(format!(
+                "Component `{}` was saved with version {}, which is newer than the current version {}",
+                saved.name, saved.version, current.version
+            ));
+        }
+        if saved.version == current.version && saved.hash != current.hash {
+            return Err(format!(
+                "Component `{}` was saved with version {}, but a different schema hash",
+                saved.name, saved.version
+            ));
+        }
+        match (saved.version..current.version)
+            .find(|&version| !self.steps.contains_key(&(saved.name.clone(), version)))
+        {
+            Some(version) => Err(format!(
+                "No migration of component `{}` from version {}",
+                saved.name, version
+            )),
+            None => Ok(()),
+        }
+    }
+}
+
+/// A trait which allows to serialize entities and their components into a
+/// `VersionedSave`.
+///
+/// This is implemented for tuples of storages, like `SerializeComponents`.
+pub trait SerializeVersioned<E, M>
+where
+    M: Marker,
+{
+    /// Returns the current schemas of the components.
+    fn schema() -> Vec<ComponentSchema>;
+
+    /// Serializes the components of a single entity to payloads using a
+    /// entity -> marker mapping.
+    fn serialize_entity<F, ER>(&self, entity: Entity, ids: F) -> Result<Vec<Payload>, ER>
+    where
+        F: FnMut(Entity) -> Option<M>,
+        ER: ser::Error;
+
+    /// Serializes the components of all marked entities with provided
+    /// serializer. Like `SerializeComponents::serialize`, referenced
+    /// entities aren't marked.
+    fn serialize_versioned<S>(
+        &self,
+        entities: &EntitiesRes,
+        markers: &ReadStorage<M>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error>
+    where
+        E: Display,
+        S: Serializer,
+    {
+        let ids = |entity| -> Option<M> { markers.get(entity).cloned() };
+        let entities = (entities.with_disabled(), markers.with_disabled())
+            .join()
+            .map(|(entity, marker)| {
+                Ok(EntityData {
+                    marker: marker.clone(),
+                    components: self.serialize_entity::<_, S::Error>(entity, &ids)?,
+                })
+            })
+            .collect::<Result<Vec<_>, S::Error>>()?;
+
+        VersionedSave {
+            header: SaveHeader {
+                format_version: FORMAT_VERSION,
+                components: Self::schema(),
+            },
+            entities,
+        }
+        .serialize(serializer)
+    }
+}
+
+/// A trait which allows to deserialize entities and their components from a
+/// `VersionedSave`, migrating payloads of older versions.
+///
+/// This is implemented for tuples of storages, like `DeserializeComponents`.
+pub trait DeserializeVersioned<E, M>
+where
+    Self: Sized,
+    E: Display,
+    M: Marker,
+{
+    /// Returns the current schemas of the components.
+    fn schema() -> Vec<ComponentSchema>;
+
+    /// Loads the components of a single entity from payloads in the current
+    /// version. `Some(None)` marks a component the entity didn't have, which
+    /// is removed, while `None` marks a component missing from the save,
+    /// which is kept.
+    fn deserialize_entity<F, ER>(
+        &mut self,
+        entity: Entity,
+        components: Vec<Option<Option<Value>>>,
+        ids: F,
+    ) -> Result<(), ER>
+    where
+        F: FnMut(M) -> Option<Entity>,
+        ER: de::Error;
+
+    /// Deserializes entities according to markers.
+    ///
+    /// Components missing from the save are kept as they are. Fails if the
+    /// save has a newer format version, or a component of a newer version or
+    /// one which can't be migrated.
+    fn deserialize_versioned<'a: 'b, 'b, 'de, D>(
+        &'b mut self,
+        entities: &'b EntitiesRes,
+        markers: &'b mut WriteStorage<'a, M>,
+        allocator: &'b mut M::Allocator,
+        migrations: &Migrations,
+        deserializer: D,
+    ) -> Result<(), D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let VersionedSave { header, entities: saved } =
+            VersionedSave::<M>::deserialize(deserializer)?;
+        if header.format_version > FORMAT_VERSION {
+            return Err(de::Error::custom(format!(
+                "Unsupported save format version {}, expected at most {}",
+                header.format_version, FORMAT_VERSION
+            )));
+        }
+
+        // The column of the save and its schema for every current component.
+        let mut columns = vec![];
+        for current in Self::schema() {
+            let column = header
+                .components
+                .iter()
+                .position(|saved| saved.name == current.name);
+            if let Some(column) = column {
+                migrations
+                    .check(&header.components[column], &current)
+                    .map_err(de::Error::custom)?;
+            }
+            columns.push((column, current));
+        }
+
+        for data in saved {
+            let mut payloads = data.components;
+            let mut components = Vec::with_capacity(columns.len());
+            for &(column, ref current) in &columns {
+                let column = match column {
+                    Some(column) => column,
+                    None => {
+                        components.push(None);
+                        continue;
+                    }
+                };
+                let saved = &header.components[column];
+                let payload = payloads
+                    .get_mut(column)
+                    .map(|payload| mem::replace(payload, Payload::Absent));
+                let value = match payload {
+                    Some(Payload::Json(json)) => {
+                        let value = serde_json::from_str(&json).map_err(de::Error::custom)?;
+                        let value = migrations
+                            .migrate(&saved.name, saved.version, current.version, value)
+                            .expect("Migrations were checked");
+                        Some(value)
+                    }
+                    Some(Payload::Absent) | None => None,
+                };
+                components.push(Some(value));
+            }
+
+            let entity = allocator.retrieve_entity(data.marker, markers, entities);
+            let ids = |marker: M| Some(allocator.retrieve_entity(marker, markers, entities));
+            self.deserialize_entity::<_, D::Error>(entity, components, ids)?;
+        }
+
+        Ok(())
+    }
+}
+
+macro_rules! versioned_components {
+    ($($comp:ident => $sto:ident,)*) => {
+        impl<E, M, $($comp,)* $($sto,)*> SerializeVersioned<E, M> for ($($sto,)*)
+        where
+            E: Display,
+            M: Marker,
+            $(
+                $sto: GenericReadStorage<Component = $comp>,
+                $comp: ConvertSaveload<M> + Component + Versioned,
+                E: From<<$comp as ConvertSaveload<M>>::Error>,
+            )*
+        {
+            fn schema() -> Vec<ComponentSchema> {
+                vec![$(ComponentSchema::of::<$comp>(),)*]
+            }
+
+            #[allow(unused)]
+            fn serialize_entity<F, ER>(
+                &self,
+                entity: Entity,
+                mut ids: F,
+            ) -> Result<Vec<Payload>, ER>
+            where
+                F: FnMut(Entity) -> Option<M>,
+                ER: ser::Error,
+            {
+                #[allow(bad_style)]
+                let ($(ref $comp,)*) = *self;
+
+                Ok(vec![$(
+                    match $comp.get(entity) {
+                        Some(component) => {
+                            let data = component
+                                .convert_into(&mut ids)
+                                .map_err(|e| ER::custom(E::from(e)))?;
+                            Payload::Json(serde_json::to_string(&data).map_err(ER::custom)?)
+                        }
+                        None => Payload::Absent,
+                    },
+                )*])
+            }
+        }
+
+        impl<E, M, $($sto,)*> DeserializeVersioned<E, M> for ($($sto,)*)
+        where
+            E: Display,
+            M: Marker,
+            $(
+                $sto: GenericWriteStorage,
+                <$sto as GenericWriteStorage>::Component: ConvertSaveload<M> + Component + Versioned,
+                E: From<<
+                    <$sto as GenericWriteStorage>::Component as ConvertSaveload<M>
+                >::Error>,
+            )*
+        {
+            fn schema() -> Vec<ComponentSchema> {
+                vec![$(ComponentSchema::of::<<$sto as GenericWriteStorage>::Component>(),)*]
+            }
+
+            #[allow(unused)]
+            fn deserialize_entity<F, ER>(
+                &mut self,
+                entity: Entity,
+                components: Vec<Option<Option<Value>>>,
+                mut ids: F,
+            ) -> Result<(), ER>
+            where
+                F: FnMut(M) -> Option<Entity>,
+                ER: de::Error,
+            {
+                #[allow(bad_style)]
+                let ($(ref mut $sto,)*) = *self;
+                let mut components = components.into_iter();
+                $(
+                    match components.next().unwrap_or(None) {
+                        Some(Some(value)) => {
+                            let data = serde_json::from_value(value).map_err(ER::custom)?;
+                            let component = ConvertSaveload::<M>::convert_from(data, &mut ids)
+                                .map_err(|e| ER::custom(E::from(e)))?;
+                            $sto.insert(entity, component);
+                        }
+                        Some(None) => $sto.remove(entity),
+                        None => {}
+                    }
+                )*
+
+                Ok(())
+            }
+        }
+
+        versioned_components!(@pop $($comp => $sto,)*);
+    };
+    (@pop) => {};
+    (@pop $head0:ident => $head1:ident, $($tail0:ident => $tail1:ident,)*) => {
+        versioned_components!($($tail0 => $tail1,)*);
+    };
+}
+
+versioned_components!(
+    CA => SA,
+    CB => SB,
+    CC => SC,
+    CD => SD,
+    CE => SE,
+    CF => SF,
+    CG => SG,
+    CH => SH,
+    CI => SI,
+    CJ => SJ,
+    CK => SK,
+    CL => SL,
+    CN => SN,
+    CM => SM,
+    CO => SO,
+    CP => SP,
+);
@@ -0,0 +1,286 @@
+use std::{
+    marker::PhantomData,
+    ops::{Deref, DerefMut},
+};
+
+use hibitset::{AtomicBitSet, BitSet, BitSetAnd, BitSetLike, BitSetNot};
+
+#[cfg(feature = "parallel")]
+use crate::join::ParJoin;
+use crate::{
+    join::Join,
+    storage::{
+        DenseVecStorage, DistinctStorage, MaskedStorage, Storage, TryDefault, UnprotectedStorage,
+    },
+    world::{Component, Index},
+};
+
+/// `UnprotectedStorage`s that record insertions, modifications and removals
+/// of components as `BitSet`s, which are cleared by `WorldExt::maintain`.
+pub trait ChangeTracked {
+    /// Indices whose component was inserted since the last maintain.
+    fn inserted(&self) -> &BitSet;
+    /// Indices whose component was accessed mutably since the last maintain.
+    fn modified(&self) -> &AtomicBitSet;
+    /// Indices whose component was removed since the last maintain.
+    fn removed(&self) -> &BitSet;
+}
+
+/// Wrapper storage that records changes of components in per-tick
+/// `BitSet`s.
+///
+/// In contrast to `FlaggedStorage`, there are no events to read. The
+/// bitsets are available through `Storage::inserted`, `Storage::modified`
+/// and `Storage::removed`, can be joined directly and are cleared each time
+/// `WorldExt::maintain` is called.
+///
+/// Like with `FlaggedStorage`, every mutable access marks a component as
+/// modified, including joins over `&mut storage`. Join over
+/// `storage.track_mut()` instead to only mark the components which are
+/// actually written to.
+///
+/// `WorldExt::maintain` clears the bitsets before it does anything else.
+/// The changes made by `maintain` itself, i.e. the removals of deleted
+/// entities' components, the applied `CommandBuffer`s and the lazy updates,
+/// are therefore reported during the following tick, together with the
+/// changes made by systems until the next `maintain`.
+///
+/// # Example
+///
+/// ```
+/// # use specs::prelude::*;
+/// # use specs::storage::ChangeTrackedStorage;
+/// pub struct Pos(i32);
+///
+/// impl Component for Pos {
+///     type Storage = ChangeTrackedStorage<Self>;
+/// }
+///
+/// let mut world = World::new();
+/// world.register::<Pos>();
+///
+/// let a = world.create_entity().with(Pos(0)).build();
+/// let b = world.create_entity().with(Pos(5)).build();
+/// world.maintain();
+///
+/// {
+///     let mut positions = world.write_storage::<Pos>();
+///     for mut pos in positions.track_mut().join() {
+///         if pos.0 > 0 {
+///             pos.0 -= 1;
+///         }
+///     }
+/// }
+///
+/// let entities = world.entities();
+/// let positions = world.read_storage::<Pos>();
+/// let modified: Vec<_> = (&entities, positions.modified()).join().map(|(e, _)| e).collect();
+/// assert_eq!(modified, vec![b]);
+/// ```
+pub struct ChangeTrackedStorage<C, T = DenseVecStorage<C>> {
+    storage: T,
+    inserted: BitSet,
+    modified: AtomicBitSet,
+    removed: BitSet,
+    phantom: PhantomData<C>,
+}
+
+impl<C, T> Default for ChangeTrackedStorage<C, T>
+where
+    T: TryDefault,
+{
+    fn default() -> Self {
+        ChangeTrackedStorage {
+            storage: T::unwrap_default(),
+            inserted: BitSet::new(),
+            modified: AtomicBitSet::new(),
+            removed: BitSet::new(),
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<C: Component, T: UnprotectedStorage<C>> UnprotectedStorage<C> for ChangeTrackedStorage<C, T> {
+    unsafe fn clean<B>(&mut self, has: B)
+    where
+        B: BitSetLike,
+    {
+        self.storage.clean(has);
+    }
+
+    unsafe fn get(&self, id: Index) -> &C {
+        self.storage.get(id)
+    }
+
+    unsafe fn get_mut(&mut self, id: Index) -> &mut C {
+        // Parallel joins call this through aliased pointers, so the bit has to
+        // be set atomically.
+        self.modified.add_atomic(id);
+        self.storage.get_mut(id)
+    }
+
+    unsafe fn replace(&mut self, id: Index, comp: C) -> C {
+        self.modified.add(id);
+        self.storage.replace(id, comp)
+    }
+
+    unsafe fn insert(&mut self, id: Index, comp: C) {
+        self.inserted.add(id);
+        self.storage.insert(id, comp);
+    }
+
+    unsafe fn remove(&mut self, id: Index) -> C {
+        self.removed.add(id);
+        self.storage.remove(id)
+    }
+
+    unsafe fn drop(&mut self, id: Index) {
+        self.removed.add(id);
+        self.storage.drop(id);
+    }
+
+    fn maintain(&mut self) {
+        self.inserted.clear();
+        self.modified.clear();
+        self.removed.clear();
+        self.storage.maintain();
+    }
+}
+
+// SAFETY: `get_mut` only modifies the inner storage, which is
+// `DistinctStorage`, and sets the modified bit atomically.
+unsafe impl<C, T> DistinctStorage for ChangeTrackedStorage<C, T> where T: DistinctStorage {}
+
+impl<C, T> ChangeTracked for ChangeTrackedStorage<C, T> {
+    fn inserted(&self) -> &BitSet {
+        &self.inserted
+    }
+
+    fn modified(&self) -> &AtomicBitSet {
+        &self.modified
+    }
+
+    fn removed(&self) -> &BitSet {
+        &self.removed
+    }
+}
+
+impl<'e, T, D> Storage<'e, T, D>
+where
+    T: Component,
+    T::Storage: ChangeTracked,
+    D: Deref<Target = MaskedStorage<T>>,
+{
+    /// Returns the indices whose component was inserted since the last
+    /// `WorldExt::maintain`.
+    pub fn inserted(&self) -> &BitSet {
+        unsafe { self.open() }.1.inserted()
+    }
+
+    /// Returns the indices whose component was modified since the last
+    /// `WorldExt::maintain`.
+    pub fn modified(&self) -> &AtomicBitSet {
+        unsafe { self.open() }.1.modified()
+    }
+
+    /// Returns the indices whose component was removed since the last
+    /// `WorldExt::maintain`.
+    pub fn removed(&self) -> &BitSet {
+        unsafe { self.open() }.1.removed()
+    }
+}
+
+impl<'e, C, T, D> Storage<'e, C, D>
+where
+    C: Component<Storage = ChangeTrackedStorage<C, T>>,
+    T: UnprotectedStorage<C>,
+    D: DerefMut<Target = MaskedStorage<C>>,
+{
+    /// Returns a `Join`-able structure yielding `ChangeGuard`s, which only
+    /// mark a component as modified once it is mutably dereferenced.
+    pub fn track_mut(&mut self) -> TrackMut<'_, C, T> {
+        let disabled = self.entities.disabled();
+        let (mask, storage) = self.data.open_mut();
+
+        TrackMut {
+            mask: BitSetAnd(mask, BitSetNot(disabled)),
+            storage,
+        }
+    }
+}
+
+/// A mutable join over a `ChangeTrackedStorage`, created by
+/// `Storage::track_mut`.
+pub struct TrackMut<'a, C, T> {
+    mask: BitSetAnd<&'a BitSet, BitSetNot<&'a BitSet>>,
+    storage: &'a mut ChangeTrackedStorage<C, T>,
+}
+
+impl<'a, C, T> Join for TrackMut<'a, C, T>
+where
+    C: Component,
+    T: UnprotectedStorage<C>,
+{
+    type Mask = BitSetAnd<&'a BitSet, BitSetNot<&'a BitSet>>;
+    type Type = ChangeGuard<'a, C>;
+    type Value = &'a mut ChangeTrackedStorage<C, T>;
+
+    // SAFETY: No unsafe code and no invariants to fulfill.
+    unsafe fn open(self) -> (Self::Mask, Self::Value) {
+        (self.mask, self.storage)
+    }
+
+    // SAFETY: Since we require that the mask was checked, an element for `id`
+    // must have been inserted without being removed. The returned references
+    // point to distinct fields, see `Join for &mut Storage` for the aliasing.
+    unsafe fn get(v: &mut Self::Value, id: Index) -> ChangeGuard<'a, C> {
+        let storage: *mut ChangeTrackedStorage<C, T> = &mut **v;
+
+        ChangeGuard {
+            value: (*storage).storage.get_mut(id),
+            modified: &(*storage).modified,
+            id,
+        }
+    }
+}
+
+// SAFETY: This is safe because of the `DistinctStorage` guarantees of the
+// inner storage; the modified bit is set atomically.
+#[cfg(feature = "parallel")]
+unsafe impl<'a, C, T> ParJoin for TrackMut<'a, C, T>
+where
+    C: Component,
+    T: UnprotectedStorage<C>,
+    ChangeTrackedStorage<C, T>: Sync + DistinctStorage,
+{
+}
+
+/// Mutable access to a component of a `ChangeTrackedStorage`, which marks
+/// the component as modified on the first mutable dereference.
+pub struct ChangeGuard<'a, C> {
+    value: &'a mut C,
+    modified: &'a AtomicBitSet,
+    id: Index,
+}
+
+impl<'a, C> ChangeGuard<'a, C> {
+    /// Returns the index of the component.
+    pub fn id(&self) -> Index {
+        self.id
+    }
+}
+
+impl<'a, C> Deref for ChangeGuard<'a, C> {
+    type Target = C;
+
+    fn deref(&self) -> &C {
+        self.value
+    }
+}
+
+impl<'a, C> DerefMut for ChangeGuard<'a, C> {
+    fn deref_mut(&mut self) -> &mut C {
+        self.modified.add_atomic(self.id);
+        self.value
+    }
+}
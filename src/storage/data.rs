@@ -123,6 +123,7 @@ where
     fn setup(res: &mut World) {
         res.entry::<MaskedStorage<T>>()
             .or_insert_with(|| MaskedStorage::new(<T::Storage as TryDefault>::unwrap_default()));
+        MaskedStorage::<T>::connect_lazy(res);
         res.fetch_mut::<MetaTable<dyn AnyStorage>>()
             .register(&*res.fetch::<MaskedStorage<T>>());
     }
@@ -210,6 +211,7 @@ where
     fn setup(res: &mut World) {
         res.entry::<MaskedStorage<T>>()
             .or_insert_with(|| MaskedStorage::new(<T::Storage as TryDefault>::unwrap_default()));
+        MaskedStorage::<T>::connect_lazy(res);
         res.fetch_mut::<MetaTable<dyn AnyStorage>>()
             .register(&*res.fetch::<MaskedStorage<T>>());
     }
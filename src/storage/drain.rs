@@ -3,14 +3,16 @@ use hibitset::BitSet;
 use crate::{
     join::Join,
     storage::MaskedStorage,
-    world::{Component, Index},
+    world::{Component, EntitiesRes, Index},
 };
 
 /// A draining storage wrapper which has a `Join` implementation
-/// that removes the components.
+/// that removes the components, invoking `Component::on_remove`.
 pub struct Drain<'a, T: Component> {
     /// The masked storage
     pub data: &'a mut MaskedStorage<T>,
+    /// The entities, used to pass the removed entity to the hooks
+    pub entities: &'a EntitiesRes,
 }
 
 impl<'a, T> Join for Drain<'a, T>
@@ -19,18 +21,19 @@ where
 {
     type Mask = BitSet;
     type Type = T;
-    type Value = &'a mut MaskedStorage<T>;
+    type Value = (&'a mut MaskedStorage<T>, &'a EntitiesRes);
 
     // SAFETY: No invariants to meet and no unsafe code.
     unsafe fn open(self) -> (Self::Mask, Self::Value) {
         let mask = self.data.mask.clone();
 
-        (mask, self.data)
+        (mask, (self.data, self.entities))
     }
 
     // SAFETY: No invariants to meet and no unsafe code.
-    unsafe fn get(value: &mut Self::Value, id: Index) -> T {
-        value.remove(id).expect("Tried to access same index twice")
+    unsafe fn get((data, entities): &mut Self::Value, id: Index) -> T {
+        data.remove_entity(entities.entity(id))
+            .expect("Tried to access same index twice")
     }
 }
 
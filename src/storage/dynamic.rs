@@ -0,0 +1,522 @@
+use std::{any::TypeId, collections::HashMap, marker::PhantomData, slice};
+
+use hibitset::{BitSet, BitSetAnd, BitSetNot};
+
+#[cfg(feature = "parallel")]
+use crate::join::ParJoin;
+use crate::{
+    error::{Error, WrongGeneration},
+    join::Join,
+    storage::{check_alive, AnyStorage, InsertResult},
+    world::{EntitiesRes, Entity, Index, WithDisabled},
+};
+
+/// The runtime id of a dynamic component, returned by
+/// `DynamicComponents::register`.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct DynamicComponentId(u32);
+
+impl DynamicComponentId {
+    /// Returns the id as a number, which is the position of the component
+    /// in the order of registration.
+    pub fn id(self) -> u32 {
+        self.0
+    }
+}
+
+/// Resource holding components whose types are only known at run time,
+/// like components defined by scripts.
+///
+/// Every dynamic component is registered with a name and a size in bytes,
+/// and each component value is a byte slice of that size; encoding values
+/// as bytes is up to the user. The storages are `Join`able together with
+/// typed storages, and components of deleted entities are removed by
+/// `WorldExt::maintain`. Like joins of typed storages, joins skip disabled
+/// entities unless `DynamicStorage::with_disabled` is used.
+///
+/// This resource is added to the world by default. In the
+/// `MetaTable<dyn AnyStorage>`, all dynamic components are represented by
+/// this single storage.
+///
+/// ## Example
+///
+/// ```
+/// # use specs::prelude::*;
+/// # use specs::storage::DynamicComponents;
+/// # struct Pos(f32); impl Component for Pos { type Storage = VecStorage<Self>; }
+/// let mut world = World::new();
+/// world.register::<Pos>();
+///
+/// let health = world
+///     .write_resource::<DynamicComponents>()
+///     .register("health", 4);
+///
+/// let a = world.create_entity().with(Pos(1.0)).build();
+/// world.create_entity().with(Pos(2.0)).build();
+/// {
+///     let entities = world.entities();
+///     let mut dynamic = world.write_resource::<DynamicComponents>();
+///     dynamic
+///         .storage_mut(health)
+///         .insert(&entities, a, &100u32.to_le_bytes())
+///         .unwrap();
+/// }
+///
+/// let positions = world.read_storage::<Pos>();
+/// let dynamic = world.read_resource::<DynamicComponents>();
+/// for (pos, bytes) in (&positions, dynamic.storage(health)).join() {
+///     assert_eq!(pos.0, 1.0);
+///     assert_eq!(bytes, &100u32.to_le_bytes()[..]);
+/// }
+/// ```
+#[derive(Default)]
+pub struct DynamicComponents {
+    names: HashMap<String, DynamicComponentId>,
+    storages: Vec<DynamicStorage>,
+    disabled: BitSet,
+}
+
+impl DynamicComponents {
+    /// Registers a dynamic component with `size` bytes per value and
+    /// returns its id. If a component with the same name is already
+    /// registered, its id is returned.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a component with the same name but a different size is
+    /// already registered.
+    pub fn register(&mut self, name: &str, size: usize) -> DynamicComponentId {
+        if let Some(&id) = self.names.get(name) {
+            assert_eq!(
+                self.storage(id).size(),
+                size,
+                "Dynamic component `{}` is already registered with a different size",
+                name
+            );
+
+            return id;
+        }
+
+        let id = DynamicComponentId(self.storages.len() as u32);
+        let mut storage = DynamicStorage::new(id, name.to_owned(), size);
+        storage.disabled.clone_from(&self.disabled);
+        self.storages.push(storage);
+        self.names.insert(name.to_owned(), id);
+
+        id
+    }
+
+    /// Returns the id of the dynamic component called `name`.
+    pub fn id(&self, name: &str) -> Option<DynamicComponentId> {
+        self.names.get(name).cloned()
+    }
+
+    /// Returns the ids of all dynamic components, in the order they were
+    /// registered.
+    pub fn ids(&self) -> impl Iterator<Item = DynamicComponentId> {
+        (0..self.storages.len() as u32).map(DynamicComponentId)
+    }
+
+    /// Returns the storage of a dynamic component.
+    pub fn storage(&self, id: DynamicComponentId) -> &DynamicStorage {
+        &self.storages[id.0 as usize]
+    }
+
+    /// Returns the storage of a dynamic component mutably.
+    pub fn storage_mut(&mut self, id: DynamicComponentId) -> &mut DynamicStorage {
+        &mut self.storages[id.0 as usize]
+    }
+
+    /// Returns two different storages mutably, so they can be joined
+    /// together.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `a` and `b` are the same.
+    pub fn storage_pair_mut(
+        &mut self,
+        a: DynamicComponentId,
+        b: DynamicComponentId,
+    ) -> (&mut DynamicStorage, &mut DynamicStorage) {
+        assert_ne!(a, b, "Cannot borrow the same dynamic storage twice");
+
+        let (a, b) = (a.0 as usize, b.0 as usize);
+        if a < b {
+            let (left, right) = self.storages.split_at_mut(b);
+            (&mut left[a], &mut right[0])
+        } else {
+            let (left, right) = self.storages.split_at_mut(a);
+            (&mut right[0], &mut left[b])
+        }
+    }
+
+    /// Updates the disabled entities skipped by joins, which is done by
+    /// `WorldExt::maintain` since the storages can't access `EntitiesRes`.
+    pub(crate) fn set_disabled(&mut self, disabled: &BitSet) {
+        if self.disabled == *disabled {
+            return;
+        }
+
+        self.disabled.clone_from(disabled);
+        for storage in &mut self.storages {
+            storage.disabled.clone_from(disabled);
+        }
+    }
+}
+
+impl AnyStorage for DynamicComponents {
+    fn drop(&mut self, entities: &[Entity]) {
+        for storage in &mut self.storages {
+            for entity in entities {
+                storage.remove_id(entity.id());
+            }
+        }
+    }
+
+    fn has(&self, entity: Entity) -> bool {
+        self.storages
+            .iter()
+            .any(|storage| storage.mask.contains(entity.id()))
+    }
+
+    fn component_type(&self) -> TypeId {
+        TypeId::of::<Self>()
+    }
+
+    fn type_name(&self) -> &'static str {
+        std::any::type_name::<Self>()
+    }
+
+    fn remove(&mut self, entities: &EntitiesRes, entity: Entity) -> Result<bool, WrongGeneration> {
+        check_alive(entities, entity, "remove component of")?;
+
+        let mut removed = false;
+        for storage in &mut self.storages {
+            removed |= storage.remove_id(entity.id()).is_some();
+        }
+
+        Ok(removed)
+    }
+}
+
+/// The storage of a single dynamic component, holding a byte slice of
+/// `size` bytes per entity.
+///
+/// Joining `&DynamicStorage` yields `&[u8]`, joining `&mut DynamicStorage`
+/// yields `&mut [u8]`.
+pub struct DynamicStorage {
+    id: DynamicComponentId,
+    name: String,
+    size: usize,
+    mask: BitSet,
+    disabled: BitSet,
+    data: Vec<u8>,
+    entities: Vec<Entity>,
+    rows: Vec<Index>,
+}
+
+impl DynamicStorage {
+    fn new(id: DynamicComponentId, name: String, size: usize) -> Self {
+        DynamicStorage {
+            id,
+            name,
+            size,
+            mask: BitSet::new(),
+            disabled: BitSet::new(),
+            data: Vec::new(),
+            entities: Vec::new(),
+            rows: Vec::new(),
+        }
+    }
+
+    /// Returns the id of the component.
+    pub fn id(&self) -> DynamicComponentId {
+        self.id
+    }
+
+    /// Returns the name of the component.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the size of a component value in bytes.
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// Returns the mask of entity ids having this component.
+    pub fn mask(&self) -> &BitSet {
+        &self.mask
+    }
+
+    /// Returns the number of components.
+    pub fn len(&self) -> usize {
+        self.entities.len()
+    }
+
+    /// Returns a joinable wrapper which, unlike `&DynamicStorage`, doesn't
+    /// skip the components of disabled entities. See
+    /// `EntitiesRes::set_enabled`.
+    pub fn with_disabled(&self) -> WithDisabled<&Self> {
+        WithDisabled(self)
+    }
+
+    /// Returns a joinable wrapper which, unlike `&mut DynamicStorage`,
+    /// doesn't skip the components of disabled entities. See
+    /// `EntitiesRes::set_enabled`.
+    pub fn with_disabled_mut(&mut self) -> WithDisabled<&mut Self> {
+        WithDisabled(self)
+    }
+
+    /// Returns `true` if there are no components.
+    pub fn is_empty(&self) -> bool {
+        self.entities.is_empty()
+    }
+
+    /// Returns `true` if `e` has this component.
+    pub fn contains(&self, e: Entity) -> bool {
+        self.row(e).is_some()
+    }
+
+    /// Returns the component value of `e`.
+    pub fn get(&self, e: Entity) -> Option<&[u8]> {
+        let size = self.size;
+
+        self.row(e).map(|row| &self.data[row * size..(row + 1) * size])
+    }
+
+    /// Returns the component value of `e` mutably.
+    pub fn get_mut(&mut self, e: Entity) -> Option<&mut [u8]> {
+        let size = self.size;
+
+        match self.row(e) {
+            Some(row) => Some(&mut self.data[row * size..(row + 1) * size]),
+            None => None,
+        }
+    }
+
+    /// Inserts the component value of `e`, returning the previous value.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `value` doesn't have the size of this component.
+    pub fn insert(
+        &mut self,
+        entities: &EntitiesRes,
+        e: Entity,
+        value: &[u8],
+    ) -> InsertResult<Vec<u8>> {
+        assert_eq!(
+            value.len(),
+            self.size,
+            "Value for dynamic component `{}` has the wrong size",
+            self.name
+        );
+
+        if !entities.is_alive(e) {
+            return Err(Error::WrongGeneration(WrongGeneration {
+                action: "insert component for entity",
+                actual_gen: entities.entity(e.id()).gen(),
+                entity: e,
+            }));
+        }
+
+        let id = e.id();
+        let size = self.size;
+        if self.mask.contains(id) {
+            let row = self.rows[id as usize] as usize;
+            self.entities[row] = e;
+            let old = &mut self.data[row * size..(row + 1) * size];
+            let previous = old.to_vec();
+            old.copy_from_slice(value);
+
+            return Ok(Some(previous));
+        }
+
+        if self.rows.len() <= id as usize {
+            self.rows.resize(id as usize + 1, 0);
+        }
+        self.mask.add(id);
+        self.rows[id as usize] = self.entities.len() as Index;
+        self.entities.push(e);
+        self.data.extend_from_slice(value);
+
+        Ok(None)
+    }
+
+    /// Removes the component of `e`, returning its value.
+    pub fn remove(&mut self, e: Entity) -> Option<Vec<u8>> {
+        if self.contains(e) {
+            self.remove_id(e.id())
+        } else {
+            None
+        }
+    }
+
+    fn row(&self, e: Entity) -> Option<usize> {
+        let id = e.id();
+        if !self.mask.contains(id) {
+            return None;
+        }
+
+        let row = self.rows[id as usize] as usize;
+        if self.entities[row] == e {
+            Some(row)
+        } else {
+            None
+        }
+    }
+
+    fn remove_id(&mut self, id: Index) -> Option<Vec<u8>> {
+        if !self.mask.remove(id) {
+            return None;
+        }
+
+        let size = self.size;
+        let row = self.rows[id as usize] as usize;
+        let last = self.entities.len() - 1;
+        let value = self.data[row * size..(row + 1) * size].to_vec();
+
+        self.data.copy_within(last * size..(last + 1) * size, row * size);
+        self.data.truncate(last * size);
+        self.entities.swap_remove(row);
+        if let Some(moved) = self.entities.get(row) {
+            self.rows[moved.id() as usize] = row as Index;
+        }
+
+        Some(value)
+    }
+}
+
+impl<'a> Join for &'a DynamicStorage {
+    type Mask = BitSetAnd<&'a BitSet, BitSetNot<&'a BitSet>>;
+    type Type = &'a [u8];
+    type Value = &'a DynamicStorage;
+
+    // SAFETY: No unsafe code and no invariants to fulfill.
+    unsafe fn open(self) -> (Self::Mask, Self::Value) {
+        (BitSetAnd(&self.mask, BitSetNot(&self.disabled)), self)
+    }
+
+    // SAFETY: Since we require that the mask was checked, `id` has a row.
+    unsafe fn get(v: &mut Self::Value, id: Index) -> &'a [u8] {
+        let storage: &'a DynamicStorage = v;
+        let start = storage.rows[id as usize] as usize * storage.size;
+
+        &storage.data[start..start + storage.size]
+    }
+}
+
+// SAFETY: `get` only reads.
+#[cfg(feature = "parallel")]
+unsafe impl ParJoin for &DynamicStorage {}
+
+/// The `Join::Value` of `&mut DynamicStorage`.
+///
+/// The data is kept as a raw pointer, so `get` doesn't create a `&mut`
+/// reference to all of it while other threads access their rows during a
+/// `ParJoin`.
+pub struct DynamicDataMut<'a> {
+    rows: &'a [Index],
+    data: *mut u8,
+    size: usize,
+    phantom: PhantomData<&'a mut [u8]>,
+}
+
+impl<'a> DynamicDataMut<'a> {
+    fn new(rows: &'a [Index], data: &'a mut [u8], size: usize) -> Self {
+        DynamicDataMut {
+            rows,
+            data: data.as_mut_ptr(),
+            size,
+            phantom: PhantomData,
+        }
+    }
+}
+
+// SAFETY: This is a mutable borrow of the data, which is `Send`.
+unsafe impl Send for DynamicDataMut<'_> {}
+
+impl<'a> Join for &'a mut DynamicStorage {
+    type Mask = BitSetAnd<&'a BitSet, BitSetNot<&'a BitSet>>;
+    type Type = &'a mut [u8];
+    type Value = DynamicDataMut<'a>;
+
+    // SAFETY: No unsafe code and no invariants to fulfill.
+    unsafe fn open(self) -> (Self::Mask, Self::Value) {
+        let DynamicStorage {
+            mask,
+            disabled,
+            data,
+            rows,
+            size,
+            ..
+        } = self;
+
+        (
+            BitSetAnd(mask, BitSetNot(disabled)),
+            DynamicDataMut::new(rows, data, *size),
+        )
+    }
+
+    // SAFETY: Since we require that the mask was checked, `id` has a row.
+    // Every id maps to a distinct row, so the returned slices don't overlap.
+    unsafe fn get(v: &mut Self::Value, id: Index) -> &'a mut [u8] {
+        let start = v.rows[id as usize] as usize * v.size;
+
+        slice::from_raw_parts_mut(v.data.add(start), v.size)
+    }
+}
+
+// SAFETY: Every id maps to a distinct row, so `get` never hands out
+// overlapping slices.
+#[cfg(feature = "parallel")]
+unsafe impl ParJoin for &mut DynamicStorage {}
+
+impl<'a> Join for WithDisabled<&'a DynamicStorage> {
+    type Mask = &'a BitSet;
+    type Type = &'a [u8];
+    type Value = &'a DynamicStorage;
+
+    // SAFETY: No unsafe code and no invariants to fulfill.
+    unsafe fn open(self) -> (Self::Mask, Self::Value) {
+        (&self.0.mask, self.0)
+    }
+
+    // SAFETY: See `Join for &DynamicStorage`.
+    unsafe fn get(v: &mut Self::Value, id: Index) -> &'a [u8] {
+        <&'a DynamicStorage as Join>::get(v, id)
+    }
+}
+
+// SAFETY: `get` only reads.
+#[cfg(feature = "parallel")]
+unsafe impl ParJoin for WithDisabled<&DynamicStorage> {}
+
+impl<'a> Join for WithDisabled<&'a mut DynamicStorage> {
+    type Mask = &'a BitSet;
+    type Type = &'a mut [u8];
+    type Value = DynamicDataMut<'a>;
+
+    // SAFETY: No unsafe code and no invariants to fulfill.
+    unsafe fn open(self) -> (Self::Mask, Self::Value) {
+        let DynamicStorage {
+            mask,
+            data,
+            rows,
+            size,
+            ..
+        } = self.0;
+
+        (mask, DynamicDataMut::new(rows, data, *size))
+    }
+
+    // SAFETY: See `Join for &mut DynamicStorage`.
+    unsafe fn get(v: &mut Self::Value, id: Index) -> &'a mut [u8] {
+        <&'a mut DynamicStorage as Join>::get(v, id)
+    }
+}
+
+// SAFETY: See `ParJoin for &mut DynamicStorage`.
+#[cfg(feature = "parallel")]
+unsafe impl ParJoin for WithDisabled<&mut DynamicStorage> {}
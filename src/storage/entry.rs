@@ -147,7 +147,7 @@ where
         // This is HACK. See implementation of Join for &'a mut Storage<'e, T, D> for
         // details why it is necessary.
         let storage: *mut Storage<'b, T, D> = *value as *mut Storage<'b, T, D>;
-        if (*storage).data.mask.contains(id) {
+        if (*storage).data.deref().mask.contains(id) {
             StorageEntry::Occupied(OccupiedEntry {
                 id,
                 storage: &mut *storage,
@@ -206,14 +206,15 @@ where
     }
 
     /// Inserts a value into the storage and returns the old one.
-    pub fn insert(&mut self, mut component: T) -> T {
-        std::mem::swap(&mut component, self.get_mut());
-        component
+    pub fn insert(&mut self, component: T) -> T {
+        let entity = self.storage.entities.entity(self.id);
+        self.storage.data.insert(entity, component).unwrap()
     }
 
     /// Removes the component from the storage and returns it.
     pub fn remove(self) -> T {
-        self.storage.data.remove(self.id).unwrap()
+        let entity = self.storage.entities.entity(self.id);
+        self.storage.data.remove_entity(entity).unwrap()
     }
 }
 
@@ -231,12 +232,10 @@ where
 {
     /// Inserts a value into the storage.
     pub fn insert(self, component: T) -> &'a mut T {
-        self.storage.data.mask.add(self.id);
-        // SAFETY: This is safe since we added `self.id` to the mask.
-        unsafe {
-            self.storage.data.inner.insert(self.id, component);
-            self.storage.data.inner.get_mut(self.id)
-        }
+        let entity = self.storage.entities.entity(self.id);
+        self.storage.data.insert(entity, component);
+        // SAFETY: This is safe since the component was just inserted.
+        unsafe { self.storage.data.inner.get_mut(self.id) }
     }
 }
 
@@ -218,6 +218,13 @@ impl<C: Component, T: UnprotectedStorage<C>> UnprotectedStorage<C> for FlaggedSt
         self.storage.get_mut(id)
     }
 
+    unsafe fn replace(&mut self, id: Index, comp: C) -> C {
+        if self.emit_event() {
+            self.channel.single_write(ComponentEvent::Modified(id));
+        }
+        self.storage.replace(id, comp)
+    }
+
     unsafe fn insert(&mut self, id: Index, comp: C) {
         if self.emit_event() {
             self.channel.single_write(ComponentEvent::Inserted(id));
@@ -231,6 +238,10 @@ impl<C: Component, T: UnprotectedStorage<C>> UnprotectedStorage<C> for FlaggedSt
         }
         self.storage.remove(id)
     }
+
+    fn maintain(&mut self) {
+        self.storage.maintain();
+    }
 }
 
 impl<C, T> Tracked for FlaggedStorage<C, T> {
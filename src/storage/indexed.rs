@@ -0,0 +1,267 @@
+use std::{
+    hash::Hash,
+    marker::PhantomData,
+    ops::{Deref, DerefMut},
+};
+
+use hashbrown::HashMap;
+use hibitset::{AtomicBitSet, BitSet, BitSetLike};
+
+use crate::{
+    join::Join,
+    storage::{
+        DenseVecStorage, DistinctStorage, MaskedStorage, Storage, TryDefault, UnprotectedStorage,
+    },
+    world::{Component, Index},
+};
+
+/// The key function of components stored in an `IndexedStorage<Self, K>`.
+pub trait IndexKey<K> {
+    /// Returns the key this component is indexed by.
+    fn index_key(&self) -> K;
+}
+
+/// `UnprotectedStorage`s that keep the indices of their components grouped
+/// by a key.
+pub trait Indexed {
+    /// The key the components are grouped by.
+    type Key;
+
+    /// Indices whose component has the key `key`.
+    fn with_key(&self, key: &Self::Key) -> &BitSet;
+    /// Updates the keys of all components accessed mutably since the last
+    /// update.
+    fn reindex(&mut self);
+}
+
+/// Wrapper storage maintaining a secondary index from the key returned by
+/// `IndexKey::index_key` to the indices of the components with that key.
+///
+/// The `BitSet` of a key is available through `Storage::with_key` and can
+/// be joined directly, so there is no need to scan all components for the
+/// ones with a certain key.
+///
+/// Insertions, including ones replacing a component, and removals update
+/// the index right away. Components accessed mutably, e.g. through
+/// `get_mut` or a join over `&mut storage`, can't be re-keyed until the
+/// access is over, so they are re-keyed on the next insertion or removal,
+/// on `Storage::reindex` and on `WorldExt::maintain`.
+///
+/// # Example
+///
+/// ```
+/// # use specs::prelude::*;
+/// # use specs::storage::{IndexKey, IndexedStorage};
+/// pub struct GridCell(i32, i32);
+///
+/// impl Component for GridCell {
+///     type Storage = IndexedStorage<Self, (i32, i32)>;
+/// }
+///
+/// impl IndexKey<(i32, i32)> for GridCell {
+///     fn index_key(&self) -> (i32, i32) {
+///         (self.0, self.1)
+///     }
+/// }
+///
+/// let mut world = World::new();
+/// world.register::<GridCell>();
+///
+/// let a = world.create_entity().with(GridCell(0, 0)).build();
+/// let b = world.create_entity().with(GridCell(0, 0)).build();
+///
+/// {
+///     let mut cells = world.write_storage::<GridCell>();
+///     cells.get_mut(b).unwrap().1 = 1;
+///     cells.reindex();
+/// }
+///
+/// let entities = world.entities();
+/// let cells = world.read_storage::<GridCell>();
+/// let origin: Vec<_> = (&entities, cells.with_key(&(0, 0))).join().map(|(e, _)| e).collect();
+/// assert_eq!(origin, vec![a]);
+/// ```
+pub struct IndexedStorage<C, K, T = DenseVecStorage<C>> {
+    storage: T,
+    index: HashMap<K, BitSet>,
+    keys: HashMap<Index, K>,
+    dirty: AtomicBitSet,
+    empty: BitSet,
+    phantom: PhantomData<C>,
+}
+
+impl<C, K, T> Default for IndexedStorage<C, K, T>
+where
+    T: TryDefault,
+{
+    fn default() -> Self {
+        IndexedStorage {
+            storage: T::unwrap_default(),
+            index: HashMap::new(),
+            keys: HashMap::new(),
+            dirty: AtomicBitSet::new(),
+            empty: BitSet::new(),
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<C, K, T> IndexedStorage<C, K, T>
+where
+    C: IndexKey<K>,
+    K: Clone + Eq + Hash,
+    T: UnprotectedStorage<C>,
+{
+    /// Re-keys the components accessed mutably since the last call.
+    ///
+    /// Every removal calls this first, so all dirty indices still have a
+    /// component.
+    fn flush(&mut self) {
+        if self.dirty.is_empty() {
+            return;
+        }
+
+        let dirty: Vec<Index> = (&self.dirty).iter().collect();
+        self.dirty.clear();
+        for id in dirty {
+            // SAFETY: Dirty indices were accessed through `get_mut` and
+            // haven't been removed since, see above.
+            let key = unsafe { self.storage.get(id) }.index_key();
+            if self.keys.get(&id) != Some(&key) {
+                self.unlink(id);
+                self.link(id, key);
+            }
+        }
+    }
+
+    fn link(&mut self, id: Index, key: K) {
+        self.index.entry(key.clone()).or_default().add(id);
+        self.keys.insert(id, key);
+    }
+
+    fn unlink(&mut self, id: Index) {
+        if let Some(key) = self.keys.remove(&id) {
+            let now_empty = match self.index.get_mut(&key) {
+                Some(ids) => {
+                    ids.remove(id);
+                    ids.is_empty()
+                }
+                None => false,
+            };
+            if now_empty {
+                self.index.remove(&key);
+            }
+        }
+    }
+}
+
+impl<C, K, T> UnprotectedStorage<C> for IndexedStorage<C, K, T>
+where
+    C: Component + IndexKey<K>,
+    K: Clone + Eq + Hash,
+    T: UnprotectedStorage<C>,
+{
+    unsafe fn clean<B>(&mut self, has: B)
+    where
+        B: BitSetLike,
+    {
+        self.storage.clean(has);
+        self.index.clear();
+        self.keys.clear();
+        self.dirty.clear();
+    }
+
+    unsafe fn get(&self, id: Index) -> &C {
+        self.storage.get(id)
+    }
+
+    unsafe fn get_mut(&mut self, id: Index) -> &mut C {
+        // Parallel joins call this through aliased pointers, so the bit has to
+        // be set atomically.
+        self.dirty.add_atomic(id);
+        self.storage.get_mut(id)
+    }
+
+    unsafe fn insert(&mut self, id: Index, comp: C) {
+        self.flush();
+        self.link(id, comp.index_key());
+        self.storage.insert(id, comp);
+    }
+
+    unsafe fn replace(&mut self, id: Index, comp: C) -> C {
+        self.flush();
+        self.unlink(id);
+        self.link(id, comp.index_key());
+        self.storage.replace(id, comp)
+    }
+
+    unsafe fn remove(&mut self, id: Index) -> C {
+        self.flush();
+        self.unlink(id);
+        self.storage.remove(id)
+    }
+
+    unsafe fn drop(&mut self, id: Index) {
+        self.flush();
+        self.unlink(id);
+        self.storage.drop(id);
+    }
+
+    fn maintain(&mut self) {
+        self.flush();
+        self.storage.maintain();
+    }
+}
+
+// SAFETY: `get_mut` only modifies the inner storage, which is
+// `DistinctStorage`, and sets the dirty bit atomically.
+unsafe impl<C, K, T> DistinctStorage for IndexedStorage<C, K, T> where T: DistinctStorage {}
+
+impl<C, K, T> Indexed for IndexedStorage<C, K, T>
+where
+    C: Component + IndexKey<K>,
+    K: Clone + Eq + Hash,
+    T: UnprotectedStorage<C>,
+{
+    type Key = K;
+
+    fn with_key(&self, key: &K) -> &BitSet {
+        self.index.get(key).unwrap_or(&self.empty)
+    }
+
+    fn reindex(&mut self) {
+        self.flush();
+    }
+}
+
+impl<'e, T, D> Storage<'e, T, D>
+where
+    T: Component,
+    T::Storage: Indexed,
+    D: Deref<Target = MaskedStorage<T>>,
+{
+    /// Returns the indices whose component has the key `key`.
+    ///
+    /// Components accessed mutably since the last `Storage::reindex` may
+    /// still be listed under their previous key.
+    pub fn with_key(&self, key: &<T::Storage as Indexed>::Key) -> &BitSet {
+        // SAFETY: Only the storage is used and the mask is dropped, so
+        // neither can be swapped out.
+        unsafe { self.open() }.1.with_key(key)
+    }
+}
+
+impl<'e, T, D> Storage<'e, T, D>
+where
+    T: Component,
+    T::Storage: Indexed,
+    D: DerefMut<Target = MaskedStorage<T>>,
+{
+    /// Updates the keys of all components accessed mutably since the last
+    /// update.
+    pub fn reindex(&mut self) {
+        // SAFETY: Only the storage is used and the mask is dropped, so
+        // neither can be swapped out.
+        unsafe { self.open() }.1.reindex();
+    }
+}
@@ -1,16 +1,24 @@
 //! Component storage types, implementations for component joins, etc.
 
 pub use self::{
+    change_tracked::{ChangeGuard, ChangeTracked, ChangeTrackedStorage, TrackMut},
     data::{ReadStorage, WriteStorage},
+    dynamic::{DynamicComponentId, DynamicComponents, DynamicDataMut, DynamicStorage},
     entry::{Entries, OccupiedEntry, StorageEntry, VacantEntry},
     flagged::FlaggedStorage,
     generic::{GenericReadStorage, GenericWriteStorage},
+    indexed::{IndexKey, Indexed, IndexedStorage},
     restrict::{
         ImmutableParallelRestriction, MutableParallelRestriction, RestrictedStorage,
         SequentialRestriction, PairedStorage
     },
+    soa::{
+        Chunk, ChunkedSlice, ReadSoa, Soa, SoaColumns, SoaColumnsMut, SoaComponent, SoaRows,
+        SoaStorage, WriteSoa,
+    },
     storages::{
-        BTreeStorage, DefaultVecStorage, DenseVecStorage, HashMapStorage, NullStorage, VecStorage,
+        BTreeStorage, DefaultVecStorage, DenseVecStorage, HashMapStorage, NullStorage,
+        SortableStorage, SparseSetStorage, VecStorage,
     },
     track::{ComponentEvent, Tracked},
 };
@@ -19,29 +27,45 @@ use self::storages::SliceAccess;
 
 use std::{
     self,
+    any::{Any, TypeId},
+    cmp::Ordering,
     marker::PhantomData,
     ops::{Deref, DerefMut, Not},
 };
 
-use hibitset::{BitSet, BitSetLike, BitSetNot};
-use shred::{CastFrom, Fetch};
+use hibitset::{BitSet, BitSetAnd, BitSetLike, BitSetNot};
+use shred::{CastFrom, Fetch, World};
 
+#[cfg(feature = "reflect")]
+use crate::error::ReflectError;
 #[cfg(feature = "parallel")]
 use crate::join::ParJoin;
+#[cfg(feature = "parallel")]
+use rayon::iter::{
+    IndexedParallelIterator, IntoParallelRefIterator, IntoParallelRefMutIterator, ParallelIterator,
+};
+#[cfg(feature = "reflect")]
+use serde::{de::DeserializeOwned, Serialize};
+#[cfg(feature = "reflect")]
+use serde_json::Value;
 use crate::{
     error::{Error, WrongGeneration},
-    join::Join,
-    world::{Component, EntitiesRes, Entity, Generation, Index},
+    join::{Join, OrderedJoinIter},
+    world::{Component, EntitiesRes, Entity, Generation, Index, LazyUpdate, WithDisabled},
 };
 
 use self::drain::Drain;
 
+mod change_tracked;
 mod data;
 mod drain;
+mod dynamic;
 mod entry;
 mod flagged;
 mod generic;
+mod indexed;
 mod restrict;
+mod soa;
 mod storages;
 #[cfg(test)]
 mod tests;
@@ -73,9 +97,119 @@ unsafe impl<'a> DistinctStorage for AntiStorage<'a> {}
 unsafe impl<'a> ParJoin for AntiStorage<'a> {}
 
 /// A dynamic storage.
+///
+/// Every registered storage is part of the `MetaTable<dyn AnyStorage>`,
+/// which allows operating on the components of an entity without knowing
+/// their types, for example to list them in an editor:
+///
+/// ```
+/// # use specs::prelude::*;
+/// # use specs::{shred::MetaTable, storage::AnyStorage};
+/// # struct Pos(f32); impl Component for Pos { type Storage = VecStorage<Self>; }
+/// # struct Vel(f32); impl Component for Vel { type Storage = VecStorage<Self>; }
+/// let mut world = World::new();
+/// world.register::<Pos>();
+/// world.register::<Vel>();
+///
+/// let e = world.create_entity().with(Pos(0.0)).build();
+///
+/// let table = world.fetch::<MetaTable<dyn AnyStorage>>();
+/// let names: Vec<_> = table
+///     .iter(&world)
+///     .filter(|storage| storage.has(e))
+///     .map(|storage| storage.type_name())
+///     .collect();
+/// assert_eq!(names.len(), 1);
+/// assert!(names[0].ends_with("Pos"));
+/// ```
+///
+/// Only `remove` and `deserialize_into` check whether the passed entity is
+/// alive; the other methods only use its id.
+///
+/// All methods except `drop` have default implementations, which treat the
+/// storage as empty, so existing implementors keep compiling.
 pub trait AnyStorage {
     /// Drop components of given entities.
     fn drop(&mut self, entities: &[Entity]);
+
+    /// Called once per `WorldExt::maintain`, before any entities are
+    /// deleted. Does nothing by default.
+    fn maintain(&mut self) {}
+
+    /// Returns `true` if this storage has a component for `entity`.
+    ///
+    /// Returns `false` by default, so storages which don't override this
+    /// aren't listed by `WorldExt::components_of`.
+    fn has(&self, _entity: Entity) -> bool {
+        false
+    }
+
+    /// Returns the `TypeId` of the stored component type.
+    ///
+    /// Returns the `TypeId` of `()` by default.
+    fn component_type(&self) -> TypeId {
+        TypeId::of::<()>()
+    }
+
+    /// Returns the name of the stored component type, as returned by
+    /// `std::any::type_name`.
+    ///
+    /// Returns `"unknown"` by default.
+    fn type_name(&self) -> &'static str {
+        "unknown"
+    }
+
+    /// Removes the component of `entity`, returning `true` if there was one.
+    ///
+    /// Fails if `entity` isn't alive. Removes nothing by default.
+    fn remove(&mut self, entities: &EntitiesRes, entity: Entity) -> Result<bool, WrongGeneration> {
+        check_alive(entities, entity, "remove component of")?;
+
+        Ok(false)
+    }
+
+    /// Serializes the component of `entity`, returning `None` if it has
+    /// none.
+    ///
+    /// Fails with `ReflectError::NotReflectable` unless the component was
+    /// registered through `WorldExt::register_reflect`.
+    #[cfg(feature = "reflect")]
+    fn serialize_for(&self, _entity: Entity) -> Result<Option<Value>, ReflectError> {
+        Err(ReflectError::NotReflectable(self.type_name()))
+    }
+
+    /// Deserializes `value` and inserts it as the component of `entity`,
+    /// replacing the previous one.
+    ///
+    /// Fails with `ReflectError::NotReflectable` unless the component was
+    /// registered through `WorldExt::register_reflect`, and with
+    /// `ReflectError::WrongGeneration` if `entity` isn't alive.
+    #[cfg(feature = "reflect")]
+    fn deserialize_into(
+        &mut self,
+        _entities: &EntitiesRes,
+        _entity: Entity,
+        _value: Value,
+    ) -> Result<(), ReflectError> {
+        Err(ReflectError::NotReflectable(self.type_name()))
+    }
+}
+
+/// Fails with `WrongGeneration` if `entity` isn't alive, naming `action`.
+pub(crate) fn check_alive(
+    entities: &EntitiesRes,
+    entity: Entity,
+    action: &'static str,
+) -> Result<(), WrongGeneration> {
+    if entities.is_alive(entity) {
+        Ok(())
+    } else {
+        Err(WrongGeneration {
+            action,
+            actual_gen: entities.entity(entity.id()).gen(),
+            entity,
+        })
+    }
 }
 
 unsafe impl<T> CastFrom<T> for dyn AnyStorage
@@ -97,8 +231,212 @@ where
 {
     fn drop(&mut self, entities: &[Entity]) {
         for entity in entities {
-            MaskedStorage::drop(self, entity.id());
+            MaskedStorage::drop_entity(self, *entity);
+        }
+    }
+
+    fn maintain(&mut self) {
+        self.inner.maintain();
+    }
+
+    fn has(&self, entity: Entity) -> bool {
+        self.mask.contains(entity.id())
+    }
+
+    fn component_type(&self) -> TypeId {
+        TypeId::of::<T>()
+    }
+
+    fn type_name(&self) -> &'static str {
+        std::any::type_name::<T>()
+    }
+
+    fn remove(&mut self, entities: &EntitiesRes, entity: Entity) -> Result<bool, WrongGeneration> {
+        check_alive(entities, entity, "remove component of")?;
+
+        Ok(MaskedStorage::remove_entity(self, entity).is_some())
+    }
+
+    #[cfg(feature = "reflect")]
+    fn serialize_for(&self, entity: Entity) -> Result<Option<Value>, ReflectError> {
+        let reflect = self
+            .reflect
+            .ok_or_else(|| ReflectError::NotReflectable(self.type_name()))?;
+        let id = entity.id();
+        if !self.mask.contains(id) {
+            return Ok(None);
+        }
+
+        // SAFETY: We checked the mask, so all invariants are met.
+        let component = unsafe { self.inner.get(id) };
+        (reflect.serialize)(component)
+            .map(Some)
+            .map_err(ReflectError::Serde)
+    }
+
+    #[cfg(feature = "reflect")]
+    fn deserialize_into(
+        &mut self,
+        entities: &EntitiesRes,
+        entity: Entity,
+        value: Value,
+    ) -> Result<(), ReflectError> {
+        let reflect = self
+            .reflect
+            .ok_or_else(|| ReflectError::NotReflectable(self.type_name()))?;
+        check_alive(entities, entity, "insert component for")?;
+        let component = (reflect.deserialize)(value).map_err(ReflectError::Serde)?;
+        self.insert(entity, component);
+
+        Ok(())
+    }
+}
+
+/// A dynamic storage whose components can be cloned.
+///
+/// Storages are only registered as `CloneStorage` if requested through
+/// `WorldExt::register_cloneable`. This is what `WorldExt::snapshot` and
+/// `WorldExt::restore` use to copy the components of a `World`.
+pub trait CloneStorage {
+    /// Returns the `TypeId` of the component type stored.
+    fn component_type(&self) -> TypeId;
+
+    /// Clones all components of this storage.
+    fn snapshot(&self) -> StorageSnapshot;
+
+    /// Replaces all components of this storage with clones of the ones in
+    /// `snapshot`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `snapshot` was taken from a storage of another component
+    /// type.
+    fn restore(&mut self, snapshot: &StorageSnapshot);
+
+    /// Inserts a clone of the component of `from` for `to`, replacing the
+    /// component `to` had before. Returns `false` if `from` has no
+    /// component. Only the ids of the entities are used.
+    ///
+    /// Clones nothing and returns `false` by default.
+    fn clone_component(&mut self, _from: Entity, _to: Entity) -> bool {
+        false
+    }
+}
+
+unsafe impl<T> CastFrom<T> for dyn CloneStorage
+where
+    T: CloneStorage + 'static,
+{
+    fn cast(t: &T) -> &Self {
+        t
+    }
+
+    fn cast_mut(t: &mut T) -> &mut Self {
+        t
+    }
+}
+
+/// The components of a single storage, as cloned by
+/// `CloneStorage::snapshot`.
+#[cfg(feature = "parallel")]
+pub struct StorageSnapshot(Box<dyn Any + Send + Sync>);
+
+/// The components of a single storage, as cloned by
+/// `CloneStorage::snapshot`.
+#[cfg(not(feature = "parallel"))]
+pub struct StorageSnapshot(Box<dyn Any>);
+
+impl<T> MaskedStorage<T>
+where
+    T: Component + Clone,
+{
+    fn components(&self) -> Vec<(Index, T)> {
+        (&self.mask)
+            .iter()
+            // SAFETY: We just got the index from the mask.
+            .map(|id| (id, unsafe { self.inner.get(id) }.clone()))
+            .collect()
+    }
+
+    fn restore_components(&mut self, snapshot: &StorageSnapshot) {
+        let components = snapshot
+            .0
+            .downcast_ref::<Vec<(Index, T)>>()
+            .expect("Tried to restore a storage from the snapshot of another component type");
+
+        // Components are dropped one by one so tracked storages emit events.
+        // Restoring doesn't invoke the component hooks.
+        let ids: Vec<Index> = (&self.mask).iter().collect();
+        for id in ids {
+            self.mask.remove(id);
+            // SAFETY: `id` was in the mask.
+            unsafe {
+                self.inner.drop(id);
+            }
         }
+        for (id, component) in components {
+            self.mask.add(*id);
+            // SAFETY: The mask was cleared above, so it is safe to insert.
+            unsafe {
+                self.inner.insert(*id, component.clone());
+            }
+        }
+    }
+
+    fn clone_id(&mut self, from: Index, to: Entity) -> bool {
+        if !self.mask.contains(from) {
+            return false;
+        }
+
+        // SAFETY: We checked the mask, so all invariants are met.
+        let component = unsafe { self.inner.get(from) }.clone();
+        self.insert(to, component);
+
+        true
+    }
+}
+
+#[cfg(feature = "parallel")]
+impl<T> CloneStorage for MaskedStorage<T>
+where
+    T: Component + Clone + Send + Sync,
+{
+    fn component_type(&self) -> TypeId {
+        TypeId::of::<T>()
+    }
+
+    fn snapshot(&self) -> StorageSnapshot {
+        StorageSnapshot(Box::new(self.components()))
+    }
+
+    fn restore(&mut self, snapshot: &StorageSnapshot) {
+        self.restore_components(snapshot);
+    }
+
+    fn clone_component(&mut self, from: Entity, to: Entity) -> bool {
+        self.clone_id(from.id(), to)
+    }
+}
+
+#[cfg(not(feature = "parallel"))]
+impl<T> CloneStorage for MaskedStorage<T>
+where
+    T: Component + Clone,
+{
+    fn component_type(&self) -> TypeId {
+        TypeId::of::<T>()
+    }
+
+    fn snapshot(&self) -> StorageSnapshot {
+        StorageSnapshot(Box::new(self.components()))
+    }
+
+    fn restore(&mut self, snapshot: &StorageSnapshot) {
+        self.restore_components(snapshot);
+    }
+
+    fn clone_component(&mut self, from: Entity, to: Entity) -> bool {
+        self.clone_id(from.id(), to)
     }
 }
 
@@ -134,8 +472,29 @@ pub type InsertResult<T> = Result<Option<T>, Error>;
 pub struct MaskedStorage<T: Component> {
     mask: BitSet,
     inner: T::Storage,
+    lazy: LazyUpdate,
+    #[cfg(feature = "reflect")]
+    reflect: Option<Reflect<T>>,
+}
+
+/// The (de)serialization functions of a component registered through
+/// `WorldExt::register_reflect`.
+#[cfg(feature = "reflect")]
+struct Reflect<T> {
+    serialize: fn(&T) -> serde_json::Result<Value>,
+    deserialize: fn(Value) -> serde_json::Result<T>,
+}
+
+#[cfg(feature = "reflect")]
+impl<T> Clone for Reflect<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
 }
 
+#[cfg(feature = "reflect")]
+impl<T> Copy for Reflect<T> {}
+
 impl<T: Component> Default for MaskedStorage<T>
 where
     T::Storage: Default,
@@ -144,6 +503,9 @@ where
         Self {
             mask: Default::default(),
             inner: Default::default(),
+            lazy: Default::default(),
+            #[cfg(feature = "reflect")]
+            reflect: None,
         }
     }
 }
@@ -155,6 +517,36 @@ impl<T: Component> MaskedStorage<T> {
         MaskedStorage {
             mask: BitSet::new(),
             inner,
+            lazy: Default::default(),
+            #[cfg(feature = "reflect")]
+            reflect: None,
+        }
+    }
+
+    /// Makes the components of this storage accessible through
+    /// `AnyStorage::serialize_for` and `AnyStorage::deserialize_into`.
+    /// This is called by `WorldExt::register_reflect`.
+    #[cfg(feature = "reflect")]
+    pub fn enable_reflect(&mut self)
+    where
+        T: Serialize + DeserializeOwned,
+    {
+        fn serialize<T: Serialize>(component: &T) -> serde_json::Result<Value> {
+            serde_json::to_value(component)
+        }
+
+        self.reflect = Some(Reflect {
+            serialize: serialize::<T>,
+            deserialize: serde_json::from_value::<T>,
+        });
+    }
+
+    /// Lets the hooks of `T` queue work on the `LazyUpdate` of `world`.
+    /// This is called when the component is registered; until then, the
+    /// work is queued on a `LazyUpdate` of the storage which is never run.
+    pub(crate) fn connect_lazy(world: &World) {
+        if let Some(lazy) = world.try_fetch::<LazyUpdate>() {
+            world.fetch_mut::<MaskedStorage<T>>().lazy = lazy.share();
         }
     }
 
@@ -163,6 +555,9 @@ impl<T: Component> MaskedStorage<T> {
     }
 
     /// Clear the contents of this storage.
+    ///
+    /// This doesn't invoke `Component::on_remove`, in contrast to
+    /// `Storage::clear`.
     pub fn clear(&mut self) {
         // SAFETY: `self.mask` is the correct mask as specified.
         unsafe {
@@ -171,7 +566,31 @@ impl<T: Component> MaskedStorage<T> {
         self.mask.clear();
     }
 
+    /// Inserts a component for `entity`, invoking `Component::on_insert`
+    /// for the new component and then `Component::on_remove` for the
+    /// replaced one. Only the id of `entity` is used to locate the
+    /// component.
+    pub fn insert(&mut self, entity: Entity, mut component: T) -> Option<T> {
+        let id = entity.id();
+        if self.mask.contains(id) {
+            component.on_insert(entity, &self.lazy);
+            // SAFETY: We checked the mask, so all invariants are met.
+            let mut old = unsafe { self.inner.replace(id, component) };
+            old.on_remove(entity, &self.lazy);
+            Some(old)
+        } else {
+            component.on_insert(entity, &self.lazy);
+            self.mask.add(id);
+            // SAFETY: The mask was previously empty, so it is safe to insert.
+            unsafe { self.inner.insert(id, component) };
+            None
+        }
+    }
+
     /// Remove an element by a given index.
+    ///
+    /// This doesn't invoke `Component::on_remove`, in contrast to
+    /// `remove_entity`.
     pub fn remove(&mut self, id: Index) -> Option<T> {
         if self.mask.remove(id) {
             // SAFETY: We checked the mask (`remove` returned `true`)
@@ -182,6 +601,9 @@ impl<T: Component> MaskedStorage<T> {
     }
 
     /// Drop an element by a given index.
+    ///
+    /// This doesn't invoke `Component::on_remove`, in contrast to
+    /// `drop_entity`.
     pub fn drop(&mut self, id: Index) {
         if self.mask.remove(id) {
             // SAFETY: We checked the mask (`remove` returned `true`)
@@ -190,6 +612,21 @@ impl<T: Component> MaskedStorage<T> {
             }
         }
     }
+
+    /// Removes the component of `entity`, invoking `Component::on_remove`.
+    /// Only the id of `entity` is used to locate the component.
+    pub fn remove_entity(&mut self, entity: Entity) -> Option<T> {
+        let mut component = self.remove(entity.id())?;
+        component.on_remove(entity, &self.lazy);
+
+        Some(component)
+    }
+
+    /// Drops the component of `entity`, invoking `Component::on_remove`.
+    /// Only the id of `entity` is used to locate the component.
+    pub fn drop_entity(&mut self, entity: Entity) {
+        self.remove_entity(entity);
+    }
 }
 
 impl<T: Component> Drop for MaskedStorage<T> {
@@ -270,6 +707,12 @@ where
     pub fn mask(&self) -> &BitSet {
         &self.data.mask
     }
+
+    /// Returns a joinable wrapper which, unlike `&Storage`, doesn't skip the
+    /// components of disabled entities. See `EntitiesRes::set_enabled`.
+    pub fn with_disabled(&self) -> WithDisabled<&Self> {
+        WithDisabled(self)
+    }
 }
 
 impl<'e, T, D> Storage<'e, T, D>
@@ -302,6 +745,205 @@ where
     }
 }
 
+impl<'e, T, D> Storage<'e, T, D>
+where
+    T: Component,
+    D: Deref<Target = MaskedStorage<T>>,
+    T::Storage: SortableStorage<T>,
+{
+    /// Joins `join` in the order of this storage's slice instead of the
+    /// order of the entity ids, yielding the joined values of all entities
+    /// which have a component in this storage.
+    ///
+    /// Together with `sort_by` this allows iterating in a custom order, like
+    /// the z-order of sprites. Include `self` in `join` to get the
+    /// components of this storage as well.
+    ///
+    /// Ids of `packed_ids` whose entity has no component in this storage
+    /// are skipped.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// # use specs::prelude::*;
+    /// # #[derive(Debug, PartialEq)]
+    /// # struct Depth(i32); impl Component for Depth { type Storage = DenseVecStorage<Self>; }
+    /// let mut world = World::new();
+    /// world.register::<Depth>();
+    ///
+    /// let a = world.create_entity().with(Depth(2)).build();
+    /// let b = world.create_entity().with(Depth(-1)).build();
+    /// let c = world.create_entity().with(Depth(5)).build();
+    ///
+    /// let mut depths = world.write_storage::<Depth>();
+    /// depths.sort_by_key(|d| d.0);
+    ///
+    /// let entities = world.entities();
+    /// let sorted: Vec<_> = depths
+    ///     .ordered_join((&entities, &depths))
+    ///     .map(|(e, _)| e)
+    ///     .collect();
+    /// assert_eq!(sorted, vec![b, a, c]);
+    /// ```
+    pub fn ordered_join<J>(&self, join: J) -> OrderedJoinIter<'_, J>
+    where
+        J: Join,
+    {
+        OrderedJoinIter::filtered(self.data.inner.packed_ids(), &self.data.mask, join)
+    }
+}
+
+impl<'e, T, D> Storage<'e, T, D>
+where
+    T: Component,
+    D: DerefMut<Target = MaskedStorage<T>>,
+    T::Storage: SortableStorage<T>,
+{
+    /// Sorts the components of this storage with a comparator function.
+    ///
+    /// This permutes the slice returned by `as_slice`, keeping the
+    /// components associated with their entities. The sort is stable.
+    /// Components inserted afterwards are not sorted, and removals may move
+    /// components as well (see the documentation of the storage), so sort
+    /// again before relying on the order.
+    pub fn sort_by<F>(&mut self, mut compare: F)
+    where
+        F: FnMut(&T, &T) -> Ordering,
+    {
+        let inner = &mut self.data.inner;
+        let mut order: Vec<usize> = (0..inner.as_slice().len()).collect();
+        {
+            let slice = inner.as_slice();
+            order.sort_by(|&a, &b| compare(&slice[a], &slice[b]));
+        }
+        inner.permute(&order);
+    }
+
+    /// Sorts the components of this storage with a key extraction function.
+    ///
+    /// See `sort_by` for details.
+    pub fn sort_by_key<K, F>(&mut self, mut f: F)
+    where
+        F: FnMut(&T) -> K,
+        K: Ord,
+    {
+        self.sort_by(|a, b| f(a).cmp(&f(b)));
+    }
+}
+
+#[cfg(feature = "parallel")]
+impl<'e, T, D> Storage<'e, T, D>
+where
+    T: Component + Sync,
+    D: Deref<Target = MaskedStorage<T>>,
+    T::Storage: SortableStorage<T>,
+{
+    /// Returns a parallel iterator over the entity ids and components of
+    /// this storage, in the order of its slice, skipping disabled entities.
+    ///
+    /// In contrast to `par_join`, this splits the work into batches of
+    /// components instead of ranges of entity ids. Skipping disabled
+    /// entities means the iterator isn't indexed; see
+    /// `par_packed_with_disabled` for an `IndexedParallelIterator`.
+    ///
+    /// For `DefaultVecStorage` this includes the default values of unused
+    /// slots; check `mask` if they need to be skipped.
+    pub fn par_packed(&self) -> impl ParallelIterator<Item = (Index, &T)> + '_ {
+        let disabled = self.entities.disabled();
+
+        self.par_packed_with_disabled()
+            .filter(move |&(id, _)| !disabled.contains(id))
+    }
+
+    /// Returns an indexed parallel iterator over the entity ids and
+    /// components of this storage, in the order of its slice, including the
+    /// components of disabled entities.
+    ///
+    /// This supports the adaptors of `IndexedParallelIterator`, like
+    /// `with_min_len`, `enumerate` and `zip`. See `par_packed` for details.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// # use specs::prelude::*;
+    /// use specs::rayon::prelude::*;
+    ///
+    /// # struct Mass(u32); impl Component for Mass { type Storage = DenseVecStorage<Self>; }
+    /// let mut world = World::new();
+    /// world.register::<Mass>();
+    /// for i in 0..1000 {
+    ///     world.create_entity().with(Mass(i)).build();
+    /// }
+    ///
+    /// let masses = world.read_storage::<Mass>();
+    /// let total: u32 = masses
+    ///     .par_packed_with_disabled()
+    ///     .with_min_len(128)
+    ///     .map(|(_, mass)| mass.0)
+    ///     .sum();
+    /// assert_eq!(total, 499_500);
+    /// ```
+    pub fn par_packed_with_disabled(
+        &self,
+    ) -> impl IndexedParallelIterator<Item = (Index, &T)> + '_ {
+        let inner = &self.data.inner;
+
+        inner
+            .packed_ids()
+            .par_iter()
+            .cloned()
+            .zip(inner.as_slice().par_iter())
+    }
+}
+
+#[cfg(feature = "parallel")]
+impl<'e, T, D> Storage<'e, T, D>
+where
+    T: Component + Send,
+    D: DerefMut<Target = MaskedStorage<T>>,
+    T::Storage: SortableStorage<T>,
+{
+    /// Returns a parallel iterator over the entity ids and mutable
+    /// components of this storage, in the order of its slice, skipping
+    /// disabled entities.
+    ///
+    /// See `par_packed` for details.
+    pub fn par_packed_mut(&mut self) -> impl ParallelIterator<Item = (Index, &mut T)> + '_ {
+        let disabled = self.entities.disabled();
+        let (ids, slice) = self.data.inner.packed_mut();
+
+        ids.par_iter()
+            .cloned()
+            .zip(slice.par_iter_mut())
+            .filter(move |&(id, _)| !disabled.contains(id))
+    }
+
+    /// Returns an indexed parallel iterator over the entity ids and mutable
+    /// components of this storage, in the order of its slice, including the
+    /// components of disabled entities.
+    ///
+    /// See `par_packed_with_disabled` for details.
+    pub fn par_packed_with_disabled_mut(
+        &mut self,
+    ) -> impl IndexedParallelIterator<Item = (Index, &mut T)> + '_ {
+        let (ids, slice) = self.data.inner.packed_mut();
+
+        ids.par_iter().cloned().zip(slice.par_iter_mut())
+    }
+}
+
+impl<'e, T, D> Storage<'e, T, D>
+where
+    T: Component<Storage = SparseSetStorage<T>>,
+    D: DerefMut<Target = MaskedStorage<T>>,
+{
+    /// Gets mutable access to the wrapped `SparseSetStorage` in order to
+    /// reorder its packed data.
+    pub fn sparse_set_mut(&mut self) -> &mut SparseSetStorage<T> {
+        &mut self.data.inner
+    }
+}
+
 impl<'e, T, D> Storage<'e, T, D>
 where
     T: Component,
@@ -334,19 +976,9 @@ where
     /// If a component already existed for the given `Entity`, then it will
     /// be overwritten with the new component. If it did overwrite, then the
     /// result will contain `Some(T)` where `T` is the previous component.
-    pub fn insert(&mut self, e: Entity, mut v: T) -> InsertResult<T> {
+    pub fn insert(&mut self, e: Entity, v: T) -> InsertResult<T> {
         if self.entities.is_alive(e) {
-            let id = e.id();
-            if self.data.mask.contains(id) {
-                // SAFETY: We checked the mask, so all invariants are met.
-                std::mem::swap(&mut v, unsafe { self.data.inner.get_mut(id) });
-                Ok(Some(v))
-            } else {
-                self.data.mask.add(id);
-                // SAFETY: The mask was previously empty, so it is safe to insert.
-                unsafe { self.data.inner.insert(id, v) };
-                Ok(None)
-            }
+            Ok(self.data.insert(e, v))
         } else {
             Err(Error::WrongGeneration(WrongGeneration {
                 action: "insert component for entity",
@@ -359,15 +991,30 @@ where
     /// Removes the data associated with an `Entity`.
     pub fn remove(&mut self, e: Entity) -> Option<T> {
         if self.entities.is_alive(e) {
-            self.data.remove(e.id())
+            self.data.remove_entity(e)
         } else {
             None
         }
     }
 
-    /// Clears the contents of the storage.
+    /// Returns a joinable wrapper which, unlike `&mut Storage`, doesn't skip
+    /// the components of disabled entities. See `EntitiesRes::set_enabled`.
+    pub fn with_disabled_mut(&mut self) -> WithDisabled<&mut Self> {
+        WithDisabled(self)
+    }
+
+    /// Clears the contents of the storage, invoking `Component::on_remove`
+    /// for every component.
+    ///
+    /// Every component is removed individually, so tracking storages record
+    /// the removals like for `remove`.
     pub fn clear(&mut self) {
-        self.data.clear();
+        let data = &mut *self.data;
+        let ids: Vec<Index> = (&data.mask).iter().collect();
+        for id in ids {
+            data.remove_entity(self.entities.entity(id));
+        }
+        data.clear();
     }
 
     /// Creates a draining storage wrapper which can be `.join`ed
@@ -375,6 +1022,7 @@ where
     pub fn drain(&mut self) -> Drain<T> {
         Drain {
             data: &mut self.data,
+            entities: &self.entities,
         }
     }
 }
@@ -397,13 +1045,15 @@ where
     T: Component,
     D: Deref<Target = MaskedStorage<T>>,
 {
-    type Mask = &'a BitSet;
+    type Mask = BitSetAnd<&'a BitSet, BitSetNot<&'a BitSet>>;
     type Type = &'a T;
     type Value = &'a T::Storage;
 
     // SAFETY: No unsafe code and no invariants.
     unsafe fn open(self) -> (Self::Mask, Self::Value) {
-        (&self.data.mask, &self.data.inner)
+        let mask = BitSetAnd(&self.data.mask, BitSetNot(self.entities.disabled()));
+
+        (mask, &self.data.inner)
     }
 
     // SAFETY: Since we require that the mask was checked, an element for `i` must
@@ -441,13 +1091,16 @@ where
     T: Component,
     D: DerefMut<Target = MaskedStorage<T>>,
 {
-    type Mask = &'a BitSet;
+    type Mask = BitSetAnd<&'a BitSet, BitSetNot<&'a BitSet>>;
     type Type = &'a mut T;
     type Value = &'a mut T::Storage;
 
     // SAFETY: No unsafe code and no invariants to fulfill.
     unsafe fn open(self) -> (Self::Mask, Self::Value) {
-        self.data.open_mut()
+        let disabled = self.entities.disabled();
+        let (mask, value) = self.data.open_mut();
+
+        (BitSetAnd(mask, BitSetNot(disabled)), value)
     }
 
     // TODO: audit unsafe
@@ -470,6 +1123,71 @@ where
 {
 }
 
+impl<'a, 'e, T, D> Join for WithDisabled<&'a Storage<'e, T, D>>
+where
+    T: Component,
+    D: Deref<Target = MaskedStorage<T>>,
+{
+    type Mask = &'a BitSet;
+    type Type = &'a T;
+    type Value = &'a T::Storage;
+
+    // SAFETY: No unsafe code and no invariants.
+    unsafe fn open(self) -> (Self::Mask, Self::Value) {
+        (&self.0.data.mask, &self.0.data.inner)
+    }
+
+    // SAFETY: Since we require that the mask was checked, an element for `i` must
+    // have been inserted without being removed.
+    unsafe fn get(v: &mut Self::Value, i: Index) -> &'a T {
+        v.get(i)
+    }
+}
+
+// SAFETY: This is always safe because immutable access can in no case cause
+// memory issues, even if access to common memory occurs.
+#[cfg(feature = "parallel")]
+unsafe impl<'a, 'e, T, D> ParJoin for WithDisabled<&'a Storage<'e, T, D>>
+where
+    T: Component,
+    D: Deref<Target = MaskedStorage<T>>,
+    T::Storage: Sync,
+{
+}
+
+impl<'a, 'e, T, D> Join for WithDisabled<&'a mut Storage<'e, T, D>>
+where
+    T: Component,
+    D: DerefMut<Target = MaskedStorage<T>>,
+{
+    type Mask = &'a BitSet;
+    type Type = &'a mut T;
+    type Value = &'a mut T::Storage;
+
+    // SAFETY: No unsafe code and no invariants to fulfill.
+    unsafe fn open(self) -> (Self::Mask, Self::Value) {
+        let storage = self.0;
+        storage.data.open_mut()
+    }
+
+    // TODO: audit unsafe
+    unsafe fn get(v: &mut Self::Value, i: Index) -> &'a mut T {
+        // See the `Join` implementation of `&mut Storage`.
+        let value: *mut Self::Value = v as *mut Self::Value;
+        (*value).get_mut(i)
+    }
+}
+
+// SAFETY: This is safe because of the `DistinctStorage` guarantees.
+#[cfg(feature = "parallel")]
+unsafe impl<'a, 'e, T, D> ParJoin for WithDisabled<&'a mut Storage<'e, T, D>>
+where
+    T: Component,
+    D: DerefMut<Target = MaskedStorage<T>>,
+    T::Storage: Sync + DistinctStorage,
+{
+}
+
 /// Tries to create a default value, returns an `Err` with the name of the
 /// storage and/or component if there's no default.
 pub trait TryDefault: Sized {
@@ -552,6 +1270,20 @@ pub trait UnprotectedStorage<T>: TryDefault {
     /// removed / dropped.
     unsafe fn remove(&mut self, id: Index) -> T;
 
+    /// Replaces the data associated with an `Index`, returning the previous
+    /// data.
+    /// Storages which keep state derived from the data, like
+    /// `IndexedStorage`, override this to update it.
+    /// Defaults to replacing the data returned by `get_mut`.
+    ///
+    /// # Safety
+    ///
+    /// May only be called if an element with `id` was `insert`ed and not yet
+    /// removed / dropped.
+    unsafe fn replace(&mut self, id: Index, value: T) -> T {
+        std::mem::replace(self.get_mut(id), value)
+    }
+
     /// Drops the data associated with an `Index`.
     /// This could be used when a more efficient implementation for it exists than `remove` when the data
     /// is no longer needed.
@@ -564,6 +1296,12 @@ pub trait UnprotectedStorage<T>: TryDefault {
     unsafe fn drop(&mut self, id: Index) {
         self.remove(id);
     }
+
+    /// Called once per `WorldExt::maintain`, allowing storages to reset
+    /// per-tick state like the bitsets of `ChangeTrackedStorage`.
+    /// Wrapper storages should forward this to the storage they wrap.
+    /// Defaults to doing nothing.
+    fn maintain(&mut self) {}
 }
 
 #[cfg(test)]
@@ -4,7 +4,7 @@ use std::{
     ops::{Deref, DerefMut},
 };
 
-use hibitset::BitSet;
+use hibitset::{BitSet, BitSetAnd, BitSetNot};
 use shred::Fetch;
 
 use crate::join::Join;
@@ -106,13 +106,14 @@ where
     S: Borrow<C::Storage>,
     B: Borrow<BitSet>,
 {
-    type Mask = &'rf BitSet;
+    type Mask = BitSetAnd<&'rf BitSet, BitSetNot<&'rf BitSet>>;
     type Type = PairedStorage<'rf, 'st, C, &'rf C::Storage, &'rf BitSet, Restrict>;
     type Value = (&'rf C::Storage, &'rf Fetch<'st, EntitiesRes>, &'rf BitSet);
 
     unsafe fn open(self) -> (Self::Mask, Self::Value) {
         let bitset = self.bitset.borrow();
-        (bitset, (self.data.borrow(), self.entities, bitset))
+        let mask = BitSetAnd(bitset, BitSetNot(self.entities.disabled()));
+        (mask, (self.data.borrow(), self.entities, bitset))
     }
 
     unsafe fn get(value: &mut Self::Value, id: Index) -> Self::Type {
@@ -133,7 +134,7 @@ where
     S: BorrowMut<C::Storage>,
     B: Borrow<BitSet>,
 {
-    type Mask = &'rf BitSet;
+    type Mask = BitSetAnd<&'rf BitSet, BitSetNot<&'rf BitSet>>;
     type Type = PairedStorage<'rf, 'st, C, &'rf mut C::Storage, &'rf BitSet, Restrict>;
     type Value = (
         &'rf mut C::Storage,
@@ -143,7 +144,8 @@ where
 
     unsafe fn open(self) -> (Self::Mask, Self::Value) {
         let bitset = self.bitset.borrow();
-        (bitset, (self.data.borrow_mut(), self.entities, bitset))
+        let mask = BitSetAnd(bitset, BitSetNot(self.entities.disabled()));
+        (mask, (self.data.borrow_mut(), self.entities, bitset))
     }
 
     unsafe fn get(value: &mut Self::Value, id: Index) -> Self::Type {
@@ -0,0 +1,624 @@
+use std::{
+    any::TypeId,
+    marker::PhantomData,
+    ops::{Deref, DerefMut},
+    slice,
+};
+
+use hibitset::{BitSet, BitSetAnd, BitSetNot};
+use shred::{Fetch, FetchMut, MetaTable, Resource, ResourceId, SystemData, World};
+
+#[cfg(feature = "parallel")]
+use crate::join::ParJoin;
+use crate::{
+    error::{Error, WrongGeneration},
+    join::Join,
+    storage::{check_alive, AnyStorage},
+    world::{EntitiesRes, Entity, Index, WithDisabled},
+};
+
+/// Components which are stored as a structure of arrays, with one column
+/// per field.
+///
+/// This is usually implemented with `#[derive(SoaComponent)]`, which also
+/// generates the `Columns` type: for a struct `Motion`, it generates
+/// `MotionColumns` holding a `Vec` per field, and `MotionSlices` /
+/// `MotionSlicesMut` with a slice per field, which are returned by
+/// `MotionColumns::slices` and `SoaColumnsMut::slices_mut`.
+///
+/// Such components are not stored in a `MaskedStorage`, but in a
+/// `SoaStorage`, accessed through `ReadSoa` and `WriteSoa`.
+pub trait SoaComponent: Sized + 'static {
+    /// The columns storing the fields of this component.
+    type Columns: SoaColumns<Self>;
+}
+
+/// The columns of a `SoaComponent`, all of which have the same length.
+///
+/// Rows are not associated with entities; that's done by `SoaStorage`.
+pub trait SoaColumns<T>: Default {
+    /// Returns the number of rows.
+    fn len(&self) -> usize;
+
+    /// Returns `true` if there are no rows.
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Appends a row.
+    fn push(&mut self, value: T);
+
+    /// Removes a row, replacing it with the last row.
+    fn swap_remove(&mut self, row: usize) -> T;
+
+    /// Replaces a row, returning the previous value.
+    fn replace(&mut self, row: usize, value: T) -> T;
+}
+
+/// Mutable access to the columns of a `SoaComponent` as slices, which can
+/// be written to but not resized or reordered.
+///
+/// The lifetime parameter stands in for a generic associated type.
+pub trait SoaColumnsMut<'a> {
+    /// The columns as mutable slices, e.g. `MotionSlicesMut<'a>`.
+    type SlicesMut;
+
+    /// Returns the columns as mutable slices.
+    fn slices_mut(&'a mut self) -> Self::SlicesMut;
+}
+
+/// Storage of a `SoaComponent`, keeping the rows of its columns packed.
+///
+/// The row of an entity is not stable: removing a component moves the last
+/// row into its place. `entities` returns the entity of every row, so the
+/// columns can be iterated in lock-step with it.
+pub struct SoaStorage<T: SoaComponent> {
+    mask: BitSet,
+    columns: T::Columns,
+    entities: Vec<Entity>,
+    rows: Vec<Index>,
+}
+
+impl<T: SoaComponent> Default for SoaStorage<T> {
+    fn default() -> Self {
+        SoaStorage {
+            mask: BitSet::new(),
+            columns: Default::default(),
+            entities: Vec::new(),
+            rows: Vec::new(),
+        }
+    }
+}
+
+impl<T: SoaComponent> SoaStorage<T> {
+    /// Returns the mask of entity ids having a component.
+    pub fn mask(&self) -> &BitSet {
+        &self.mask
+    }
+
+    /// Returns the number of components.
+    pub fn len(&self) -> usize {
+        self.entities.len()
+    }
+
+    /// Returns `true` if there are no components.
+    pub fn is_empty(&self) -> bool {
+        self.entities.is_empty()
+    }
+
+    /// Returns the entity of every row.
+    pub fn entities(&self) -> &[Entity] {
+        &self.entities
+    }
+
+    /// Returns the columns.
+    pub fn columns(&self) -> &T::Columns {
+        &self.columns
+    }
+
+    /// Returns the columns as mutable slices of fixed length; insert and
+    /// remove components through `WriteSoa` instead.
+    pub fn columns_mut<'a>(&'a mut self) -> <T::Columns as SoaColumnsMut<'a>>::SlicesMut
+    where
+        T::Columns: SoaColumnsMut<'a>,
+    {
+        self.columns.slices_mut()
+    }
+
+    /// Returns the row of the component of `e`.
+    pub fn row(&self, e: Entity) -> Option<usize> {
+        let id = e.id();
+        if !self.mask.contains(id) {
+            return None;
+        }
+
+        let row = self.rows[id as usize] as usize;
+        if self.entities[row] == e {
+            Some(row)
+        } else {
+            None
+        }
+    }
+
+    /// Returns `true` if `e` has a component.
+    pub fn contains(&self, e: Entity) -> bool {
+        self.row(e).is_some()
+    }
+
+    fn insert(&mut self, e: Entity, value: T) -> Option<T> {
+        let id = e.id();
+        if self.mask.contains(id) {
+            let row = self.rows[id as usize] as usize;
+            self.entities[row] = e;
+
+            return Some(self.columns.replace(row, value));
+        }
+
+        if self.rows.len() <= id as usize {
+            self.rows.resize(id as usize + 1, 0);
+        }
+        self.mask.add(id);
+        self.rows[id as usize] = self.entities.len() as Index;
+        self.entities.push(e);
+        self.columns.push(value);
+
+        None
+    }
+
+    fn remove_id(&mut self, id: Index) -> Option<T> {
+        if !self.mask.remove(id) {
+            return None;
+        }
+
+        let row = self.rows[id as usize] as usize;
+        self.entities.swap_remove(row);
+        if let Some(moved) = self.entities.get(row) {
+            self.rows[moved.id() as usize] = row as Index;
+        }
+
+        Some(self.columns.swap_remove(row))
+    }
+}
+
+impl<T> AnyStorage for SoaStorage<T>
+where
+    T: SoaComponent,
+{
+    fn drop(&mut self, entities: &[Entity]) {
+        for entity in entities {
+            self.remove_id(entity.id());
+        }
+    }
+
+    fn has(&self, entity: Entity) -> bool {
+        self.mask.contains(entity.id())
+    }
+
+    fn component_type(&self) -> TypeId {
+        TypeId::of::<T>()
+    }
+
+    fn type_name(&self) -> &'static str {
+        std::any::type_name::<T>()
+    }
+
+    fn remove(&mut self, entities: &EntitiesRes, entity: Entity) -> Result<bool, WrongGeneration> {
+        check_alive(entities, entity, "remove component of")?;
+
+        Ok(self.remove_id(entity.id()).is_some())
+    }
+}
+
+/// A joinable view of a `SoaStorage`, returned by `Soa::rows`.
+///
+/// Joining it yields the row of each entity's component, which indexes the
+/// columns of the storage.
+pub struct SoaRows<'a> {
+    mask: &'a BitSet,
+    disabled: &'a BitSet,
+    rows: &'a [Index],
+}
+
+impl<'a> SoaRows<'a> {
+    /// Returns a joinable wrapper which doesn't skip the rows of disabled
+    /// entities. See `EntitiesRes::set_enabled`.
+    pub fn with_disabled(self) -> WithDisabled<Self> {
+        WithDisabled(self)
+    }
+}
+
+impl<'a> Join for SoaRows<'a> {
+    type Mask = BitSetAnd<&'a BitSet, BitSetNot<&'a BitSet>>;
+    type Type = usize;
+    type Value = &'a [Index];
+
+    // SAFETY: No unsafe code and no invariants to fulfill.
+    unsafe fn open(self) -> (Self::Mask, Self::Value) {
+        (BitSetAnd(self.mask, BitSetNot(self.disabled)), self.rows)
+    }
+
+    // SAFETY: Since we require that the mask was checked, `id` has a row.
+    unsafe fn get(v: &mut Self::Value, id: Index) -> usize {
+        *v.get_unchecked(id as usize) as usize
+    }
+}
+
+// SAFETY: `get` only reads.
+#[cfg(feature = "parallel")]
+unsafe impl<'a> ParJoin for SoaRows<'a> {}
+
+impl<'a> Join for WithDisabled<SoaRows<'a>> {
+    type Mask = &'a BitSet;
+    type Type = usize;
+    type Value = &'a [Index];
+
+    // SAFETY: No unsafe code and no invariants to fulfill.
+    unsafe fn open(self) -> (Self::Mask, Self::Value) {
+        (self.0.mask, self.0.rows)
+    }
+
+    // SAFETY: Since we require that the mask was checked, `id` has a row.
+    unsafe fn get(v: &mut Self::Value, id: Index) -> usize {
+        *v.get_unchecked(id as usize) as usize
+    }
+}
+
+// SAFETY: `get` only reads.
+#[cfg(feature = "parallel")]
+unsafe impl<'a> ParJoin for WithDisabled<SoaRows<'a>> {}
+
+/// A `SoaStorage` together with the `Entities` resource, like `Storage` is
+/// for components.
+pub struct Soa<'e, T, D> {
+    data: D,
+    entities: Fetch<'e, EntitiesRes>,
+    phantom: PhantomData<T>,
+}
+
+/// Read access to the `SoaStorage` of `T`.
+pub type ReadSoa<'a, T> = Soa<'a, T, Fetch<'a, SoaStorage<T>>>;
+
+/// Write access to the `SoaStorage` of `T`.
+///
+/// ## Examples
+///
+/// ```
+/// use specs::{
+///     prelude::*,
+///     storage::{ChunkedSlice, SoaColumns, SoaColumnsMut, SoaComponent, WriteSoa},
+/// };
+///
+/// struct Motion {
+///     pos: f32,
+///     vel: f32,
+/// }
+///
+/// # #[derive(Default)]
+/// # struct MotionColumns { pos: Vec<f32>, vel: Vec<f32> }
+/// # impl SoaColumns<Motion> for MotionColumns {
+/// #     fn len(&self) -> usize { self.pos.len() }
+/// #     fn push(&mut self, v: Motion) { self.pos.push(v.pos); self.vel.push(v.vel); }
+/// #     fn swap_remove(&mut self, r: usize) -> Motion {
+/// #         Motion { pos: self.pos.swap_remove(r), vel: self.vel.swap_remove(r) }
+/// #     }
+/// #     fn replace(&mut self, r: usize, v: Motion) -> Motion {
+/// #         Motion {
+/// #             pos: std::mem::replace(&mut self.pos[r], v.pos),
+/// #             vel: std::mem::replace(&mut self.vel[r], v.vel),
+/// #         }
+/// #     }
+/// # }
+/// # struct MotionSlicesMut<'a> { pos: &'a mut [f32], vel: &'a mut [f32] }
+/// # impl<'a> SoaColumnsMut<'a> for MotionColumns {
+/// #     type SlicesMut = MotionSlicesMut<'a>;
+/// #     fn slices_mut(&'a mut self) -> MotionSlicesMut<'a> {
+/// #         MotionSlicesMut { pos: &mut self.pos, vel: &mut self.vel }
+/// #     }
+/// # }
+/// # impl SoaComponent for Motion { type Columns = MotionColumns; }
+/// // With the `specs-derive` feature, the above is generated by
+/// // `#[derive(SoaComponent)]`.
+///
+/// let mut world = World::new();
+/// <WriteSoa<Motion> as SystemData>::setup(&mut world);
+///
+/// for i in 0..10 {
+///     let e = world.create_entity().build();
+///     let mut motions = world.system_data::<WriteSoa<Motion>>();
+///     motions.insert(e, Motion { pos: 0.0, vel: i as f32 }).unwrap();
+/// }
+///
+/// let mut motions = world.system_data::<WriteSoa<Motion>>();
+/// let columns = motions.columns_mut();
+/// let (pos, vel) = (columns.pos, &*columns.vel);
+/// let (pos_chunks, pos_rest) = pos.as_chunk_slice_mut::<[f32; 4]>();
+/// let (vel_chunks, vel_rest) = vel.as_chunk_slice::<[f32; 4]>();
+/// for (p, v) in pos_chunks.iter_mut().zip(vel_chunks) {
+///     for i in 0..4 {
+///         p[i] += v[i];
+///     }
+/// }
+/// for (p, v) in pos_rest.iter_mut().zip(vel_rest) {
+///     *p += v;
+/// }
+///
+/// assert_eq!(motions.columns().pos[9], 9.0);
+/// ```
+pub type WriteSoa<'a, T> = Soa<'a, T, FetchMut<'a, SoaStorage<T>>>;
+
+impl<'e, T, D> Soa<'e, T, D>
+where
+    T: SoaComponent,
+    D: Deref<Target = SoaStorage<T>>,
+{
+    /// Returns a joinable view yielding the row of every entity with a
+    /// component, which indexes the columns.
+    ///
+    /// Like joining a `Storage`, the view skips disabled entities unless
+    /// `SoaRows::with_disabled` is used.
+    pub fn rows(&self) -> SoaRows<'_> {
+        SoaRows {
+            mask: &self.data.mask,
+            disabled: self.entities.disabled(),
+            rows: &self.data.rows,
+        }
+    }
+}
+
+impl<'e, T, D> Soa<'e, T, D>
+where
+    T: SoaComponent,
+    D: DerefMut<Target = SoaStorage<T>>,
+{
+    /// Inserts the component of `e`, returning the previous one.
+    pub fn insert(&mut self, e: Entity, value: T) -> Result<Option<T>, Error> {
+        if self.entities.is_alive(e) {
+            Ok(self.data.insert(e, value))
+        } else {
+            Err(Error::WrongGeneration(WrongGeneration {
+                action: "insert component for entity",
+                actual_gen: self.entities.entity(e.id()).gen(),
+                entity: e,
+            }))
+        }
+    }
+
+    /// Removes the component of `e`.
+    pub fn remove(&mut self, e: Entity) -> Option<T> {
+        if self.data.contains(e) {
+            self.data.remove_id(e.id())
+        } else {
+            None
+        }
+    }
+
+    /// Removes all components.
+    pub fn clear(&mut self) {
+        *self.data = Default::default();
+    }
+
+    /// Returns the joinable view of `rows` together with the columns as
+    /// mutable slices, so joins can write to the columns.
+    ///
+    /// ## Examples
+    ///
+    /// ```
+    /// use specs::{prelude::*, storage::{SoaColumns, SoaColumnsMut, SoaComponent, WriteSoa}};
+    ///
+    /// struct Health(f32);
+    ///
+    /// # #[derive(Default)]
+    /// # struct HealthColumns(Vec<f32>);
+    /// # impl SoaColumns<Health> for HealthColumns {
+    /// #     fn len(&self) -> usize { self.0.len() }
+    /// #     fn push(&mut self, v: Health) { self.0.push(v.0); }
+    /// #     fn swap_remove(&mut self, r: usize) -> Health { Health(self.0.swap_remove(r)) }
+    /// #     fn replace(&mut self, r: usize, v: Health) -> Health {
+    /// #         Health(std::mem::replace(&mut self.0[r], v.0))
+    /// #     }
+    /// # }
+    /// # impl<'a> SoaColumnsMut<'a> for HealthColumns {
+    /// #     type SlicesMut = &'a mut [f32];
+    /// #     fn slices_mut(&'a mut self) -> &'a mut [f32] { &mut self.0 }
+    /// # }
+    /// # impl SoaComponent for Health { type Columns = HealthColumns; }
+    /// let mut world = World::new();
+    /// <WriteSoa<Health> as SystemData>::setup(&mut world);
+    ///
+    /// let a = world.create_entity().build();
+    /// let b = world.create_entity().build();
+    /// let mut health = world.system_data::<WriteSoa<Health>>();
+    /// health.insert(a, Health(10.0)).unwrap();
+    /// health.insert(b, Health(20.0)).unwrap();
+    ///
+    /// let entities = world.entities();
+    /// let (rows, values) = health.rows_and_columns_mut();
+    /// for (e, row) in (&entities, rows).join() {
+    ///     if e == b {
+    ///         values[row] -= 5.0;
+    ///     }
+    /// }
+    ///
+    /// assert_eq!(health.columns().0, vec![10.0, 15.0]);
+    /// ```
+    pub fn rows_and_columns_mut<'a>(
+        &'a mut self,
+    ) -> (SoaRows<'a>, <T::Columns as SoaColumnsMut<'a>>::SlicesMut)
+    where
+        T::Columns: SoaColumnsMut<'a>,
+    {
+        let disabled = self.entities.disabled();
+        let SoaStorage {
+            mask,
+            columns,
+            rows,
+            ..
+        } = &mut *self.data;
+        let rows = SoaRows {
+            mask,
+            disabled,
+            rows,
+        };
+
+        (rows, columns.slices_mut())
+    }
+}
+
+impl<'e, T, D> Deref for Soa<'e, T, D>
+where
+    T: SoaComponent,
+    D: Deref<Target = SoaStorage<T>>,
+{
+    type Target = SoaStorage<T>;
+
+    fn deref(&self) -> &SoaStorage<T> {
+        &self.data
+    }
+}
+
+impl<'e, T, D> DerefMut for Soa<'e, T, D>
+where
+    T: SoaComponent,
+    D: DerefMut<Target = SoaStorage<T>>,
+{
+    fn deref_mut(&mut self) -> &mut SoaStorage<T> {
+        &mut self.data
+    }
+}
+
+fn setup_soa<T>(res: &mut World)
+where
+    T: SoaComponent,
+    SoaStorage<T>: Resource,
+{
+    res.entry::<SoaStorage<T>>().or_insert_with(Default::default);
+    res.fetch_mut::<MetaTable<dyn AnyStorage>>()
+        .register(&*res.fetch::<SoaStorage<T>>());
+}
+
+impl<'a, T> SystemData<'a> for ReadSoa<'a, T>
+where
+    T: SoaComponent,
+    SoaStorage<T>: Resource,
+{
+    fn setup(res: &mut World) {
+        setup_soa::<T>(res);
+    }
+
+    fn fetch(res: &'a World) -> Self {
+        Soa {
+            data: res.fetch(),
+            entities: res.fetch(),
+            phantom: PhantomData,
+        }
+    }
+
+    fn reads() -> Vec<ResourceId> {
+        vec![
+            ResourceId::new::<EntitiesRes>(),
+            ResourceId::new::<SoaStorage<T>>(),
+        ]
+    }
+
+    fn writes() -> Vec<ResourceId> {
+        vec![]
+    }
+}
+
+impl<'a, T> SystemData<'a> for WriteSoa<'a, T>
+where
+    T: SoaComponent,
+    SoaStorage<T>: Resource,
+{
+    fn setup(res: &mut World) {
+        setup_soa::<T>(res);
+    }
+
+    fn fetch(res: &'a World) -> Self {
+        Soa {
+            data: res.fetch_mut(),
+            entities: res.fetch(),
+            phantom: PhantomData,
+        }
+    }
+
+    fn reads() -> Vec<ResourceId> {
+        vec![ResourceId::new::<EntitiesRes>()]
+    }
+
+    fn writes() -> Vec<ResourceId> {
+        vec![ResourceId::new::<SoaStorage<T>>()]
+    }
+}
+
+/// Fixed-size arrays a slice can be split into with `ChunkedSlice`.
+///
+/// # Safety
+///
+/// Implementors must have the layout of `LEN` consecutive `Item`s.
+pub unsafe trait Chunk {
+    /// The element type.
+    type Item;
+    /// The number of elements.
+    const LEN: usize;
+}
+
+macro_rules! chunk {
+    ($($len:expr),*) => {
+        $(
+            // SAFETY: Arrays are laid out as consecutive elements.
+            unsafe impl<T> Chunk for [T; $len] {
+                type Item = T;
+                const LEN: usize = $len;
+            }
+        )*
+    };
+}
+
+chunk!(2, 4, 8, 16, 32, 64);
+
+/// Access to a slice as fixed-size chunks, e.g. `&[[f32; 8]]`, which
+/// compilers can easily vectorize.
+///
+/// This is implemented for slices, so it can be used with the columns of a
+/// `SoaComponent` as well as with `SliceAccess`.
+pub trait ChunkedSlice<T> {
+    /// Splits `self` into as many chunks as possible and the remaining
+    /// elements.
+    fn as_chunk_slice<C>(&self) -> (&[C], &[T])
+    where
+        C: Chunk<Item = T>;
+
+    /// Splits `self` mutably into as many chunks as possible and the
+    /// remaining elements.
+    fn as_chunk_slice_mut<C>(&mut self) -> (&mut [C], &mut [T])
+    where
+        C: Chunk<Item = T>;
+}
+
+impl<T> ChunkedSlice<T> for [T] {
+    fn as_chunk_slice<C>(&self) -> (&[C], &[T])
+    where
+        C: Chunk<Item = T>,
+    {
+        let chunks = self.len() / C::LEN;
+        let (head, tail) = self.split_at(chunks * C::LEN);
+        // SAFETY: `head` consists of exactly `chunks` runs of `C::LEN`
+        // elements, which is the layout of `C`.
+        let head = unsafe { slice::from_raw_parts(head.as_ptr() as *const C, chunks) };
+
+        (head, tail)
+    }
+
+    fn as_chunk_slice_mut<C>(&mut self) -> (&mut [C], &mut [T])
+    where
+        C: Chunk<Item = T>,
+    {
+        let chunks = self.len() / C::LEN;
+        let (head, tail) = self.split_at_mut(chunks * C::LEN);
+        // SAFETY: See `as_chunk_slice`; `head` and `tail` don't overlap.
+        let head = unsafe { slice::from_raw_parts_mut(head.as_mut_ptr() as *mut C, chunks) };
+
+        (head, tail)
+    }
+}
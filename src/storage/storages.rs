@@ -1,6 +1,6 @@
 //! Different types of storages you can use for your components.
 
-use std::{collections::BTreeMap, mem::MaybeUninit};
+use std::{cmp::Ordering, collections::BTreeMap, mem::MaybeUninit};
 
 use hashbrown::HashMap;
 use hibitset::BitSetLike;
@@ -22,6 +22,48 @@ pub trait SliceAccess<T> {
     fn as_mut_slice(&mut self) -> &mut [Self::Element];
 }
 
+/// Storages whose slice can be reordered while keeping the mapping from
+/// entity ids to components consistent.
+///
+/// This is what `Storage::sort_by`, `Storage::sort_by_key` and
+/// `Storage::ordered_join` are built on. `VecStorage`, whose slice is
+/// indexed by the entity id and may be uninitialized, cannot implement it.
+pub trait SortableStorage<T>: SliceAccess<T, Element = T> {
+    /// Returns the entity ids of the components, in slice order.
+    ///
+    /// Storages without gaps in their slice, like `DefaultVecStorage`, may
+    /// include ids of entities which have no component.
+    fn packed_ids(&self) -> &[Index];
+
+    /// Returns the entity ids together with the mutable slice of the
+    /// components.
+    fn packed_mut(&mut self) -> (&[Index], &mut [T]);
+
+    /// Reorders the slice such that position `i` holds the component which
+    /// was at position `order[i]`.
+    ///
+    /// `order` has to be a permutation of the slice positions.
+    fn permute(&mut self, order: &[usize]);
+}
+
+/// Applies the permutation `order` (see `SortableStorage::permute`) through
+/// `swap`, without allocating.
+fn permute_with<F>(order: &[usize], mut swap: F)
+where
+    F: FnMut(usize, usize),
+{
+    for i in 0..order.len() {
+        // Earlier swaps may have moved the component; follow it.
+        let mut pos = order[i];
+        while pos < i {
+            pos = order[pos];
+        }
+        if pos != i {
+            swap(i, pos);
+        }
+    }
+}
+
 /// BTreeMap-based storage.
 pub struct BTreeStorage<T>(BTreeMap<Index, T>);
 
@@ -108,6 +150,10 @@ unsafe impl<T> DistinctStorage for HashMapStorage<T> {}
 /// cannot be compared with indices from any other storage, and
 /// a particular entity's position within this slice may change
 /// over time.
+///
+/// Removing a component moves the last component of the slice into its
+/// place, so the order established by `Storage::sort_by` is only kept
+/// until the next removal.
 pub struct DenseVecStorage<T> {
     data: Vec<T>,
     entity_id: Vec<Index>,
@@ -193,6 +239,265 @@ impl<T> UnprotectedStorage<T> for DenseVecStorage<T> {
 
 unsafe impl<T> DistinctStorage for DenseVecStorage<T> {}
 
+impl<T> SortableStorage<T> for DenseVecStorage<T> {
+    fn packed_ids(&self) -> &[Index] {
+        &self.entity_id
+    }
+
+    fn packed_mut(&mut self) -> (&[Index], &mut [T]) {
+        (&self.entity_id, &mut self.data)
+    }
+
+    fn permute(&mut self, order: &[usize]) {
+        let DenseVecStorage {
+            data,
+            entity_id,
+            data_id,
+        } = self;
+        permute_with(order, |a, b| {
+            data.swap(a, b);
+            entity_id.swap(a, b);
+            // SAFETY: `entity_id` only contains ids which were inserted, so
+            // their `data_id`s are initialized.
+            unsafe {
+                data_id
+                    .get_unchecked_mut(entity_id[a] as usize)
+                    .as_mut_ptr()
+                    .write(a as Index);
+                data_id
+                    .get_unchecked_mut(entity_id[b] as usize)
+                    .as_mut_ptr()
+                    .write(b as Index);
+            }
+        });
+    }
+}
+
+/// Sparse set storage, keeping the components packed in a dense `Vec` in
+/// a stable order.
+///
+/// Like `DenseVecStorage`, a sparse `Vec` maps entity ids to positions in
+/// the packed data. In contrast to it, the packed order is exposed through
+/// `packed_ids`, `iter` and `iter_mut` and is only changed by insertions,
+/// which append to the end, and by the explicit reordering methods
+/// `sort_by`, `sort_by_key` and `group_with`. Removals shift the remaining
+/// components and are therefore `O(n)`.
+///
+/// Grouping two storages moves the components of their common entities to
+/// the front of both packed arrays, in the same order, so tight loops over
+/// both can zip the slices with linear memory access.
+///
+/// The storage of a fetched component is accessed with
+/// `Storage::unprotected_storage` and `Storage::sparse_set_mut`.
+///
+/// ## Examples
+///
+/// ```
+/// # use specs::prelude::*;
+/// # use specs::storage::SparseSetStorage;
+/// # #[derive(Debug, PartialEq)]
+/// # struct Pos(f32); impl Component for Pos { type Storage = SparseSetStorage<Self>; }
+/// # #[derive(Debug, PartialEq)]
+/// # struct Vel(f32); impl Component for Vel { type Storage = SparseSetStorage<Self>; }
+/// let mut world = World::new();
+/// world.register::<Pos>();
+/// world.register::<Vel>();
+///
+/// world.create_entity().with(Pos(3.0)).build();
+/// world.create_entity().with(Pos(1.0)).with(Vel(-1.0)).build();
+/// world.create_entity().with(Vel(0.5)).with(Pos(2.0)).build();
+///
+/// let mut pos = world.write_storage::<Pos>();
+/// let mut vel = world.write_storage::<Vel>();
+///
+/// let len = pos.sparse_set_mut().group_with(vel.sparse_set_mut());
+/// assert_eq!(len, 2);
+///
+/// let velocities = &vel.as_slice()[..len];
+/// for (p, v) in pos.as_mut_slice()[..len].iter_mut().zip(velocities) {
+///     p.0 += v.0;
+/// }
+///
+/// let sorted: Vec<_> = pos.unprotected_storage().iter().map(|(_, p)| p.0).collect();
+/// assert_eq!(sorted, vec![0.0, 2.5, 3.0]);
+/// ```
+pub struct SparseSetStorage<T> {
+    data: Vec<T>,
+    entity_id: Vec<Index>,
+    data_id: Vec<Index>,
+}
+
+impl<T> SparseSetStorage<T> {
+    /// Returns the entity ids of the components, in packed order.
+    pub fn packed_ids(&self) -> &[Index] {
+        &self.entity_id
+    }
+
+    /// Returns the position of the component of `id` in the packed data, if
+    /// there is one.
+    pub fn position(&self, id: Index) -> Option<usize> {
+        self.data_id
+            .get(id as usize)
+            .map(|&did| did as usize)
+            .filter(|&did| self.entity_id.get(did) == Some(&id))
+    }
+
+    /// Iterates over the entity ids and components in packed order.
+    pub fn iter(&self) -> impl Iterator<Item = (Index, &T)> {
+        self.entity_id.iter().cloned().zip(self.data.iter())
+    }
+
+    /// Mutably iterates over the entity ids and components in packed order.
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (Index, &mut T)> {
+        self.entity_id.iter().cloned().zip(self.data.iter_mut())
+    }
+
+    /// Sorts the packed data with a comparator function.
+    ///
+    /// The sort is stable and moves the components by swapping them.
+    pub fn sort_by<F>(&mut self, mut compare: F)
+    where
+        F: FnMut(&T, &T) -> Ordering,
+    {
+        let mut order: Vec<usize> = (0..self.data.len()).collect();
+        order.sort_by(|&a, &b| compare(&self.data[a], &self.data[b]));
+        self.permute(&order);
+    }
+
+    /// Sorts the packed data with a key extraction function.
+    ///
+    /// The sort is stable and moves the components by swapping them.
+    pub fn sort_by_key<K, F>(&mut self, mut f: F)
+    where
+        F: FnMut(&T) -> K,
+        K: Ord,
+    {
+        self.sort_by(|a, b| f(a).cmp(&f(b)));
+    }
+
+    /// Moves the components of all entities which are part of both storages
+    /// to the front of `self` and `other`, in the same order. Returns the
+    /// number of grouped components.
+    ///
+    /// Grouped components keep the relative order they had in `self`, all
+    /// other components keep their relative order within their storage.
+    pub fn group_with<U>(&mut self, other: &mut SparseSetStorage<U>) -> usize {
+        let (mut grouped, rest): (Vec<usize>, Vec<usize>) = (0..self.data.len())
+            .partition(|&did| other.position(self.entity_id[did]).is_some());
+        let len = grouped.len();
+        grouped.extend(rest);
+        self.permute(&grouped);
+
+        let mut order: Vec<usize> = self.entity_id[..len]
+            .iter()
+            .map(|&id| other.position(id).expect("Grouped entity is missing"))
+            .collect();
+        let mut is_grouped = vec![false; other.data.len()];
+        for &did in &order {
+            is_grouped[did] = true;
+        }
+        order.extend((0..other.data.len()).filter(|&did| !is_grouped[did]));
+        other.permute(&order);
+
+        len
+    }
+
+    /// Reorders the packed data such that position `i` holds the component
+    /// which was at position `order[i]`.
+    fn permute(&mut self, order: &[usize]) {
+        permute_with(order, |a, b| self.swap(a, b));
+    }
+
+    fn swap(&mut self, a: usize, b: usize) {
+        self.data.swap(a, b);
+        self.entity_id.swap(a, b);
+        self.data_id[self.entity_id[a] as usize] = a as Index;
+        self.data_id[self.entity_id[b] as usize] = b as Index;
+    }
+}
+
+impl<T> Default for SparseSetStorage<T> {
+    fn default() -> Self {
+        Self {
+            data: Default::default(),
+            entity_id: Default::default(),
+            data_id: Default::default(),
+        }
+    }
+}
+
+impl<T> SliceAccess<T> for SparseSetStorage<T> {
+    type Element = T;
+
+    /// Returns a slice of all the components in this storage, in packed
+    /// order.
+    #[inline]
+    fn as_slice(&self) -> &[Self::Element] {
+        self.data.as_slice()
+    }
+
+    /// Returns a mutable slice of all the components in this storage, in
+    /// packed order.
+    #[inline]
+    fn as_mut_slice(&mut self) -> &mut [Self::Element] {
+        self.data.as_mut_slice()
+    }
+}
+
+impl<T> UnprotectedStorage<T> for SparseSetStorage<T> {
+    unsafe fn clean<B>(&mut self, _has: B)
+    where
+        B: BitSetLike,
+    {
+        // nothing to do
+    }
+
+    unsafe fn get(&self, id: Index) -> &T {
+        let did = *self.data_id.get_unchecked(id as usize);
+        self.data.get_unchecked(did as usize)
+    }
+
+    unsafe fn get_mut(&mut self, id: Index) -> &mut T {
+        let did = *self.data_id.get_unchecked(id as usize);
+        self.data.get_unchecked_mut(did as usize)
+    }
+
+    unsafe fn insert(&mut self, id: Index, v: T) {
+        let id = id as usize;
+        if self.data_id.len() <= id {
+            self.data_id.resize(id + 1, 0);
+        }
+        self.data_id[id] = self.data.len() as Index;
+        self.entity_id.push(id as Index);
+        self.data.push(v);
+    }
+
+    unsafe fn remove(&mut self, id: Index) -> T {
+        let did = self.data_id[id as usize] as usize;
+        self.entity_id.remove(did);
+        for (i, &id) in self.entity_id.iter().enumerate().skip(did) {
+            self.data_id[id as usize] = i as Index;
+        }
+        self.data.remove(did)
+    }
+}
+
+unsafe impl<T> DistinctStorage for SparseSetStorage<T> {}
+
+impl<T> SortableStorage<T> for SparseSetStorage<T> {
+    fn packed_ids(&self) -> &[Index] {
+        &self.entity_id
+    }
+
+    fn packed_mut(&mut self) -> (&[Index], &mut [T]) {
+        (&self.entity_id, &mut self.data)
+    }
+
+    fn permute(&mut self, order: &[usize]) {
+        SparseSetStorage::permute(self, order);
+    }
+}
+
 /// A null storage type, used for cases where the component
 /// doesn't contain any data and instead works as a simple flag.
 pub struct NullStorage<T>(T);
@@ -318,11 +623,38 @@ unsafe impl<T> DistinctStorage for VecStorage<T> {}
 /// `as_slice()` and `as_mut_slice()` indices correspond to entity IDs.
 /// These can be compared to other `DefaultVecStorage`s, to other
 /// `VecStorage`s, and to `Entity::id()`s for live entities.
-pub struct DefaultVecStorage<T>(Vec<T>);
+///
+/// This no longer holds once the storage has been sorted with
+/// `Storage::sort_by` or `Storage::sort_by_key`; afterwards the slice
+/// indices are arbitrary, like those of `DenseVecStorage`, until the
+/// storage is cleaned. The default values of unused slots are sorted along
+/// with the components.
+pub struct DefaultVecStorage<T> {
+    data: Vec<T>,
+    entity_id: Vec<Index>,
+    /// Maps entity ids to slice positions; empty as long as the slice is
+    /// indexed by entity id.
+    data_id: Vec<Index>,
+}
+
+impl<T> DefaultVecStorage<T> {
+    #[inline]
+    unsafe fn position(&self, id: Index) -> usize {
+        if self.data_id.is_empty() {
+            id as usize
+        } else {
+            *self.data_id.get_unchecked(id as usize) as usize
+        }
+    }
+}
 
 impl<T> Default for DefaultVecStorage<T> {
     fn default() -> Self {
-        Self(Default::default())
+        Self {
+            data: Default::default(),
+            entity_id: Default::default(),
+            data_id: Default::default(),
+        }
     }
 }
 
@@ -334,28 +666,41 @@ where
     where
         B: BitSetLike,
     {
-        self.0.clear();
+        self.data.clear();
+        self.entity_id.clear();
+        self.data_id.clear();
     }
 
     unsafe fn get(&self, id: Index) -> &T {
-        self.0.get_unchecked(id as usize)
+        self.data.get_unchecked(self.position(id))
     }
 
     unsafe fn get_mut(&mut self, id: Index) -> &mut T {
-        self.0.get_unchecked_mut(id as usize)
+        let pos = self.position(id);
+        self.data.get_unchecked_mut(pos)
     }
 
     unsafe fn insert(&mut self, id: Index, v: T) {
-        let id = id as usize;
-
-        if self.0.len() <= id {
-            // fill all the empty slots with default values
-            self.0.resize_with(id, Default::default);
+        let len = self.data.len() as Index;
+
+        if len <= id {
+            // fill all the empty slots with default values, appending the
+            // new ids to the end of the slice
+            self.data.resize_with(id as usize, Default::default);
+            self.entity_id.extend(len..id);
+            if !self.data_id.is_empty() {
+                self.data_id.extend(len..id);
+            }
             // store the desired value
-            self.0.push(v)
+            self.data.push(v);
+            self.entity_id.push(id);
+            if !self.data_id.is_empty() {
+                self.data_id.push(id);
+            }
         } else {
             // store the desired value directly
-            self.0[id] = v;
+            let pos = self.position(id);
+            *self.data.get_unchecked_mut(pos) = v;
         }
     }
 
@@ -363,7 +708,8 @@ where
         // make a new default value
         let mut v = T::default();
         // swap it into the vec
-        std::ptr::swap(self.0.get_unchecked_mut(id as usize), &mut v);
+        let pos = self.position(id);
+        std::ptr::swap(self.data.get_unchecked_mut(pos), &mut v);
         // return the old value
         v
     }
@@ -377,12 +723,42 @@ impl<T> SliceAccess<T> for DefaultVecStorage<T> {
     /// Returns a slice of all the components in this storage.
     #[inline]
     fn as_slice(&self) -> &[Self::Element] {
-        self.0.as_slice()
+        self.data.as_slice()
     }
 
     /// Returns a mutable slice of all the components in this storage.
     #[inline]
     fn as_mut_slice(&mut self) -> &mut [Self::Element] {
-        self.0.as_mut_slice()
+        self.data.as_mut_slice()
+    }
+}
+
+impl<T> SortableStorage<T> for DefaultVecStorage<T> {
+    /// Returns the entity ids of all slots, including the unused ones
+    /// holding default values.
+    fn packed_ids(&self) -> &[Index] {
+        &self.entity_id
+    }
+
+    fn packed_mut(&mut self) -> (&[Index], &mut [T]) {
+        (&self.entity_id, &mut self.data)
+    }
+
+    fn permute(&mut self, order: &[usize]) {
+        if self.data_id.is_empty() {
+            self.data_id = self.entity_id.clone();
+        }
+
+        let DefaultVecStorage {
+            data,
+            entity_id,
+            data_id,
+        } = self;
+        permute_with(order, |a, b| {
+            data.swap(a, b);
+            entity_id.swap(a, b);
+            data_id[entity_id[a] as usize] = a as Index;
+            data_id[entity_id[b] as usize] = b as Index;
+        });
     }
 }
@@ -226,7 +226,7 @@ mod test {
         type Storage = VecStorage<Self>;
     }
 
-    #[derive(PartialEq, Eq, Debug, Default)]
+    #[derive(PartialEq, Eq, PartialOrd, Ord, Debug, Default)]
     struct CdefaultVec(u32);
     impl From<u32> for CdefaultVec {
         fn from(v: u32) -> CdefaultVec {
@@ -242,6 +242,38 @@ mod test {
         type Storage = DefaultVecStorage<Self>;
     }
 
+    #[derive(PartialEq, Eq, PartialOrd, Ord, Debug)]
+    struct CdenseVec(u32);
+    impl From<u32> for CdenseVec {
+        fn from(v: u32) -> CdenseVec {
+            CdenseVec(v)
+        }
+    }
+    impl AsMut<u32> for CdenseVec {
+        fn as_mut(&mut self) -> &mut u32 {
+            &mut self.0
+        }
+    }
+    impl Component for CdenseVec {
+        type Storage = DenseVecStorage<Self>;
+    }
+
+    #[derive(PartialEq, Eq, PartialOrd, Ord, Debug)]
+    struct CsparseSet(u32);
+    impl From<u32> for CsparseSet {
+        fn from(v: u32) -> CsparseSet {
+            CsparseSet(v)
+        }
+    }
+    impl AsMut<u32> for CsparseSet {
+        fn as_mut(&mut self) -> &mut u32 {
+            &mut self.0
+        }
+    }
+    impl Component for CsparseSet {
+        type Storage = SparseSetStorage<Self>;
+    }
+
     fn test_add<T: Component + From<u32> + Debug + Eq>()
     where
         T::Storage: Default,
@@ -472,6 +504,43 @@ mod test {
         }
     }
 
+    fn test_sort<T: Component + From<u32> + Debug + Ord>()
+    where
+        T::Storage: Default + SortableStorage<T>,
+    {
+        let mut w = World::new();
+        let mut s: Storage<T, _> = create(&mut w);
+
+        for i in 0..100 {
+            // Scatter the values so they need sorting.
+            let v = (i * 37) % 100;
+            if let Err(err) = s.insert(Entity::new(i, Generation::new(1)), v.into()) {
+                panic!("Failed to insert component into entity! {:?}", err);
+            }
+        }
+
+        s.sort_by(|a, b| a.cmp(b));
+
+        for (i, v) in s.as_slice().iter().enumerate() {
+            assert_eq!(v, &(i as u32).into());
+        }
+        for i in 0..100 {
+            let e = Entity::new(i, Generation::new(1));
+            assert_eq!(s.get(e), Some(&((i * 37) % 100).into()));
+        }
+
+        let ordered: Vec<_> = s.ordered_join(&s).collect();
+        assert_eq!(ordered.len(), 100);
+        for (i, v) in ordered.into_iter().enumerate() {
+            assert_eq!(v, &(i as u32).into());
+        }
+
+        let mask: BitSet = (0..100).filter(|i| i % 2 == 0).collect();
+        let ordered: Vec<_> = s.ordered_join(&mask).collect();
+        assert_eq!(ordered.len(), 50);
+        assert_eq!(&ordered[..4], &[0, 46, 92, 38]);
+    }
+
     #[test]
     fn vec_test_add() {
         test_add::<Cvec>();
@@ -650,6 +719,172 @@ mod test {
         test_clear::<CBtree>();
     }
 
+    #[test]
+    fn dense_vec_test_sort() {
+        test_sort::<CdenseVec>();
+    }
+    #[test]
+    fn dense_vec_sort_after_removal() {
+        let mut w = World::new();
+        let mut s: Storage<CdenseVec, _> = create(&mut w);
+
+        for i in 0..5 {
+            s.insert(Entity::new(i, Generation::new(1)), (4 - i).into())
+                .unwrap();
+        }
+        s.sort_by_key(|c| c.0);
+        assert_eq!(s.as_slice().iter().map(|c| c.0).collect::<Vec<_>>(), vec![0, 1, 2, 3, 4]);
+
+        // The last component is moved into the place of the removed one.
+        s.remove(Entity::new(3, Generation::new(1)));
+        assert_eq!(s.as_slice().iter().map(|c| c.0).collect::<Vec<_>>(), vec![0, 4, 2, 3]);
+        assert_eq!(s.get(Entity::new(0, Generation::new(1))), Some(&4.into()));
+
+        s.sort_by_key(|c| c.0);
+        assert_eq!(s.as_slice().iter().map(|c| c.0).collect::<Vec<_>>(), vec![0, 2, 3, 4]);
+    }
+    #[test]
+    fn dense_vec_test_slice_access() {
+        test_slice_access::<CdenseVec>();
+    }
+    #[test]
+    fn default_vec_test_sort() {
+        test_sort::<CdefaultVec>();
+    }
+    #[test]
+    fn default_vec_sort_with_gaps() {
+        let mut w = World::new();
+        let mut s: Storage<CdefaultVec, _> = create(&mut w);
+        let e = |i| Entity::new(i, Generation::new(1));
+
+        s.insert(e(0), 5.into()).unwrap();
+        s.insert(e(2), 3.into()).unwrap();
+        s.insert(e(4), 1.into()).unwrap();
+        s.sort_by_key(|c| c.0);
+
+        // The unused slots hold default values, which are sorted as well.
+        assert_eq!(s.as_slice().iter().map(|c| c.0).collect::<Vec<_>>(), vec![0, 0, 1, 3, 5]);
+        assert_eq!(s.get(e(0)), Some(&5.into()));
+        assert_eq!(s.get(e(2)), Some(&3.into()));
+        assert_eq!(s.get(e(4)), Some(&1.into()));
+
+        let ordered: Vec<_> = s.ordered_join(&s).map(|c| c.0).collect();
+        assert_eq!(ordered, vec![1, 3, 5]);
+
+        // Inserting past the end appends to the slice.
+        s.insert(e(6), 2.into()).unwrap();
+        s.insert(e(1), 4.into()).unwrap();
+        assert_eq!(s.get(e(6)), Some(&2.into()));
+        assert_eq!(s.get(e(1)), Some(&4.into()));
+        assert_eq!(s.remove(e(2)), Some(3.into()));
+        assert_eq!(s.get(e(2)), None);
+
+        s.sort_by_key(|c| c.0);
+        let ordered: Vec<_> = s.ordered_join(&s).map(|c| c.0).collect();
+        assert_eq!(ordered, vec![1, 2, 4, 5]);
+    }
+    #[test]
+    fn sparse_set_test_sort() {
+        test_sort::<CsparseSet>();
+    }
+
+    #[test]
+    fn sparse_set_test_add() {
+        test_add::<CsparseSet>();
+    }
+    #[test]
+    fn sparse_set_test_sub() {
+        test_sub::<CsparseSet>();
+    }
+    #[test]
+    fn sparse_set_test_get_mut() {
+        test_get_mut::<CsparseSet>();
+    }
+    #[test]
+    fn sparse_set_test_add_gen() {
+        test_add_gen::<CsparseSet>();
+    }
+    #[test]
+    fn sparse_set_test_sub_gen() {
+        test_sub_gen::<CsparseSet>();
+    }
+    #[test]
+    fn sparse_set_test_clear() {
+        test_clear::<CsparseSet>();
+    }
+    #[test]
+    fn sparse_set_test_anti() {
+        test_anti::<CsparseSet>();
+    }
+    #[test]
+    fn sparse_set_test_slice_access() {
+        test_slice_access::<CsparseSet>();
+    }
+
+    #[test]
+    fn sparse_set_sort_and_group() {
+        let mut w = World::new();
+        let mut sparse: Storage<CsparseSet, _> = create(&mut w);
+
+        for (i, v) in [5, 3, 8, 1, 9, 2].iter().enumerate() {
+            sparse
+                .insert(Entity::new(i as Index, Generation::new(1)), CsparseSet(*v))
+                .unwrap();
+        }
+
+        sparse.sparse_set_mut().sort_by_key(|c| c.0);
+        assert_eq!(
+            sparse.as_slice(),
+            &[
+                CsparseSet(1),
+                CsparseSet(2),
+                CsparseSet(3),
+                CsparseSet(5),
+                CsparseSet(8),
+                CsparseSet(9),
+            ]
+        );
+        assert_eq!(sparse.unprotected_storage().packed_ids(), &[3, 5, 1, 0, 2, 4]);
+        for (i, v) in [5, 3, 8, 1, 9, 2].iter().enumerate() {
+            let e = Entity::new(i as Index, Generation::new(1));
+            assert_eq!(sparse.get(e), Some(&CsparseSet(*v)));
+        }
+
+        // Removing keeps the order of the remaining components.
+        sparse.remove(Entity::new(5, Generation::new(1)));
+        assert_eq!(sparse.unprotected_storage().packed_ids(), &[3, 1, 0, 2, 4]);
+        assert_eq!(
+            sparse.get(Entity::new(4, Generation::new(1))),
+            Some(&CsparseSet(9))
+        );
+    }
+
+    #[test]
+    fn sparse_set_group_with() {
+        let mut a = SparseSetStorage::<u32>::default();
+        let mut b = SparseSetStorage::<i64>::default();
+
+        unsafe {
+            for id in 0..6 {
+                a.insert(id, id * 10);
+            }
+            for &id in &[7, 4, 1, 2] {
+                b.insert(id, -(id as i64));
+            }
+        }
+
+        assert_eq!(a.group_with(&mut b), 3);
+        assert_eq!(a.packed_ids(), &[1, 2, 4, 0, 3, 5]);
+        assert_eq!(b.packed_ids(), &[1, 2, 4, 7]);
+        assert_eq!(a.as_slice(), &[10, 20, 40, 0, 30, 50]);
+        assert_eq!(b.as_slice(), &[-1, -2, -4, -7]);
+        for id in 0..6 {
+            assert_eq!(unsafe { *a.get(id) }, id * 10);
+        }
+        assert_eq!(b.position(7), Some(3));
+        assert_eq!(b.position(3), None);
+    }
+
     #[test]
     fn dummy_test_clear() {
         test_clear::<Cnull>();
@@ -988,6 +1223,184 @@ mod test {
         }
     }
 
+    #[test]
+    fn flagged_clear() {
+        let mut w = World::new();
+        w.register::<FlaggedCvec>();
+
+        let mut s: Storage<FlaggedCvec, _> = w.write_storage();
+        for i in 0..3 {
+            let entity = w.entities().create();
+            s.insert(entity, i.into()).unwrap();
+        }
+
+        let mut reader_id = s.register_reader();
+        s.clear();
+
+        let events: Vec<_> = s.channel().read(&mut reader_id).cloned().collect();
+        assert_eq!(
+            events,
+            vec![
+                ComponentEvent::Removed(0),
+                ComponentEvent::Removed(1),
+                ComponentEvent::Removed(2),
+            ]
+        );
+        assert!(s.mask().is_empty());
+    }
+
+    #[test]
+    fn change_tracked() {
+        use crate::{join::Join, world::Builder};
+
+        struct Changes(u32);
+
+        impl Component for Changes {
+            type Storage = ChangeTrackedStorage<Self, VecStorage<Self>>;
+        }
+
+        let mut w = World::new();
+        w.register::<Changes>();
+
+        let entities: Vec<_> = (0..10)
+            .map(|i| w.create_entity().with(Changes(i)).build())
+            .collect();
+
+        {
+            let s = w.read_storage::<Changes>();
+            assert_eq!((s.inserted()).join().count(), 10);
+            assert_eq!((s.modified()).join().count(), 0);
+        }
+
+        w.maintain();
+
+        {
+            let mut s = w.write_storage::<Changes>();
+            assert_eq!((s.inserted()).join().count(), 0);
+
+            for mut c in s.track_mut().join() {
+                if c.0 % 2 == 0 {
+                    c.0 += 1;
+                }
+            }
+            s.remove(entities[1]);
+            s.get_mut(entities[3]);
+        }
+
+        {
+            let s = w.read_storage::<Changes>();
+            let modified: Vec<_> = s.modified().iter().collect();
+            assert_eq!(modified, vec![0, 2, 3, 4, 6, 8]);
+            let removed: Vec<_> = s.removed().iter().collect();
+            assert_eq!(removed, vec![1]);
+        }
+
+        // Disabled entities are skipped by both mutable joins.
+        w.entities().set_enabled(entities[2], false).unwrap();
+        w.maintain();
+        {
+            let mut s = w.write_storage::<Changes>();
+            for c in (&mut s).join() {
+                c.0 += 1;
+            }
+            let modified: Vec<_> = s.modified().iter().collect();
+            assert_eq!(modified, vec![0, 3, 4, 5, 6, 7, 8, 9]);
+        }
+        w.maintain();
+        {
+            let mut s = w.write_storage::<Changes>();
+            for mut c in s.track_mut().join() {
+                c.0 += 1;
+            }
+            let modified: Vec<_> = s.modified().iter().collect();
+            assert_eq!(modified, vec![0, 3, 4, 5, 6, 7, 8, 9]);
+        }
+        w.entities().set_enabled(entities[2], true).unwrap();
+        w.maintain();
+
+        // Deletions during `maintain` are recorded for the next tick.
+        w.entities().delete(entities[5]).unwrap();
+        w.maintain();
+
+        {
+            let s = w.read_storage::<Changes>();
+            assert_eq!((s.modified()).join().count(), 0);
+            let removed: Vec<_> = s.removed().iter().collect();
+            assert_eq!(removed, vec![5]);
+        }
+
+        // So are lazy insertions.
+        w.read_resource::<LazyUpdate>().insert(entities[1], Changes(1));
+        w.maintain();
+        let s = w.read_storage::<Changes>();
+        let inserted: Vec<_> = s.inserted().iter().collect();
+        assert_eq!(inserted, vec![1]);
+    }
+
+    #[test]
+    fn indexed() {
+        use crate::{join::Join, world::Builder};
+
+        struct Cell(i32);
+
+        impl Component for Cell {
+            type Storage = IndexedStorage<Self, i32, VecStorage<Self>>;
+        }
+
+        impl IndexKey<i32> for Cell {
+            fn index_key(&self) -> i32 {
+                self.0
+            }
+        }
+
+        let mut w = World::new();
+        w.register::<Cell>();
+
+        let entities: Vec<_> = (0..6)
+            .map(|i| w.create_entity().with(Cell(i % 3)).build())
+            .collect();
+
+        {
+            let s = w.read_storage::<Cell>();
+            let zero: Vec<_> = s.with_key(&0).iter().collect();
+            assert_eq!(zero, vec![0, 3]);
+            assert!(s.with_key(&3).is_empty());
+        }
+
+        {
+            let mut s = w.write_storage::<Cell>();
+            for cell in (&mut s).join() {
+                cell.0 += 1;
+            }
+            // Modifications are only picked up once reindexed.
+            assert!(s.with_key(&3).is_empty());
+            s.reindex();
+
+            let three: Vec<_> = s.with_key(&3).iter().collect();
+            assert_eq!(three, vec![2, 5]);
+            assert!(s.with_key(&0).is_empty());
+
+            s.remove(entities[2]);
+            // Replacing a component re-keys it right away.
+            s.insert(entities[4], Cell(3)).unwrap();
+            let three: Vec<_> = s.with_key(&3).iter().collect();
+            assert_eq!(three, vec![4, 5]);
+            let two: Vec<_> = s.with_key(&2).iter().collect();
+            assert_eq!(two, vec![1]);
+            s.get_mut(entities[0]).unwrap().0 = 3;
+        }
+
+        w.maintain();
+
+        let s = w.read_storage::<Cell>();
+        let three: Vec<_> = s.with_key(&3).iter().collect();
+        assert_eq!(three, vec![0, 4, 5]);
+        let two: Vec<_> = s.with_key(&2).iter().collect();
+        assert_eq!(two, vec![1]);
+        let one: Vec<_> = s.with_key(&1).iter().collect();
+        assert_eq!(one, vec![3]);
+    }
+
     #[test]
     fn entries() {
         use crate::{join::Join, storage::WriteStorage, world::Entities};
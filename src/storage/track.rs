@@ -89,6 +89,6 @@ where
     /// not emitted.
     #[cfg(feature = "storage-event-control")]
     pub fn set_event_emission(&mut self, emit: bool) {
-        unsafe { self.open() }.1.set_event_emission(emit);
+        self.data.open_mut().1.set_event_emission(emit);
     }
 }
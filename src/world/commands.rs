@@ -0,0 +1,737 @@
+use std::{
+    any::{type_name, Any, TypeId},
+    collections::VecDeque,
+    marker::PhantomData,
+    ops::{Deref, DerefMut},
+};
+
+use crossbeam_queue::SegQueue;
+#[cfg(feature = "serde")]
+use serde::{
+    ser::{self, SerializeSeq, Serializer},
+    Serialize,
+};
+use shred::{Fetch, ResourceId, SystemData, World};
+
+use crate::{
+    storage::WriteStorage,
+    world::{Builder, Component, Entity, WorldExt},
+};
+
+/// The entity a command applies to.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum CommandTarget {
+    /// An existing entity.
+    Entity(Entity),
+    /// The entity created by the n-th `Command::Create` of the same
+    /// `Commands`.
+    Created(usize),
+}
+
+impl From<Entity> for CommandTarget {
+    fn from(entity: Entity) -> Self {
+        CommandTarget::Entity(entity)
+    }
+}
+
+/// Existing entities are serialized as their index and generation.
+#[cfg(feature = "serde")]
+impl Serialize for CommandTarget {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match *self {
+            CommandTarget::Entity(entity) => serializer.serialize_newtype_variant(
+                "CommandTarget",
+                0,
+                "Entity",
+                &(entity.id(), entity.gen().id()),
+            ),
+            CommandTarget::Created(n) => {
+                serializer.serialize_newtype_variant("CommandTarget", 1, "Created", &n)
+            }
+        }
+    }
+}
+
+/// A single recorded command, as returned by `Commands::iter`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub enum Command {
+    /// Creates a new entity.
+    Create,
+    /// Deletes an entity.
+    Delete(CommandTarget),
+    /// Inserts a component; the components can be inspected with
+    /// `Commands::inserted`.
+    Insert {
+        /// The entity the component is inserted for.
+        target: CommandTarget,
+        /// The type name of the component.
+        component: &'static str,
+    },
+    /// Removes a component.
+    Remove {
+        /// The entity the component is removed from.
+        target: CommandTarget,
+        /// The type name of the component.
+        component: &'static str,
+    },
+}
+
+#[cfg(feature = "parallel")]
+trait ComponentCommands: Send + Sync {
+    fn as_any(&self) -> &dyn Any;
+
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+
+    fn insert_next(&mut self, world: &World, entity: Entity);
+
+    fn skip_next(&mut self);
+
+    fn remove(&self, world: &World, entity: Entity);
+}
+
+#[cfg(not(feature = "parallel"))]
+trait ComponentCommands {
+    fn as_any(&self) -> &dyn Any;
+
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+
+    fn insert_next(&mut self, world: &World, entity: Entity);
+
+    fn skip_next(&mut self);
+
+    fn remove(&self, world: &World, entity: Entity);
+}
+
+/// The components of type `C` inserted by a `Commands`, in order.
+struct ComponentBuffer<C> {
+    inserted: VecDeque<(CommandTarget, C)>,
+}
+
+impl<C> ComponentBuffer<C>
+where
+    C: Component,
+{
+    fn insert_next(&mut self, world: &World, entity: Entity) {
+        let (_, component) = self
+            .inserted
+            .pop_front()
+            .expect("Command buffer is out of sync with its components");
+        let mut storage: WriteStorage<C> = SystemData::fetch(world);
+        if storage.insert(entity, component).is_err() {
+            log::warn!(
+                "Insert command of component failed because {:?} was dead.",
+                entity
+            );
+        }
+    }
+
+    fn remove(&self, world: &World, entity: Entity) {
+        let mut storage: WriteStorage<C> = SystemData::fetch(world);
+        storage.remove(entity);
+    }
+}
+
+#[cfg(feature = "parallel")]
+impl<C> ComponentCommands for ComponentBuffer<C>
+where
+    C: Component + Send + Sync,
+{
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn insert_next(&mut self, world: &World, entity: Entity) {
+        ComponentBuffer::insert_next(self, world, entity);
+    }
+
+    fn skip_next(&mut self) {
+        self.inserted.pop_front();
+    }
+
+    fn remove(&self, world: &World, entity: Entity) {
+        ComponentBuffer::remove(self, world, entity);
+    }
+}
+
+#[cfg(not(feature = "parallel"))]
+impl<C> ComponentCommands for ComponentBuffer<C>
+where
+    C: Component,
+{
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn insert_next(&mut self, world: &World, entity: Entity) {
+        ComponentBuffer::insert_next(self, world, entity);
+    }
+
+    fn skip_next(&mut self) {
+        self.inserted.pop_front();
+    }
+
+    fn remove(&self, world: &World, entity: Entity) {
+        ComponentBuffer::remove(self, world, entity);
+    }
+}
+
+/// A recorded sequence of entity creations, deletions and component
+/// insertions and removals.
+///
+/// In contrast to `LazyUpdate`, commands aren't boxed closures: components
+/// are stored in one typed buffer per component type, so the commands can
+/// be inspected with `iter` and `inserted` before they are applied. With
+/// the `serde` feature, they can also be serialized with `serialize`. The
+/// commands are applied in the order they were recorded.
+///
+/// Entities created by the commands are only allocated once the commands
+/// are applied; until then they are referred to by
+/// `CommandTarget::Created`, as returned by `create`.
+#[derive(Default)]
+pub struct Commands {
+    commands: Vec<(Command, usize)>,
+    components: Vec<Box<dyn ComponentCommands>>,
+    created: usize,
+}
+
+impl Commands {
+    /// Creates an empty command buffer.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Returns the number of recorded commands.
+    pub fn len(&self) -> usize {
+        self.commands.len()
+    }
+
+    /// Returns `true` if no command was recorded.
+    pub fn is_empty(&self) -> bool {
+        self.commands.is_empty()
+    }
+
+    /// Returns an iterator over the recorded commands, in order.
+    pub fn iter(&self) -> impl Iterator<Item = Command> + '_ {
+        self.commands.iter().map(|&(command, _)| command)
+    }
+
+    /// Returns an iterator over the recorded insertions of components of
+    /// type `C`, in order.
+    pub fn inserted<C>(&self) -> impl Iterator<Item = (CommandTarget, &C)> + '_
+    where
+        C: Component,
+    {
+        self.components
+            .iter()
+            .filter_map(|c| c.as_any().downcast_ref::<ComponentBuffer<C>>())
+            .flat_map(|buffer| buffer.inserted.iter().map(|&(target, ref c)| (target, c)))
+    }
+
+    /// Records the creation of an entity, returning the target referring
+    /// to it.
+    pub fn create(&mut self) -> CommandTarget {
+        let target = CommandTarget::Created(self.created);
+        self.created += 1;
+        self.commands.push((Command::Create, 0));
+
+        target
+    }
+
+    /// Records the deletion of an entity.
+    pub fn delete<T>(&mut self, target: T)
+    where
+        T: Into<CommandTarget>,
+    {
+        self.commands.push((Command::Delete(target.into()), 0));
+    }
+
+    /// Records the insertion of a component.
+    #[cfg(feature = "parallel")]
+    pub fn insert<C, T>(&mut self, target: T, component: C)
+    where
+        T: Into<CommandTarget>,
+        C: Component + Send + Sync,
+    {
+        let buffer = self.buffer::<C>();
+        self.insert_into(buffer, target.into(), component);
+    }
+
+    /// Records the insertion of a component.
+    #[cfg(not(feature = "parallel"))]
+    pub fn insert<C, T>(&mut self, target: T, component: C)
+    where
+        T: Into<CommandTarget>,
+        C: Component,
+    {
+        let buffer = self.buffer::<C>();
+        self.insert_into(buffer, target.into(), component);
+    }
+
+    /// Records the removal of a component.
+    #[cfg(feature = "parallel")]
+    pub fn remove<C, T>(&mut self, target: T)
+    where
+        T: Into<CommandTarget>,
+        C: Component + Send + Sync,
+    {
+        let buffer = self.buffer::<C>();
+        self.remove_from::<C>(buffer, target.into());
+    }
+
+    /// Records the removal of a component.
+    #[cfg(not(feature = "parallel"))]
+    pub fn remove<C, T>(&mut self, target: T)
+    where
+        T: Into<CommandTarget>,
+        C: Component,
+    {
+        let buffer = self.buffer::<C>();
+        self.remove_from::<C>(buffer, target.into());
+    }
+
+    /// Applies all commands in the order they were recorded.
+    ///
+    /// Commands for dead entities, or for `CommandTarget::Created` targets
+    /// which weren't returned by this `Commands`, are skipped with a warning.
+    pub fn apply(self, world: &mut World) {
+        let Commands {
+            commands,
+            mut components,
+            created,
+        } = self;
+
+        let mut entities = Vec::with_capacity(created);
+        for (command, buffer) in commands {
+            match command {
+                Command::Create => entities.push(world.create_entity().build()),
+                Command::Delete(target) => {
+                    if let Some(entity) = resolve(target, &entities) {
+                        if world.delete_entity(entity).is_err() {
+                            log::warn!(
+                                "Delete command failed because {:?} was already dead.",
+                                entity
+                            );
+                        }
+                    }
+                }
+                Command::Insert { target, .. } => match resolve(target, &entities) {
+                    Some(entity) => components[buffer].insert_next(world, entity),
+                    None => components[buffer].skip_next(),
+                },
+                Command::Remove { target, .. } => {
+                    if let Some(entity) = resolve(target, &entities) {
+                        components[buffer].remove(world, entity);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Returns the position of the component buffer for `C`, which is added
+    /// if necessary.
+    fn buffer<C>(&mut self) -> usize
+    where
+        ComponentBuffer<C>: ComponentCommands + 'static,
+    {
+        match self
+            .components
+            .iter()
+            .position(|c| c.as_any().is::<ComponentBuffer<C>>())
+        {
+            Some(position) => position,
+            None => {
+                self.components.push(Box::new(ComponentBuffer::<C> {
+                    inserted: VecDeque::new(),
+                }));
+
+                self.components.len() - 1
+            }
+        }
+    }
+
+    fn insert_into<C>(&mut self, buffer: usize, target: CommandTarget, component: C)
+    where
+        C: Component,
+    {
+        self.components[buffer]
+            .as_any_mut()
+            .downcast_mut::<ComponentBuffer<C>>()
+            .expect("Component buffer has the wrong type")
+            .inserted
+            .push_back((target, component));
+        self.commands.push((
+            Command::Insert {
+                target,
+                component: type_name::<C>(),
+            },
+            buffer,
+        ));
+    }
+
+    fn remove_from<C>(&mut self, buffer: usize, target: CommandTarget) {
+        self.commands.push((
+            Command::Remove {
+                target,
+                component: type_name::<C>(),
+            },
+            buffer,
+        ));
+    }
+}
+
+#[cfg(feature = "serde")]
+impl Commands {
+    /// Serializes the commands in order as a sequence, where insertions
+    /// carry the inserted component in a `data` field.
+    ///
+    /// `L` is a tuple of the types of the inserted components, which have
+    /// to implement `Serialize`. Serializing fails if a component of
+    /// another type was inserted.
+    ///
+    /// Only serialization is supported; the receiving side is expected to
+    /// record the commands again, e.g. to replay them in lockstep.
+    ///
+    /// ## Examples
+    ///
+    /// ```
+    /// # use specs::prelude::*;
+    /// # use specs::world::Commands;
+    /// # use serde::Serialize;
+    /// #[derive(Serialize)]
+    /// struct Pos(i32);
+    ///
+    /// impl Component for Pos {
+    ///     type Storage = VecStorage<Self>;
+    /// }
+    ///
+    /// let mut commands = Commands::new();
+    /// let entity = commands.create();
+    /// commands.insert(entity, Pos(3));
+    ///
+    /// let mut json = Vec::new();
+    /// commands
+    ///     .serialize::<(Pos,), _>(&mut serde_json::Serializer::new(&mut json))
+    ///     .unwrap();
+    /// ```
+    pub fn serialize<L, S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        L: SerializeInserted,
+        S: Serializer,
+    {
+        let mut seq = serializer.serialize_seq(Some(self.commands.len()))?;
+        // The position of the next component of each buffer.
+        let mut next = vec![0; self.components.len()];
+        for &(command, buffer) in &self.commands {
+            match command {
+                Command::Create => seq.serialize_element(&UntypedCommand::Create)?,
+                Command::Delete(target) => {
+                    seq.serialize_element(&UntypedCommand::Delete(target))?
+                }
+                Command::Insert { target, component } => {
+                    let index = next[buffer];
+                    next[buffer] += 1;
+                    let buffer = self.components[buffer].as_any();
+                    match L::serialize_inserted(&mut seq, buffer, index, target, component) {
+                        Some(result) => result?,
+                        None => {
+                            return Err(ser::Error::custom(format!(
+                                "Inserted component `{}` isn't part of the serialized types",
+                                component
+                            )))
+                        }
+                    }
+                }
+                Command::Remove { target, component } => {
+                    seq.serialize_element(&UntypedCommand::Remove { target, component })?
+                }
+            }
+        }
+
+        seq.end()
+    }
+}
+
+/// The serialized form of a `Command`, which includes the component of an
+/// insertion.
+#[cfg(feature = "serde")]
+#[derive(Serialize)]
+#[serde(rename = "Command")]
+enum SerializedCommand<'a, C> {
+    Create,
+    Delete(CommandTarget),
+    Insert {
+        target: CommandTarget,
+        component: &'static str,
+        data: &'a C,
+    },
+    Remove {
+        target: CommandTarget,
+        component: &'static str,
+    },
+}
+
+/// A `SerializedCommand` which isn't an insertion.
+#[cfg(feature = "serde")]
+type UntypedCommand<'a> = SerializedCommand<'a, ()>;
+
+/// Tuples of component types whose insertions can be serialized by
+/// `Commands::serialize`.
+#[cfg(feature = "serde")]
+pub trait SerializeInserted {
+    /// Serializes the `index`-th component of the component buffer `buffer`
+    /// as an element of `seq`. Returns `None` if the components of `buffer`
+    /// have none of the types of `Self`.
+    #[doc(hidden)]
+    fn serialize_inserted<S>(
+        seq: &mut S,
+        buffer: &dyn Any,
+        index: usize,
+        target: CommandTarget,
+        component: &'static str,
+    ) -> Option<Result<(), S::Error>>
+    where
+        S: SerializeSeq;
+}
+
+#[cfg(feature = "serde")]
+macro_rules! serialize_inserted {
+    ($($comp:ident,)*) => {
+        impl<$($comp,)*> SerializeInserted for ($($comp,)*)
+        where
+            $($comp: Component + Serialize,)*
+        {
+            #[allow(unused)]
+            fn serialize_inserted<S>(
+                seq: &mut S,
+                buffer: &dyn Any,
+                index: usize,
+                target: CommandTarget,
+                component: &'static str,
+            ) -> Option<Result<(), S::Error>>
+            where
+                S: SerializeSeq,
+            {
+                $(
+                    if let Some(buffer) = buffer.downcast_ref::<ComponentBuffer<$comp>>() {
+                        let (_, ref data) = buffer.inserted[index];
+                        return Some(seq.serialize_element(&SerializedCommand::Insert {
+                            target,
+                            component,
+                            data,
+                        }));
+                    }
+                )*
+
+                None
+            }
+        }
+
+        serialize_inserted!(@pop $($comp,)*);
+    };
+    (@pop) => {};
+    (@pop $head:ident, $($tail:ident,)*) => {
+        serialize_inserted!($($tail,)*);
+    };
+}
+
+#[cfg(feature = "serde")]
+serialize_inserted!(CA, CB, CC, CD, CE, CF, CG, CH, CI, CJ, CK, CL, CM, CN, CO, CP,);
+
+/// Returns the entity `target` refers to, or `None` with a warning if it
+/// refers to an entity which wasn't created by the same `Commands`.
+fn resolve(target: CommandTarget, created: &[Entity]) -> Option<Entity> {
+    match target {
+        CommandTarget::Entity(entity) => Some(entity),
+        CommandTarget::Created(n) => {
+            let entity = created.get(n).cloned();
+            if entity.is_none() {
+                log::warn!(
+                    "Skipped command for `CommandTarget::Created({})`, which wasn't created by the \
+                     same `Commands`.",
+                    n
+                );
+            }
+
+            entity
+        }
+    }
+}
+
+/// Resource collecting the `Commands` of all `CommandBuffer`s until they
+/// are applied by `WorldExt::maintain`.
+///
+/// The commands are applied ordered by the position at which the tag of
+/// their buffer was registered, which happens in the order the
+/// `CommandBuffer`s are set up. For a dispatcher, that's by stage and then
+/// by system order, no matter in which order the systems actually ran.
+/// Commands with the same tag, or with a tag which was never set up, are
+/// applied in the order they were submitted, which isn't deterministic for
+/// systems running in parallel.
+pub struct CommandQueue {
+    order: Vec<TypeId>,
+    queue: SegQueue<(usize, Commands)>,
+}
+
+impl Default for CommandQueue {
+    fn default() -> Self {
+        CommandQueue {
+            order: Vec::new(),
+            queue: SegQueue::new(),
+        }
+    }
+}
+
+impl CommandQueue {
+    /// Registers the tag `S`, giving it the next position in the order in
+    /// which commands are applied. Does nothing if `S` was already
+    /// registered.
+    pub fn register<S: 'static>(&mut self) {
+        let tag = TypeId::of::<S>();
+        if !self.order.contains(&tag) {
+            self.order.push(tag);
+        }
+    }
+
+    /// Submits commands tagged with `S`.
+    pub fn submit<S: 'static>(&self, commands: Commands) {
+        self.submit_tagged(TypeId::of::<S>(), commands);
+    }
+
+    fn submit_tagged(&self, tag: TypeId, commands: Commands) {
+        let order = self
+            .order
+            .iter()
+            .position(|&t| t == tag)
+            .unwrap_or(self.order.len());
+
+        self.queue.push((order, commands));
+    }
+
+    /// Takes all submitted commands, in the order they are applied.
+    ///
+    /// This can be used to inspect or send the commands before applying
+    /// them manually.
+    pub fn drain(&mut self) -> Vec<Commands> {
+        let mut submitted = Vec::new();
+        while let Ok(commands) = self.queue.pop() {
+            submitted.push(commands);
+        }
+        // The sort is stable, so submissions with the same position stay in
+        // order.
+        submitted.sort_by_key(|&(order, _)| order);
+
+        submitted.into_iter().map(|(_, commands)| commands).collect()
+    }
+}
+
+/// `SystemData` for recording `Commands`, which are applied by
+/// `WorldExt::maintain` in a deterministic order.
+///
+/// `S` is a tag, usually the system itself, determining the order in which
+/// the commands of different systems are applied; see `CommandQueue` for
+/// details. Every system should use its own tag, as the commands of
+/// systems sharing a tag are applied in the nondeterministic order in which
+/// they finished. The commands are submitted once the `CommandBuffer` is dropped.
+///
+/// ## Examples
+///
+/// ```
+/// # use specs::prelude::*;
+/// # use specs::world::CommandBuffer;
+/// #[derive(Debug, PartialEq)]
+/// struct Pos(i32);
+///
+/// impl Component for Pos {
+///     type Storage = VecStorage<Self>;
+/// }
+///
+/// struct Spawn;
+///
+/// impl<'a> System<'a> for Spawn {
+///     type SystemData = CommandBuffer<'a, Self>;
+///
+///     fn run(&mut self, mut commands: Self::SystemData) {
+///         let entity = commands.create();
+///         commands.insert(entity, Pos(3));
+///     }
+/// }
+///
+/// let mut world = World::new();
+/// world.register::<Pos>();
+///
+/// let mut dispatcher = DispatcherBuilder::new().with(Spawn, "spawn", &[]).build();
+/// dispatcher.setup(&mut world);
+/// dispatcher.dispatch(&world);
+/// assert_eq!(world.read_storage::<Pos>().count(), 0);
+///
+/// world.maintain();
+/// assert_eq!(world.read_storage::<Pos>().join().collect::<Vec<_>>(), vec![&Pos(3)]);
+/// ```
+pub struct CommandBuffer<'a, S> {
+    queue: Fetch<'a, CommandQueue>,
+    tag: TypeId,
+    commands: Commands,
+    phantom: PhantomData<fn() -> S>,
+}
+
+impl<'a, S> Deref for CommandBuffer<'a, S> {
+    type Target = Commands;
+
+    fn deref(&self) -> &Commands {
+        &self.commands
+    }
+}
+
+impl<'a, S> DerefMut for CommandBuffer<'a, S> {
+    fn deref_mut(&mut self) -> &mut Commands {
+        &mut self.commands
+    }
+}
+
+impl<'a, S> Drop for CommandBuffer<'a, S> {
+    fn drop(&mut self) {
+        if !self.commands.is_empty() {
+            let commands = std::mem::replace(&mut self.commands, Commands::new());
+            self.queue.submit_tagged(self.tag, commands);
+        }
+    }
+}
+
+impl<'a, S> SystemData<'a> for CommandBuffer<'a, S>
+where
+    S: 'static,
+{
+    fn setup(res: &mut World) {
+        res.entry::<CommandQueue>().or_insert_with(Default::default);
+        res.fetch_mut::<CommandQueue>().register::<S>();
+    }
+
+    fn fetch(res: &'a World) -> Self {
+        CommandBuffer {
+            queue: res.fetch(),
+            tag: TypeId::of::<S>(),
+            commands: Commands::new(),
+            phantom: PhantomData,
+        }
+    }
+
+    fn reads() -> Vec<ResourceId> {
+        vec![ResourceId::new::<CommandQueue>()]
+    }
+
+    fn writes() -> Vec<ResourceId> {
+        vec![]
+    }
+}
@@ -1,6 +1,9 @@
 use std::any::Any;
 
-use crate::storage::UnprotectedStorage;
+use crate::{
+    storage::UnprotectedStorage,
+    world::{Entity, LazyUpdate},
+};
 
 /// Abstract component type.
 /// Doesn't have to be Copy or even Clone.
@@ -60,6 +63,47 @@ use crate::storage::UnprotectedStorage;
 ///     type Storage = HashMapStorage<Self>;
 /// }
 /// ```
+///
+/// ## Lifecycle hooks
+///
+/// `on_insert` and `on_remove` are invoked whenever a component is inserted
+/// into or removed from its storage, including replacements, `clear`,
+/// draining and the removal of the components of deleted entities. When a
+/// component is replaced, `on_insert` of the new one is invoked before
+/// `on_remove` of the old one. They can queue follow-up work through the
+/// `LazyUpdate` of the world, which runs during the next `World::maintain`.
+///
+/// Hooks aren't invoked when a storage is restored from a snapshot or
+/// dropped together with its `World`, nor by the index-based
+/// `MaskedStorage::remove` and `MaskedStorage::drop`.
+///
+/// ```
+/// use specs::prelude::*;
+///
+/// struct Sound(u32);
+///
+/// #[derive(Default)]
+/// struct Released(Vec<u32>);
+///
+/// impl Component for Sound {
+///     type Storage = DenseVecStorage<Self>;
+///
+///     fn on_remove(&mut self, _entity: Entity, lazy: &LazyUpdate) {
+///         let handle = self.0;
+///         lazy.exec_mut(move |world| world.fetch_mut::<Released>().0.push(handle));
+///     }
+/// }
+///
+/// let mut world = World::new();
+/// world.register::<Sound>();
+/// world.insert(Released::default());
+///
+/// let e = world.create_entity().with(Sound(7)).build();
+/// world.delete_entity(e).unwrap();
+/// world.maintain();
+///
+/// assert_eq!(world.fetch::<Released>().0, vec![7]);
+/// ```
 pub trait Component: Any + Sized {
     /// Associated storage type for this component.
     #[cfg(feature = "parallel")]
@@ -68,4 +112,12 @@ pub trait Component: Any + Sized {
     /// Associated storage type for this component.
     #[cfg(not(feature = "parallel"))]
     type Storage: UnprotectedStorage<Self> + Any;
+
+    /// Called when this component is inserted for `entity`. Does nothing by
+    /// default.
+    fn on_insert(&mut self, _entity: Entity, _lazy: &LazyUpdate) {}
+
+    /// Called when this component is removed from `entity`, no matter if
+    /// it's returned or dropped. Does nothing by default.
+    fn on_remove(&mut self, _entity: Entity, _lazy: &LazyUpdate) {}
 }
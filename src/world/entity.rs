@@ -4,7 +4,7 @@ use std::{
     sync::atomic::{AtomicUsize, Ordering},
 };
 
-use hibitset::{AtomicBitSet, BitSet, BitSetOr};
+use hibitset::{AtomicBitSet, BitSet, BitSetAnd, BitSetNot, BitSetOr};
 use shred::Read;
 
 #[cfg(feature = "parallel")]
@@ -54,6 +54,9 @@ pub(crate) struct Allocator {
     alive: BitSet,
     raised: AtomicBitSet,
     killed: AtomicBitSet,
+    disabled: BitSet,
+    disable: AtomicBitSet,
+    enable: AtomicBitSet,
     cache: EntityCache,
     max_id: AtomicUsize,
 }
@@ -71,6 +74,9 @@ impl Allocator {
             self.alive.remove(entity.id());
             // If the `Entity` was killed by `kill_atomic`, remove the bit set by it.
             self.killed.remove(entity.id());
+            self.disabled.remove(entity.id());
+            self.disable.remove(entity.id());
+            self.enable.remove(entity.id());
 
             self.update_generation_length(id);
 
@@ -185,11 +191,25 @@ impl Allocator {
 
         for i in (&self.killed).iter() {
             self.alive.remove(i);
+            self.disabled.remove(i);
             deleted.push(Entity(i, self.generations[i as usize].0.unwrap()));
             self.generations[i as usize].die();
         }
         self.killed.clear();
 
+        // Requests for entities deleted in the meantime are dropped, and
+        // disabling wins over enabling.
+        for i in (&self.enable).iter() {
+            self.disabled.remove(i);
+        }
+        self.enable.clear();
+        for i in (&self.disable).iter() {
+            if self.alive.contains(i) {
+                self.disabled.add(i);
+            }
+        }
+        self.disable.clear();
+
         self.cache.extend(deleted.iter().map(|e| e.0));
 
         deleted
@@ -203,6 +223,41 @@ impl Allocator {
     }
 }
 
+impl Clone for Allocator {
+    fn clone(&self) -> Self {
+        use hibitset::BitSetLike;
+
+        let mut raised = AtomicBitSet::new();
+        for i in (&self.raised).iter() {
+            raised.add(i);
+        }
+        let mut killed = AtomicBitSet::new();
+        for i in (&self.killed).iter() {
+            killed.add(i);
+        }
+        let mut disable = AtomicBitSet::new();
+        for i in (&self.disable).iter() {
+            disable.add(i);
+        }
+        let mut enable = AtomicBitSet::new();
+        for i in (&self.enable).iter() {
+            enable.add(i);
+        }
+
+        Allocator {
+            generations: self.generations.clone(),
+            alive: self.alive.clone(),
+            raised,
+            killed,
+            disabled: self.disabled.clone(),
+            disable,
+            enable,
+            cache: self.cache.clone(),
+            max_id: AtomicUsize::new(self.max_id.load(Ordering::Relaxed)),
+        }
+    }
+}
+
 /// An iterator for entity creation.
 /// Please note that you have to consume
 /// it because iterators are lazy.
@@ -296,6 +351,9 @@ impl EntitiesRes {
     /// Deletes an entity atomically.
     /// The associated components will be
     /// deleted as soon as you call `World::maintain`.
+    ///
+    /// If the world has a `Hierarchy`, the descendants of the entity are
+    /// deleted during `World::maintain` as well.
     pub fn delete(&self, e: Entity) -> Result<(), WrongGeneration> {
         self.alloc.kill_atomic(e)
     }
@@ -311,30 +369,134 @@ impl EntitiesRes {
     pub fn is_alive(&self, e: Entity) -> bool {
         self.alloc.is_alive(e)
     }
+
+    /// Enables or disables an entity. Disabled entities keep their
+    /// components, but are skipped when joining over `EntitiesRes` or
+    /// storages, unless the join opts in through `with_disabled`.
+    /// Accessing their components directly, e.g. with `Storage::get`, is
+    /// unaffected.
+    ///
+    /// Like `delete`, this only needs a shared reference, so it can be
+    /// called from systems, and the change takes effect with the next call
+    /// to `WorldExt::maintain`. If an entity is both disabled and enabled
+    /// before that, it ends up disabled.
+    ///
+    /// Entities are enabled when created, and deleting an entity resets
+    /// its state.
+    ///
+    /// ## Examples
+    ///
+    /// ```
+    /// # use specs::prelude::*;
+    /// # struct Pos;
+    /// # impl Component for Pos { type Storage = VecStorage<Self>; }
+    /// let mut world = World::new();
+    /// world.register::<Pos>();
+    ///
+    /// let a = world.create_entity().with(Pos).build();
+    /// let b = world.create_entity().with(Pos).build();
+    /// world.entities().set_enabled(b, false).unwrap();
+    /// world.maintain();
+    ///
+    /// let entities = world.entities();
+    /// let positions = world.read_storage::<Pos>();
+    /// let enabled: Vec<_> = (&entities, &positions).join().map(|(e, _)| e).collect();
+    /// assert_eq!(enabled, vec![a]);
+    ///
+    /// let all = (entities.with_disabled(), positions.with_disabled())
+    ///     .join()
+    ///     .count();
+    /// assert_eq!(all, 2);
+    /// ```
+    pub fn set_enabled(&self, e: Entity, enabled: bool) -> Result<(), WrongGeneration> {
+        if !self.is_alive(e) {
+            return Err(WrongGeneration {
+                action: "set enabled state",
+                actual_gen: self.entity(e.id()).gen(),
+                entity: e,
+            });
+        }
+
+        if enabled {
+            self.alloc.enable.add_atomic(e.id());
+        } else {
+            self.alloc.disable.add_atomic(e.id());
+        }
+
+        Ok(())
+    }
+
+    /// Returns `false` if the entity was disabled with `set_enabled`.
+    ///
+    /// Changes only show up after the next `WorldExt::maintain`.
+    pub fn is_enabled(&self, e: Entity) -> bool {
+        !self.alloc.disabled.contains(e.id())
+    }
+
+    /// Returns the ids of all disabled entities.
+    pub fn disabled(&self) -> &BitSet {
+        &self.alloc.disabled
+    }
+
+    /// Returns a joinable wrapper yielding all entities, including the
+    /// disabled ones.
+    pub fn with_disabled(&self) -> WithDisabled<&Self> {
+        WithDisabled(self)
+    }
+
+    fn get_entity(&self, idx: Index) -> Entity {
+        let gen = self
+            .alloc
+            .generation(idx)
+            .map(|gen| if gen.is_alive() { gen } else { gen.raised() })
+            .unwrap_or_else(Generation::one);
+        Entity(idx, gen)
+    }
 }
 
 impl<'a> Join for &'a EntitiesRes {
-    type Mask = BitSetOr<&'a BitSet, &'a AtomicBitSet>;
+    type Mask = BitSetAnd<BitSetOr<&'a BitSet, &'a AtomicBitSet>, BitSetNot<&'a BitSet>>;
     type Type = Entity;
     type Value = Self;
 
     unsafe fn open(self) -> (Self::Mask, Self) {
-        (BitSetOr(&self.alloc.alive, &self.alloc.raised), self)
+        let mask = BitSetOr(&self.alloc.alive, &self.alloc.raised);
+
+        (BitSetAnd(mask, BitSetNot(&self.alloc.disabled)), self)
     }
 
     unsafe fn get(v: &mut &'a EntitiesRes, idx: Index) -> Entity {
-        let gen = v
-            .alloc
-            .generation(idx)
-            .map(|gen| if gen.is_alive() { gen } else { gen.raised() })
-            .unwrap_or_else(Generation::one);
-        Entity(idx, gen)
+        v.get_entity(idx)
     }
 }
 
 #[cfg(feature = "parallel")]
 unsafe impl<'a> ParJoin for &'a EntitiesRes {}
 
+/// Joinable wrapper around `EntitiesRes` or a `Storage` which doesn't skip
+/// disabled entities. Created by `EntitiesRes::with_disabled`,
+/// `Storage::with_disabled` and `Storage::with_disabled_mut`.
+///
+/// See `EntitiesRes::set_enabled`.
+pub struct WithDisabled<J>(pub(crate) J);
+
+impl<'a> Join for WithDisabled<&'a EntitiesRes> {
+    type Mask = BitSetOr<&'a BitSet, &'a AtomicBitSet>;
+    type Type = Entity;
+    type Value = &'a EntitiesRes;
+
+    unsafe fn open(self) -> (Self::Mask, Self::Value) {
+        (BitSetOr(&self.0.alloc.alive, &self.0.alloc.raised), self.0)
+    }
+
+    unsafe fn get(v: &mut &'a EntitiesRes, idx: Index) -> Entity {
+        v.get_entity(idx)
+    }
+}
+
+#[cfg(feature = "parallel")]
+unsafe impl ParJoin for WithDisabled<&EntitiesRes> {}
+
 /// An entity builder from `EntitiesRes`.  Allows building an entity with its
 /// components if you have mutable access to the component storages.
 #[must_use = "Please call .build() on this to finish building it."]
@@ -489,6 +651,18 @@ impl EntityCache {
     }
 }
 
+impl Clone for EntityCache {
+    fn clone(&self) -> Self {
+        // Only the first `len` indices are still part of the cache.
+        let len = self.len.load(Ordering::Relaxed);
+
+        EntityCache {
+            cache: self.cache[..len].to_vec(),
+            len: AtomicUsize::new(len),
+        }
+    }
+}
+
 impl Extend<Index> for EntityCache {
     fn extend<T: IntoIterator<Item = Index>>(&mut self, iter: T) {
         self.maintain();
@@ -1,3 +1,5 @@
+use std::sync::Arc;
+
 use crossbeam_queue::SegQueue;
 
 use crate::{prelude::*, world::EntitiesRes};
@@ -151,14 +153,22 @@ where
 /// Please note that the provided methods take `&self`
 /// so there's no need to get `LazyUpdate` mutably.
 /// This resource is added to the world by default.
+///
+/// Updates queued while the lazy updates are run, for example by the
+/// `Component::on_remove` of a component removed by a lazy update, are left
+/// for the next `maintain`.
 pub struct LazyUpdate {
-    queue: Option<Queue<Box<dyn LazyUpdateInternal>>>,
+    queue: Arc<Queue<Box<dyn LazyUpdateInternal>>>,
+    /// `false` for the handles created by `share`; only the original handle
+    /// discards the pending updates when dropped.
+    owner: bool,
 }
 
 impl Default for LazyUpdate {
     fn default() -> Self {
         Self {
-            queue: Some(Default::default()),
+            queue: Default::default(),
+            owner: true,
         }
     }
 }
@@ -310,11 +320,7 @@ impl LazyUpdate {
         where
             F: FnOnce(&mut World) + 'static,
         {
-            self.queue
-                .as_ref()
-                .unwrap()
-                .0
-                .push(Box::new(|w: &mut World| f(w)));
+            self.queue.0.push(Box::new(|w: &mut World| f(w)));
         }
 
         /// Lazily executes a closure with mutable world access.
@@ -346,7 +352,7 @@ impl LazyUpdate {
         where
             F: FnOnce(&mut World) + 'static,
         {
-            self.queue.as_ref().unwrap().0.push(Box::new(f));
+            self.queue.0.push(Box::new(f));
         }
     }
 
@@ -376,25 +382,24 @@ impl LazyUpdate {
         LazyBuilder { entity, lazy: self }
     }
 
-    /// Allows to temporarily take the inner queue.
-    pub(super) fn take(&mut self) -> Self {
+    /// Returns a handle to the same queue. This is how component storages
+    /// hand the `LazyUpdate` of their world to `Component` hooks.
+    pub(crate) fn share(&self) -> Self {
         Self {
-            queue: self.queue.take(),
+            queue: Arc::clone(&self.queue),
+            owner: false,
         }
     }
 
-    /// Needs to be called to restore the inner queue.
-    pub(super) fn restore(&mut self, mut maintained: Self) {
-        use std::mem::swap;
-
-        swap(&mut self.queue, &mut maintained.queue);
-    }
-
-    pub(super) fn maintain(&mut self, world: &mut World) {
-        let lazy = &mut self.queue.as_mut().unwrap().0;
-
-        while let Ok(l) = lazy.pop() {
-            l.update(world);
+    pub(super) fn maintain(&self, world: &mut World) {
+        // Only run the updates queued so far, so an update which queues
+        // itself again doesn't loop forever.
+        let pending = self.queue.0.len();
+        for _ in 0..pending {
+            match self.queue.0.pop() {
+                Ok(l) => l.update(world),
+                Err(_) => break,
+            }
         }
     }
 }
@@ -402,8 +407,8 @@ impl LazyUpdate {
 impl Drop for LazyUpdate {
     fn drop(&mut self) {
         // TODO: remove as soon as leak is fixed in crossbeam
-        if let Some(queue) = self.queue.as_mut() {
-            while queue.0.pop().is_ok() {}
+        if self.owner {
+            while self.queue.0.pop().is_ok() {}
         }
     }
 }
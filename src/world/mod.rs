@@ -3,21 +3,28 @@
 pub use shred::World;
 
 pub use self::{
+    commands::{Command, CommandBuffer, CommandQueue, CommandTarget, Commands},
     comp::Component,
     entity::{
         CreateIterAtomic, Entities, EntitiesRes, Entity, EntityResBuilder, Generation, Index,
+        WithDisabled,
     },
     lazy::{LazyBuilder, LazyUpdate},
+    snapshot::WorldSnapshot,
     world_ext::WorldExt,
 };
+#[cfg(feature = "serde")]
+pub use self::commands::SerializeInserted;
 
 use shred::{FetchMut, SystemData};
 
 use crate::storage::WriteStorage;
 
+mod commands;
 mod comp;
 mod entity;
 mod lazy;
+mod snapshot;
 #[cfg(test)]
 mod tests;
 mod world_ext;
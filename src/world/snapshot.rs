@@ -0,0 +1,23 @@
+use std::any::TypeId;
+
+use hashbrown::HashMap;
+
+use super::entity::Allocator;
+use crate::storage::StorageSnapshot;
+
+/// A copy of the entities and the cloneable components of a `World`.
+///
+/// Created with `WorldExt::snapshot` and applied with `WorldExt::restore`.
+/// Only components registered with `WorldExt::register_cloneable` are part
+/// of the snapshot; entity ids and generations always are.
+pub struct WorldSnapshot {
+    pub(super) allocator: Allocator,
+    pub(super) storages: HashMap<TypeId, StorageSnapshot>,
+}
+
+impl WorldSnapshot {
+    /// Returns `true` if the components of type `T` were captured.
+    pub fn contains<T: 'static>(&self) -> bool {
+        self.storages.contains_key(&TypeId::of::<T>())
+    }
+}
@@ -1,4 +1,5 @@
 use super::{WorldExt, *};
+use shred::SystemData;
 use crate::{join::Join, storage::VecStorage};
 
 struct Pos;
@@ -113,6 +114,24 @@ fn lazy_execution_order() {
     assert_eq!(&**v, &[1, 2]);
 }
 
+#[test]
+fn lazy_requeue() {
+    fn count(world: &mut World) {
+        *world.write_resource::<u32>() += 1;
+        world.read_resource::<LazyUpdate>().exec_mut(count);
+    }
+
+    let mut world = World::new();
+    world.insert(0u32);
+    world.read_resource::<LazyUpdate>().exec_mut(count);
+
+    // Updates queued by updates are left for the next `maintain`.
+    world.maintain();
+    assert_eq!(*world.read_resource::<u32>(), 1);
+    world.maintain();
+    assert_eq!(*world.read_resource::<u32>(), 2);
+}
+
 #[test]
 fn delete_twice() {
     let mut world = World::new();
@@ -143,3 +162,290 @@ fn delete_and_lazy() {
 
     world.delete_all();
 }
+
+#[derive(Clone, Debug, PartialEq)]
+struct Health(u32);
+
+impl Component for Health {
+    type Storage = VecStorage<Self>;
+}
+
+#[test]
+fn snapshot_restore() {
+    let mut world = World::new();
+    world.register::<Health>();
+    world.register::<Pos>();
+    world.register_cloneable::<Health>();
+
+    let a = world.create_entity().with(Health(10)).with(Pos).build();
+    let b = world.create_entity().with(Health(20)).build();
+    let snapshot = world.snapshot();
+    assert!(snapshot.contains::<Health>());
+    assert!(!snapshot.contains::<Pos>());
+
+    world.write_storage::<Health>().get_mut(a).unwrap().0 = 0;
+    world.delete_entity(b).unwrap();
+    let c = world.create_entity().with(Health(30)).with(Pos).build();
+    assert_eq!(c.id(), b.id());
+
+    world.restore(&snapshot);
+
+    assert!(world.is_alive(a));
+    assert!(world.is_alive(b));
+    assert!(!world.is_alive(c));
+    assert_eq!(world.read_storage::<Health>().get(a), Some(&Health(10)));
+    assert_eq!(world.read_storage::<Health>().get(b), Some(&Health(20)));
+    // `Pos` is not cloneable, so it's only removed from dead entities.
+    assert!(world.read_storage::<Pos>().get(a).is_some());
+    assert!(world.read_storage::<Pos>().get(b).is_none());
+
+    // Allocation continues exactly like it would have after the snapshot.
+    world.delete_entity(b).unwrap();
+    assert_eq!(world.create_entity().build(), c);
+}
+
+#[test]
+fn snapshot_restore_twice() {
+    let mut world = World::new();
+    world.register::<Health>();
+    world.register_cloneable::<Health>();
+
+    let e = world.create_entity().with(Health(1)).build();
+    let snapshot = world.snapshot();
+
+    for _ in 0..2 {
+        world.write_storage::<Health>().insert(e, Health(2)).unwrap();
+        world.restore(&snapshot);
+        assert_eq!(world.read_storage::<Health>().get(e), Some(&Health(1)));
+    }
+}
+
+#[test]
+fn components_of_and_clone_entity() {
+    use std::any::TypeId;
+
+    let mut world = World::new();
+    world.register::<Health>();
+    world.register::<Pos>();
+    world.register_cloneable::<Health>();
+
+    let a = world.create_entity().with(Health(10)).with(Pos).build();
+    let mut components = world.components_of(a);
+    components.sort_by_key(|&(_, name)| name);
+    assert_eq!(
+        components,
+        vec![
+            (TypeId::of::<Health>(), std::any::type_name::<Health>()),
+            (TypeId::of::<Pos>(), std::any::type_name::<Pos>()),
+        ]
+    );
+
+    let b = world.clone_entity(a).unwrap();
+    assert_ne!(a, b);
+    assert_eq!(world.read_storage::<Health>().get(b), Some(&Health(10)));
+    // `Pos` is not cloneable.
+    assert!(world.read_storage::<Pos>().get(b).is_none());
+    assert_eq!(world.components_of(b).len(), 1);
+
+    world.delete_entity(a).unwrap();
+    assert!(world.components_of(a).is_empty());
+    assert!(world.clone_entity(a).is_err());
+}
+
+#[test]
+fn command_buffer() {
+    let mut world = World::new();
+    world.register::<Health>();
+    world.register::<Pos>();
+
+    let a = world.create_entity().with(Health(1)).with(Pos).build();
+    let b = world.create_entity().build();
+
+    let mut commands = Commands::new();
+    let c = commands.create();
+    commands.insert(c, Health(3));
+    commands.insert(a, Health(2));
+    commands.remove::<Pos, _>(a);
+    commands.delete(b);
+
+    assert_eq!(commands.len(), 5);
+    assert_eq!(
+        commands.inserted::<Health>().collect::<Vec<_>>(),
+        vec![(c, &Health(3)), (a.into(), &Health(2))]
+    );
+    assert_eq!(commands.inserted::<Pos>().count(), 0);
+    assert_eq!(
+        commands.iter().nth(3),
+        Some(Command::Remove {
+            target: a.into(),
+            component: std::any::type_name::<Pos>(),
+        })
+    );
+
+    commands.apply(&mut world);
+
+    assert!(!world.is_alive(b));
+    assert!(world.read_storage::<Pos>().get(a).is_none());
+    let health = world.read_storage::<Health>();
+    assert_eq!(health.get(a), Some(&Health(2)));
+    assert_eq!((&health).join().filter(|h| **h == Health(3)).count(), 1);
+}
+
+#[test]
+fn commands_foreign_created_target() {
+    let mut world = World::new();
+    world.register::<Health>();
+
+    let a = world.create_entity().build();
+
+    let mut other = Commands::new();
+    other.create();
+    let foreign = other.create();
+
+    let mut commands = Commands::new();
+    commands.insert(foreign, Health(1));
+    commands.insert(a, Health(2));
+    commands.delete(foreign);
+
+    // Commands for the foreign target are skipped.
+    commands.apply(&mut world);
+
+    assert!(world.is_alive(a));
+    let health = world.read_storage::<Health>();
+    assert_eq!(health.get(a), Some(&Health(2)));
+    assert_eq!((&health).join().count(), 1);
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn serialize_commands() {
+    #[derive(serde::Serialize)]
+    struct Score(u32);
+
+    impl Component for Score {
+        type Storage = VecStorage<Self>;
+    }
+
+    let mut world = World::new();
+    let a = world.create_entity().build();
+
+    let mut commands = Commands::new();
+    let c = commands.create();
+    commands.insert(c, Score(3));
+    commands.remove::<Score, _>(a);
+    commands.delete(a);
+
+    let json = {
+        let mut json = Vec::new();
+        commands
+            .serialize::<(Score,), _>(&mut serde_json::Serializer::new(&mut json))
+            .unwrap();
+        serde_json::from_slice::<serde_json::Value>(&json).unwrap()
+    };
+    let score = std::any::type_name::<Score>();
+    assert_eq!(
+        json,
+        serde_json::json!([
+            "Create",
+            { "Insert": { "target": { "Created": 0 }, "component": score, "data": 3 } },
+            { "Remove": { "target": { "Entity": [a.id(), a.gen().id()] }, "component": score } },
+            { "Delete": { "Entity": [a.id(), a.gen().id()] } },
+        ])
+    );
+
+    // Insertions of types which aren't listed can't be serialized.
+    commands.insert(a, Health(1));
+    let mut json = Vec::new();
+    assert!(commands
+        .serialize::<(Score,), _>(&mut serde_json::Serializer::new(&mut json))
+        .is_err());
+}
+
+#[test]
+fn command_buffer_order() {
+    struct First;
+    struct Second;
+
+    let mut world = World::new();
+    world.register::<Health>();
+    <CommandBuffer<First> as SystemData>::setup(&mut world);
+    <CommandBuffer<Second> as SystemData>::setup(&mut world);
+
+    let e = world.create_entity().build();
+    {
+        let mut second: CommandBuffer<Second> = SystemData::fetch(&world);
+        second.insert(e, Health(2));
+    }
+    {
+        let mut first: CommandBuffer<First> = SystemData::fetch(&world);
+        first.insert(e, Health(1));
+    }
+
+    world.maintain();
+
+    // `Second` was set up after `First`, so its commands are applied last.
+    assert_eq!(world.read_storage::<Health>().get(e), Some(&Health(2)));
+    assert!(world.write_resource::<CommandQueue>().drain().is_empty());
+}
+
+#[test]
+fn disabled_entities() {
+    let mut world = World::new();
+    world.register::<Pos>();
+    world.register::<Vel>();
+
+    let a = world.create_entity().with(Pos).with(Vel).build();
+    let b = world.create_entity().with(Pos).with(Vel).build();
+    world.entities().set_enabled(b, false).unwrap();
+    assert!(world.entities().is_enabled(b));
+    world.maintain();
+    assert!(world.entities().is_enabled(a));
+    assert!(!world.entities().is_enabled(b));
+
+    {
+        let entities = world.entities();
+        let pos = world.read_storage::<Pos>();
+        let mut vel = world.write_storage::<Vel>();
+
+        let joined: Vec<_> = (&entities, &pos, &mut vel).join().map(|(e, _, _)| e).collect();
+        assert_eq!(joined, vec![a]);
+
+        let all: Vec<_> = (entities.with_disabled(), pos.with_disabled(), vel.with_disabled_mut())
+            .join()
+            .map(|(e, _, _)| e)
+            .collect();
+        assert_eq!(all, vec![a, b]);
+
+        // Direct access is unaffected.
+        assert!(pos.get(b).is_some());
+
+        let restricted: Vec<_> = (&entities, &pos.restrict())
+            .join()
+            .map(|(e, _)| e)
+            .collect();
+        assert_eq!(restricted, vec![a]);
+        let restricted: Vec<_> = (&entities, &mut vel.restrict_mut())
+            .join()
+            .map(|(e, _)| e)
+            .collect();
+        assert_eq!(restricted, vec![a]);
+    }
+
+    // Disabling wins if both are requested before `maintain`.
+    world.entities().set_enabled(a, true).unwrap();
+    world.entities().set_enabled(a, false).unwrap();
+    world.entities().set_enabled(b, true).unwrap();
+    world.maintain();
+    assert!(!world.entities().is_enabled(a));
+    assert!(world.entities().is_enabled(b));
+    world.entities().set_enabled(a, true).unwrap();
+    world.maintain();
+
+    // Disabled entities are still deleted.
+    world.delete_all();
+    assert!(world.read_storage::<Pos>().get(b).is_none());
+
+    // Deleting resets the state, so a reused id is enabled.
+    assert!(!world.entities().disabled().contains(b.id()));
+    assert!(world.entities().set_enabled(b, true).is_err());
+}
@@ -1,15 +1,24 @@
+use std::any::TypeId;
+
 use super::{
     comp::Component,
     entity::{Allocator, EntitiesRes, Entity},
-    CreateIter, EntityBuilder, LazyUpdate,
+    CommandQueue, CreateIter, EntityBuilder, LazyUpdate, WorldSnapshot,
 };
 
 use crate::{
     error::WrongGeneration,
-    storage::{AnyStorage, MaskedStorage},
+    hierarchy,
+    join::Join,
+    mailbox::{self, Mailbox, MailboxRegistry},
+    named::{self, IndexRegistry, Name, NameIndex, Tag, TagIndex},
+    relation::{self, EntityRefs, RefPolicy, RefRegistry},
+    storage::{AnyStorage, CloneStorage, DynamicComponents, MaskedStorage},
     ReadStorage, WriteStorage,
 };
 use shred::{Fetch, FetchMut, MetaTable, Read, Resource, SystemData, World};
+#[cfg(feature = "reflect")]
+use serde::{de::DeserializeOwned, Serialize};
 
 /// This trait provides some extension methods to make working with shred's
 /// [World] easier.
@@ -140,6 +149,187 @@ pub trait WorldExt {
         F: FnOnce() -> T::Storage,
         T: Component;
 
+    /// Registers the storage of an already registered component as
+    /// cloneable, which makes its components part of `snapshot`.
+    ///
+    /// Does nothing if the component was already registered as cloneable.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the component hasn't been `register()`ed.
+    #[cfg(feature = "parallel")]
+    fn register_cloneable<T>(&mut self)
+    where
+        T: Component + Clone + Send + Sync;
+
+    /// Registers the storage of an already registered component as
+    /// cloneable, which makes its components part of `snapshot`.
+    ///
+    /// Does nothing if the component was already registered as cloneable.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the component hasn't been `register()`ed.
+    #[cfg(not(feature = "parallel"))]
+    fn register_cloneable<T>(&mut self)
+    where
+        T: Component + Clone;
+
+    /// Registers the storage of an already registered component as
+    /// reflectable, which allows (de)serializing its components through
+    /// `AnyStorage::serialize_for` and `AnyStorage::deserialize_into`
+    /// without knowing their type.
+    ///
+    /// ## Examples
+    ///
+    /// ```
+    /// # use serde::{Deserialize, Serialize};
+    /// # use specs::{prelude::*, shred::MetaTable, storage::AnyStorage};
+    /// #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    /// struct Pos(f32);
+    ///
+    /// impl Component for Pos {
+    ///     type Storage = VecStorage<Self>;
+    /// }
+    ///
+    /// let mut world = World::new();
+    /// world.register::<Pos>();
+    /// world.register_reflect::<Pos>();
+    ///
+    /// let e = world.create_entity().with(Pos(1.0)).build();
+    ///
+    /// let entities = world.entities();
+    /// let mut table = world.fetch_mut::<MetaTable<dyn AnyStorage>>();
+    /// for storage in table.iter_mut(&world).filter(|storage| storage.has(e)) {
+    ///     let value = storage.serialize_for(e).unwrap().unwrap();
+    ///     assert_eq!(value, serde_json::json!(1.0));
+    ///     storage
+    ///         .deserialize_into(&entities, e, serde_json::json!(2.0))
+    ///         .unwrap();
+    /// }
+    /// # drop((entities, table));
+    ///
+    /// assert_eq!(world.read_storage::<Pos>().get(e), Some(&Pos(2.0)));
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if the component hasn't been `register()`ed.
+    #[cfg(feature = "reflect")]
+    fn register_reflect<T>(&mut self)
+    where
+        T: Component + Serialize + DeserializeOwned;
+
+    /// Registers an already registered component as containing
+    /// `EntityRef`s. Once a referenced entity is deleted, `policy` decides
+    /// what happens to the referencing components; see the `relation`
+    /// module for details.
+    ///
+    /// Registering a component again replaces its policy.
+    fn register_entity_refs<T>(&mut self, policy: RefPolicy)
+    where
+        T: Component + EntityRefs;
+
+    /// Registers the `Name` component together with a `NameIndex`, which is
+    /// updated on every call to `maintain`. See the `named` module.
+    ///
+    /// Does nothing if the index is already registered.
+    fn register_names(&mut self);
+
+    /// Registers the `Tag<T>` component together with a `TagIndex<T>`, which
+    /// is updated on every call to `maintain`. See the `named` module.
+    ///
+    /// Does nothing if the index is already registered.
+    fn register_tags<T>(&mut self)
+    where
+        T: Clone + Eq + std::hash::Hash + Send + Sync + 'static;
+
+    /// Inserts an empty `Mailbox<E>` and registers it for clearing, so that
+    /// events which weren't drained are dropped on every call to `maintain`.
+    /// See the `mailbox` module.
+    ///
+    /// Does nothing if the mailbox is already registered.
+    fn register_mailbox<E>(&mut self)
+    where
+        Mailbox<E>: Resource;
+
+    /// Captures all entities together with the components registered
+    /// through `register_cloneable`.
+    ///
+    /// Entities which were created or deleted atomically are captured in
+    /// that state, so it's usually best to call `maintain` first.
+    ///
+    /// ## Examples
+    ///
+    /// ```
+    /// use specs::prelude::*;
+    ///
+    /// #[derive(Clone, Debug, PartialEq)]
+    /// struct Pos(f32);
+    ///
+    /// impl Component for Pos {
+    ///     type Storage = VecStorage<Self>;
+    /// }
+    ///
+    /// let mut world = World::new();
+    /// world.register::<Pos>();
+    /// world.register_cloneable::<Pos>();
+    ///
+    /// let entity = world.create_entity().with(Pos(1.0)).build();
+    /// let snapshot = world.snapshot();
+    ///
+    /// world.write_storage::<Pos>().get_mut(entity).unwrap().0 = 5.0;
+    /// world.delete_entity(entity).unwrap();
+    ///
+    /// world.restore(&snapshot);
+    /// assert_eq!(world.read_storage::<Pos>().get(entity), Some(&Pos(1.0)));
+    /// ```
+    fn snapshot(&self) -> WorldSnapshot;
+
+    /// Restores the entities and cloneable components captured in
+    /// `snapshot`, so that entity ids and generations are exactly the ones
+    /// from the time of the snapshot.
+    ///
+    /// Components of storages which are not cloneable are kept, except for
+    /// those of entities which are not alive in `snapshot`. Storages which
+    /// were registered as cloneable after the snapshot was taken are not
+    /// modified.
+    fn restore(&mut self, snapshot: &WorldSnapshot);
+
+    /// Returns the `TypeId`s and names of the components `entity` has,
+    /// looked up in all registered storages. Returns nothing if `entity` is
+    /// not alive.
+    ///
+    /// All dynamic components are reported as a single `DynamicComponents`
+    /// entry.
+    ///
+    /// ## Examples
+    ///
+    /// ```
+    /// # use std::any::TypeId;
+    /// # use specs::prelude::*;
+    /// # struct Pos(f32); impl Component for Pos { type Storage = VecStorage<Self>; }
+    /// # struct Vel(f32); impl Component for Vel { type Storage = VecStorage<Self>; }
+    /// let mut world = World::new();
+    /// world.register::<Pos>();
+    /// world.register::<Vel>();
+    ///
+    /// let e = world.create_entity().with(Pos(0.0)).build();
+    ///
+    /// let components = world.components_of(e);
+    /// assert_eq!(components.len(), 1);
+    /// assert_eq!(components[0].0, TypeId::of::<Pos>());
+    /// ```
+    fn components_of(&self, entity: Entity) -> Vec<(TypeId, &'static str)>;
+
+    /// Creates a new entity with clones of the components of `entity`.
+    ///
+    /// Only components registered through `register_cloneable` are cloned.
+    /// Other components of `entity` are skipped with a warning, so the clone
+    /// may lack some of them; compare `components_of` for both entities to
+    /// find out which.
+    fn clone_entity(&mut self, entity: Entity) -> Result<Entity, WrongGeneration>;
+
     /// Adds a resource to the world.
     ///
     /// If the resource already exists it will be overwritten.
@@ -256,9 +446,17 @@ pub trait WorldExt {
     fn create_iter(&mut self) -> CreateIter;
 
     /// Deletes an entity and its components.
+    ///
+    /// If the world has a `Hierarchy`, all descendants of the entity are
+    /// deleted as well. Components referencing the entity are handled
+    /// according to their `RefPolicy`.
     fn delete_entity(&mut self, entity: Entity) -> Result<(), WrongGeneration>;
 
     /// Deletes the specified entities and their components.
+    ///
+    /// If the world has a `Hierarchy`, all descendants of the entities are
+    /// deleted as well. Components referencing the entities are handled
+    /// according to their `RefPolicy`.
     fn delete_entities(&mut self, delete: &[Entity]) -> Result<(), WrongGeneration>;
 
     /// Deletes all entities and their components.
@@ -284,7 +482,17 @@ pub trait WorldExt {
     /// and deleted entities into the persistent generations vector.
     /// Also removes all the abandoned components.
     ///
-    /// Additionally, `LazyUpdate` will be merged.
+    /// Additionally, the `Commands` submitted to the `CommandQueue` are
+    /// applied in their deterministic order, followed by `LazyUpdate`.
+    ///
+    /// If the world has a `Hierarchy`, the descendants of deleted entities
+    /// are deleted as well and the hierarchy index is brought up to date.
+    /// Components referencing deleted entities are handled according to
+    /// their `RefPolicy`.
+    ///
+    /// The per-tick changes recorded by `ChangeTrackedStorage`s are cleared
+    /// first, so the deletions, commands and lazy updates of this call are
+    /// recorded for the next tick.
     fn maintain(&mut self);
 
     #[doc(hidden)]
@@ -297,6 +505,11 @@ impl WorldExt for World {
         world.insert(EntitiesRes::default());
         world.insert(MetaTable::<dyn AnyStorage>::default());
         world.insert(LazyUpdate::default());
+        world.insert(CommandQueue::default());
+        world.insert(DynamicComponents::default());
+        world
+            .fetch_mut::<MetaTable<dyn AnyStorage>>()
+            .register(&*world.fetch::<DynamicComponents>());
 
         world
     }
@@ -315,12 +528,164 @@ impl WorldExt for World {
     {
         self.entry()
             .or_insert_with(move || MaskedStorage::<T>::new(storage()));
+        MaskedStorage::<T>::connect_lazy(self);
         self.entry::<MetaTable<dyn AnyStorage>>()
             .or_insert_with(Default::default);
         self.fetch_mut::<MetaTable<dyn AnyStorage>>()
             .register(&*self.fetch::<MaskedStorage<T>>());
     }
 
+    #[cfg(feature = "parallel")]
+    fn register_cloneable<T>(&mut self)
+    where
+        T: Component + Clone + Send + Sync,
+    {
+        register_cloneable::<T>(self);
+    }
+
+    #[cfg(not(feature = "parallel"))]
+    fn register_cloneable<T>(&mut self)
+    where
+        T: Component + Clone,
+    {
+        register_cloneable::<T>(self);
+    }
+
+    #[cfg(feature = "reflect")]
+    fn register_reflect<T>(&mut self)
+    where
+        T: Component + Serialize + DeserializeOwned,
+    {
+        self.fetch_mut::<MaskedStorage<T>>().enable_reflect();
+    }
+
+    fn register_entity_refs<T>(&mut self, policy: RefPolicy)
+    where
+        T: Component + EntityRefs,
+    {
+        self.entry::<RefRegistry>()
+            .or_insert_with(Default::default)
+            .register::<T>(policy);
+    }
+
+    fn register_names(&mut self) {
+        self.register::<Name>();
+        if !self.has_value::<NameIndex>() {
+            let index = NameIndex::new(&mut self.write_storage());
+            self.insert(index);
+        }
+        self.entry::<IndexRegistry>()
+            .or_insert_with(Default::default)
+            .register::<NameIndex>(named::maintain_names);
+    }
+
+    fn register_tags<T>(&mut self)
+    where
+        T: Clone + Eq + std::hash::Hash + Send + Sync + 'static,
+    {
+        self.register::<Tag<T>>();
+        if !self.has_value::<TagIndex<T>>() {
+            let index = TagIndex::<T>::new(&mut self.write_storage());
+            self.insert(index);
+        }
+        self.entry::<IndexRegistry>()
+            .or_insert_with(Default::default)
+            .register::<TagIndex<T>>(named::maintain_tags::<T>);
+    }
+
+    fn register_mailbox<E>(&mut self)
+    where
+        Mailbox<E>: Resource,
+    {
+        self.entry::<Mailbox<E>>().or_insert_with(Default::default);
+        self.entry::<MailboxRegistry>()
+            .or_insert_with(Default::default)
+            .register::<E>();
+    }
+
+    fn snapshot(&self) -> WorldSnapshot {
+        let allocator = self.entities().alloc.clone();
+        let storages = match self.try_fetch::<MetaTable<dyn CloneStorage>>() {
+            Some(table) => table
+                .iter(&self)
+                .map(|storage| (storage.component_type(), storage.snapshot()))
+                .collect(),
+            None => Default::default(),
+        };
+
+        WorldSnapshot {
+            allocator,
+            storages,
+        }
+    }
+
+    fn restore(&mut self, snapshot: &WorldSnapshot) {
+        let stale: Vec<Entity> = self
+            .entities()
+            .with_disabled()
+            .join()
+            .filter(|&e| !snapshot.allocator.is_alive(e))
+            .collect();
+        self.delete_components(&stale);
+
+        self.entities_mut().alloc = snapshot.allocator.clone();
+        sync_disabled(self);
+
+        if let Some(table) = self.try_fetch_mut::<MetaTable<dyn CloneStorage>>() {
+            for storage in table.iter_mut(&self) {
+                if let Some(components) = snapshot.storages.get(&storage.component_type()) {
+                    storage.restore(components);
+                }
+            }
+        }
+    }
+
+    fn components_of(&self, entity: Entity) -> Vec<(TypeId, &'static str)> {
+        if !self.entities().alloc.is_alive(entity) {
+            return vec![];
+        }
+
+        match self.try_fetch::<MetaTable<dyn AnyStorage>>() {
+            Some(table) => table
+                .iter(&self)
+                .filter(|storage| storage.has(entity))
+                .map(|storage| (storage.component_type(), storage.type_name()))
+                .collect(),
+            None => vec![],
+        }
+    }
+
+    fn clone_entity(&mut self, entity: Entity) -> Result<Entity, WrongGeneration> {
+        if !self.entities().alloc.is_alive(entity) {
+            return Err(WrongGeneration {
+                action: "clone",
+                actual_gen: self.entities().entity(entity.id()).gen(),
+                entity,
+            });
+        }
+
+        let clone = self.entities_mut().alloc.allocate();
+        let mut cloned = Vec::new();
+        if let Some(table) = self.try_fetch_mut::<MetaTable<dyn CloneStorage>>() {
+            for storage in table.iter_mut(&self) {
+                storage.clone_component(entity, clone);
+                cloned.push(storage.component_type());
+            }
+        }
+
+        for (component, name) in self.components_of(entity) {
+            if !cloned.contains(&component) {
+                log::warn!(
+                    "Component `{}` of {:?} wasn't cloned because it isn't registered as cloneable.",
+                    name,
+                    entity
+                );
+            }
+        }
+
+        Ok(clone)
+    }
+
     fn add_resource<T: Resource>(&mut self, res: T) {
         self.insert(res);
     }
@@ -372,15 +737,24 @@ impl WorldExt for World {
     }
 
     fn delete_entities(&mut self, delete: &[Entity]) -> Result<(), WrongGeneration> {
+        {
+            let alloc = &self.entities().alloc;
+            if let Some(&entity) = delete.iter().find(|&&e| !alloc.is_alive(e)) {
+                return alloc.del_err(entity);
+            }
+        }
+
+        self.entities_mut().alloc.kill(delete)?;
+        let cascaded = cascade_deletion(self, delete);
+
         self.delete_components(delete);
+        self.delete_components(&cascaded);
 
-        self.entities_mut().alloc.kill(delete)
+        Ok(())
     }
 
     fn delete_all(&mut self) {
-        use crate::join::Join;
-
-        let entities: Vec<_> = self.entities().join().collect();
+        let entities: Vec<_> = self.entities().with_disabled().join().collect();
 
         self.delete_entities(&entities).expect(
             "Bug: previously collected entities are not valid \
@@ -396,15 +770,42 @@ impl WorldExt for World {
     }
 
     fn maintain(&mut self) {
-        let deleted = self.entities_mut().alloc.merge();
+        mailbox::maintain(self);
+
+        // Cleared before anything else changes the storages, so the changes
+        // made below are recorded for the next tick.
+        self.entry::<MetaTable<dyn AnyStorage>>()
+            .or_insert_with(Default::default);
+        for storage in self
+            .fetch_mut::<MetaTable<dyn AnyStorage>>()
+            .iter_mut(&self)
+        {
+            storage.maintain();
+        }
+
+        let mut deleted = self.entities_mut().alloc.merge();
         if !deleted.is_empty() {
+            let cascaded = cascade_deletion(self, &deleted);
+            deleted.extend(cascaded);
             self.delete_components(&deleted);
         }
+        sync_disabled(self);
+
+        let commands = match self.try_fetch_mut::<CommandQueue>() {
+            Some(mut queue) => queue.drain(),
+            None => vec![],
+        };
+        for commands in commands {
+            commands.apply(self);
+        }
 
-        // we need to swap the queue out to be able to reborrow self mutable here
-        let mut lazy = self.write_resource::<LazyUpdate>().take();
+        // the queue is shared, so the resource doesn't stay borrowed while the
+        // updates reborrow self mutably
+        let lazy = self.read_resource::<LazyUpdate>().share();
         lazy.maintain(&mut *self);
-        self.write_resource::<LazyUpdate>().restore(lazy);
+
+        hierarchy::maintain(self);
+        named::maintain(self);
     }
 
     fn delete_components(&mut self, delete: &[Entity]) {
@@ -418,3 +819,54 @@ impl WorldExt for World {
         }
     }
 }
+
+/// Copies the disabled entities to `DynamicComponents`, whose joins can't
+/// access `EntitiesRes`.
+fn sync_disabled(world: &World) {
+    if let Some(mut dynamic) = world.try_fetch_mut::<DynamicComponents>() {
+        dynamic.set_disabled(world.entities().disabled());
+    }
+}
+
+/// Kills the hierarchy descendants of the already killed `roots` and the
+/// entities referencing them with `RefPolicy::Cascade`, repeating until no
+/// further entities have to be deleted. Returns all additionally killed
+/// entities.
+fn cascade_deletion(world: &mut World, roots: &[Entity]) -> Vec<Entity> {
+    let mut cascaded = vec![];
+    let mut pending = roots.to_vec();
+    while !pending.is_empty() {
+        let mut descendants = hierarchy::descendants(world, &pending);
+        {
+            let alloc = &world.entities().alloc;
+            descendants.retain(|&e| alloc.is_alive(e));
+        }
+        world.entities_mut().alloc.kill(&descendants).expect(
+            "Bug: descendants of deleted entities are not valid \
+             even though they were just collected",
+        );
+        cascaded.extend_from_slice(&descendants);
+
+        pending = relation::invalidate_all(world);
+        world.entities_mut().alloc.kill(&pending).expect(
+            "Bug: entities referencing deleted entities are not valid \
+             even though they were just collected",
+        );
+        cascaded.extend_from_slice(&pending);
+    }
+
+    cascaded
+}
+
+fn register_cloneable<T>(world: &mut World)
+where
+    T: Component,
+    MaskedStorage<T>: CloneStorage,
+{
+    world
+        .entry::<MetaTable<dyn CloneStorage>>()
+        .or_insert_with(Default::default);
+    world
+        .fetch_mut::<MetaTable<dyn CloneStorage>>()
+        .register(&*world.fetch::<MaskedStorage<T>>());
+}
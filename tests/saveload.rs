@@ -111,8 +111,55 @@ mod tests {
 
     impl EntityLike for Entity {}
 
+    #[derive(ConvertSaveload)]
+    #[convert_save_load_transient]
+    struct TransientNamed {
+        e: Entity,
+        #[convert_save_load_transient]
+        cache: UnserializableType,
+    }
+
+    #[derive(ConvertSaveload)]
+    struct TransientTuple(Entity, #[convert_save_load_transient] Vec<u32>);
+
+    #[derive(ConvertSaveload)]
+    enum TransientEnum {
+        A(Entity, #[convert_save_load_transient] u32),
+        B {
+            e: Entity,
+            #[convert_save_load_transient]
+            cache: UnserializableType,
+        },
+    }
+
     struct NetworkSync;
 
+    #[test]
+    fn transient() {
+        type M = SimpleMarker<NetworkSync>;
+
+        assert!(<TransientNamed as ConvertSaveload<M>>::TRANSIENT);
+        assert!(!<TransientTuple as ConvertSaveload<M>>::TRANSIENT);
+        assert!(!<TransientEnum as ConvertSaveload<M>>::TRANSIENT);
+
+        let mut world = World::new();
+        let entity = world.create_entity().build();
+        let marker: M = serde_json::from_str("[7]").unwrap();
+
+        let data = TransientTuple(entity, vec![1, 2])
+            .convert_into(|_| Some(marker))
+            .unwrap();
+        let json = serde_json::to_string(&data).unwrap();
+        assert!(json.contains('7'));
+        assert!(!json.contains('2'));
+
+        let data = serde_json::from_str(&json).unwrap();
+        let loaded =
+            <TransientTuple as ConvertSaveload<M>>::convert_from(data, |_| Some(entity)).unwrap();
+        assert_eq!(loaded.0, entity);
+        assert!(loaded.1.is_empty());
+    }
+
     #[test]
     fn type_check() {
         let mut world = World::new();
@@ -139,6 +186,11 @@ mod tests {
         // so no need to test anything but unit
         black_box::<M, _>(AnEnum::Unit);
         black_box::<M, _>(Generic(entity));
+        black_box::<M, _>(TransientNamed {
+            e: entity,
+            cache: UnserializableType::default(),
+        });
+        black_box::<M, _>(TransientEnum::A(entity, 5));
     }
 
     fn black_box<M, T: ConvertSaveload<M>>(_item: T) {}
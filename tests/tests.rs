@@ -626,3 +626,606 @@ fn maintain_entity_deletion() {
     world.maintain();
     check.run_now(&world);
 }
+
+#[derive(Clone, Debug, PartialEq)]
+struct CompTracked(i8);
+
+impl Component for CompTracked {
+    type Storage = FlaggedStorage<Self>;
+}
+
+#[test]
+fn query_filters() {
+    use specs::join::{Added, Changed, Query, Removed, With, Without};
+
+    let mut world = create_world();
+    world.register::<CompTracked>();
+    let mut added = world.write_storage::<CompTracked>().register_reader();
+    let mut changed = world.write_storage::<CompTracked>().register_reader();
+    let mut removed = world.write_storage::<CompTracked>().register_reader();
+
+    let e1 = world
+        .create_entity()
+        .with(CompInt(1))
+        .with(CompTracked(1))
+        .build();
+    let e2 = world
+        .create_entity()
+        .with(CompInt(2))
+        .with(CompBool(true))
+        .with(CompTracked(2))
+        .build();
+    let e3 = world.create_entity().with(CompTracked(3)).build();
+
+    {
+        let entities = world.entities();
+        let int = world.read_storage::<CompInt>();
+        let boolean = world.read_storage::<CompBool>();
+        let tracked = world.read_storage::<CompTracked>();
+
+        let query = Query::new()
+            .filter(With::new(&int))
+            .filter(Without::new(&boolean))
+            .filter(Added::new(&tracked, &mut added));
+        let joined: Vec<_> = (&entities, query).join().map(|(e, _)| e).collect();
+        assert_eq!(joined, vec![e1]);
+    }
+
+    // Consume the insertions.
+    Changed::new(&world.read_storage::<CompTracked>(), &mut changed);
+    Removed::new(&world.read_storage::<CompTracked>(), &mut removed);
+
+    world.write_storage::<CompTracked>().get_mut(e2).unwrap().0 = 4;
+    world.write_storage::<CompTracked>().remove(e3);
+
+    let entities = world.entities();
+    let tracked = world.read_storage::<CompTracked>();
+
+    let query = Query::new().filter(Added::new(&tracked, &mut added));
+    assert_eq!((&entities, query).join().count(), 0);
+
+    let query = Query::new().filter(Changed::new(&tracked, &mut changed));
+    let joined: Vec<_> = (&entities, &tracked, query)
+        .join()
+        .map(|(e, t, _)| (e, t.0))
+        .collect();
+    assert_eq!(joined, vec![(e2, 4)]);
+
+    let query = Query::new().filter(Removed::new(&tracked, &mut removed));
+    let joined: Vec<_> = (&entities, query).join().map(|(e, _)| e).collect();
+    assert_eq!(joined, vec![e3]);
+
+    // Queries without a constraining filter are reported as unconstrained.
+    fn unconstrained<J: Join>(_: &J) -> bool {
+        J::is_unconstrained()
+    }
+    let boolean = world.read_storage::<CompBool>();
+    assert!(unconstrained(&Query::new()));
+    assert!(unconstrained(&Query::new().filter(Without::new(&boolean))));
+    assert!(!unconstrained(
+        &Query::new()
+            .filter(Without::new(&boolean))
+            .filter(With::new(&tracked))
+    ));
+    assert!(!unconstrained(&(&entities, Query::new())));
+}
+
+#[test]
+#[cfg(feature = "parallel")]
+fn par_join_query() {
+    use rayon::iter::ParallelIterator;
+    use specs::join::{Query, With, Without};
+
+    let mut world = create_world();
+    for i in 0..100 {
+        let builder = world.create_entity().with(CompInt(1));
+        if i % 2 == 0 {
+            builder.with(CompBool(true)).build();
+        } else {
+            builder.build();
+        }
+    }
+
+    let mut int = world.write_storage::<CompInt>();
+    let boolean = world.read_storage::<CompBool>();
+
+    let query = Query::new().filter(Without::new(&boolean));
+    (&mut int, query).par_join().for_each(|(int, _)| int.0 = 0);
+    let query = Query::new().filter(With::new(&boolean));
+    assert_eq!((&int, query).join().filter(|(int, _)| int.0 == 1).count(), 50);
+    assert_eq!((&int).join().filter(|int| int.0 == 0).count(), 50);
+}
+
+#[test]
+fn custom_storage_join_mut() {
+    use specs::{hibitset::BitSetLike, storage::UnprotectedStorage, world::Index};
+    use std::collections::HashMap;
+
+    struct MapStorage<T>(HashMap<Index, T>);
+
+    impl<T> Default for MapStorage<T> {
+        fn default() -> Self {
+            MapStorage(HashMap::new())
+        }
+    }
+
+    impl<T> UnprotectedStorage<T> for MapStorage<T> {
+        unsafe fn clean<B>(&mut self, _has: B)
+        where
+            B: BitSetLike,
+        {
+        }
+
+        unsafe fn get(&self, id: Index) -> &T {
+            &self.0[&id]
+        }
+
+        unsafe fn get_mut(&mut self, id: Index) -> &mut T {
+            self.0.get_mut(&id).unwrap()
+        }
+
+        unsafe fn insert(&mut self, id: Index, value: T) {
+            self.0.insert(id, value);
+        }
+
+        unsafe fn remove(&mut self, id: Index) -> T {
+            self.0.remove(&id).unwrap()
+        }
+    }
+
+    #[derive(Debug, PartialEq)]
+    struct Counter(u32);
+
+    impl Component for Counter {
+        type Storage = MapStorage<Self>;
+    }
+
+    let mut world = World::new();
+    world.register::<Counter>();
+    let a = world.create_entity().with(Counter(1)).build();
+    let b = world.create_entity().with(Counter(2)).build();
+
+    let mut counters = world.write_storage::<Counter>();
+    for counter in (&mut counters).join() {
+        let counter: &mut Counter = counter;
+        counter.0 *= 10;
+    }
+
+    assert_eq!(counters.get(a), Some(&Counter(10)));
+    assert_eq!(counters.get(b), Some(&Counter(20)));
+}
+
+#[test]
+fn soa_component() {
+    use specs::storage::{
+        ChunkedSlice, ReadSoa, SoaColumns, SoaColumnsMut, SoaComponent, WriteSoa,
+    };
+    use specs_derive::SoaComponent;
+
+    #[derive(Debug, PartialEq, SoaComponent)]
+    struct Motion {
+        pos: f32,
+        vel: f32,
+    }
+
+    let mut world = create_world();
+    <WriteSoa<Motion> as SystemData>::setup(&mut world);
+
+    let entities: Vec<_> = (0..11).map(|_| world.create_entity().build()).collect();
+    {
+        let mut motions = world.system_data::<WriteSoa<Motion>>();
+        for (i, &e) in entities.iter().enumerate() {
+            let motion = Motion {
+                pos: 0.0,
+                vel: i as f32,
+            };
+            assert_eq!(motions.insert(e, motion).unwrap(), None);
+        }
+
+        let replaced = motions.insert(entities[3], Motion { pos: 1.0, vel: 3.0 });
+        assert_eq!(replaced.unwrap(), Some(Motion { pos: 0.0, vel: 3.0 }));
+        assert_eq!(
+            motions.remove(entities[0]),
+            Some(Motion { pos: 0.0, vel: 0.0 })
+        );
+        assert_eq!(motions.remove(entities[0]), None);
+    }
+
+    world.delete_entity(entities[1]).unwrap();
+
+    {
+        let mut motions = world.system_data::<WriteSoa<Motion>>();
+        assert_eq!(motions.len(), 9);
+        assert_eq!(motions.columns().len(), 9);
+
+        let columns = motions.columns_mut();
+        let (pos, rest) = columns.pos.as_chunk_slice_mut::<[f32; 4]>();
+        let (vel, vel_rest) = columns.vel.as_chunk_slice::<[f32; 4]>();
+        assert_eq!((pos.len(), rest.len()), (2, 1));
+        for (p, v) in pos.iter_mut().zip(vel) {
+            for i in 0..4 {
+                p[i] += v[i];
+            }
+        }
+        for (p, v) in rest.iter_mut().zip(vel_rest) {
+            *p += v;
+        }
+    }
+
+    let motions = world.system_data::<ReadSoa<Motion>>();
+    let slices = motions.columns().slices();
+    for (row, &e) in motions.entities().iter().enumerate() {
+        assert_eq!(motions.row(e), Some(row));
+        let i = entities.iter().position(|&x| x == e).unwrap();
+        let start = if i == 3 { 1.0 } else { 0.0 };
+        assert_eq!(slices.pos[row], start + i as f32);
+    }
+    assert!(!motions.contains(entities[0]));
+    assert!(!motions.contains(entities[1]));
+    drop(motions);
+
+    {
+        let entities = world.entities();
+        let mut motions = world.system_data::<WriteSoa<Motion>>();
+        let (rows, columns) = motions.rows_and_columns_mut();
+        for (e, row) in (&entities, rows).join() {
+            columns.vel[row] = e.id() as f32;
+        }
+    }
+
+    let motions = world.system_data::<ReadSoa<Motion>>();
+    let slices = motions.columns().slices();
+    let joined: Vec<_> = (&world.entities(), motions.rows())
+        .join()
+        .map(|(e, row)| (e.id() as f32, slices.vel[row]))
+        .collect();
+    assert_eq!(joined.len(), 9);
+    for (id, vel) in joined {
+        assert_eq!(id, vel);
+    }
+    drop(motions);
+
+    // Rows of disabled entities are skipped unless opted in.
+    world.entities().set_enabled(entities[2], false).unwrap();
+    world.maintain();
+    let motions = world.system_data::<ReadSoa<Motion>>();
+    assert_eq!(motions.rows().join().count(), 8);
+    assert_eq!(motions.rows().with_disabled().join().count(), 9);
+}
+
+#[test]
+#[cfg(feature = "parallel")]
+fn par_join_with_config() {
+    use rayon::iter::ParallelIterator;
+    use specs::join::ParJoinConfig;
+
+    let mut world = create_world();
+    for i in 0..10_000 {
+        world.create_entity().with(CompInt((i % 3) as i8)).build();
+    }
+
+    let int = world.read_storage::<CompInt>();
+    let configs = vec![
+        ParJoinConfig::new(),
+        ParJoinConfig::new().split_layers(0),
+        ParJoinConfig::new().min_batch(1000),
+        ParJoinConfig::new().min_batch(100_000),
+    ];
+    for config in configs {
+        let sum: i64 = (&int)
+            .par_join_with(config)
+            .map(|int| i64::from(int.0))
+            .sum();
+        assert_eq!(sum, 9_999);
+    }
+    assert_eq!(ParJoinConfig::new().min_batch(1000).layers(), 2);
+}
+
+#[test]
+#[cfg(feature = "parallel")]
+fn par_packed() {
+    use rayon::iter::{IndexedParallelIterator, ParallelIterator};
+
+    #[derive(Debug, PartialEq)]
+    struct Packed(u32);
+
+    impl Component for Packed {
+        type Storage = DenseVecStorage<Self>;
+    }
+
+    let mut world = create_world();
+    world.register::<Packed>();
+    let entities: Vec<_> = (0..1000)
+        .map(|i| world.create_entity().with(Packed(i)).build())
+        .collect();
+    world.delete_entity(entities[10]).unwrap();
+
+    let mut packed = world.write_storage::<Packed>();
+    packed
+        .par_packed_with_disabled_mut()
+        .with_min_len(64)
+        .for_each(|(id, p)| p.0 = id * 2);
+
+    let positions: Vec<_> = packed
+        .par_packed_with_disabled()
+        .enumerate()
+        .map(|(position, (id, _))| (position, id))
+        .collect();
+    assert_eq!(positions.len(), 999);
+    for (position, id) in positions {
+        assert_eq!(packed.as_slice()[position], Packed(id * 2));
+    }
+    drop(packed);
+
+    // Disabled entities are only skipped without `with_disabled`.
+    world.entities().set_enabled(entities[20], false).unwrap();
+    world.maintain();
+    let mut packed = world.write_storage::<Packed>();
+    packed.par_packed_mut().for_each(|(_, p)| p.0 += 1);
+    assert_eq!(packed.get(entities[20]), Some(&Packed(40)));
+    assert_eq!(packed.get(entities[21]), Some(&Packed(43)));
+    assert_eq!(packed.par_packed().count(), 998);
+    assert_eq!(packed.par_packed_with_disabled().count(), 999);
+}
+
+#[test]
+fn dynamic_components() {
+    use specs::storage::DynamicComponents;
+
+    let mut world = create_world();
+    let (health, armor) = {
+        let mut dynamic = world.write_resource::<DynamicComponents>();
+        let health = dynamic.register("health", 2);
+        let armor = dynamic.register("armor", 1);
+        assert_eq!(dynamic.register("health", 2), health);
+        assert_eq!(dynamic.id("armor"), Some(armor));
+        (health, armor)
+    };
+
+    let entities: Vec<_> = (0..5)
+        .map(|i| world.create_entity().with(CompInt(i)).build())
+        .collect();
+    {
+        let all = world.entities();
+        let mut dynamic = world.write_resource::<DynamicComponents>();
+        let (health_storage, armor_storage) = dynamic.storage_pair_mut(health, armor);
+        for (i, &e) in entities.iter().enumerate() {
+            health_storage.insert(&all, e, &[i as u8, 0]).unwrap();
+            if i % 2 == 0 {
+                armor_storage.insert(&all, e, &[1]).unwrap();
+            }
+        }
+        assert_eq!(
+            health_storage.insert(&all, entities[0], &[7, 7]).unwrap(),
+            Some(vec![0, 0])
+        );
+    }
+
+    {
+        let ints = world.read_storage::<CompInt>();
+        let mut dynamic = world.write_resource::<DynamicComponents>();
+        let (health_storage, armor_storage) = dynamic.storage_pair_mut(health, armor);
+        for (int, health, armor) in (&ints, &mut *health_storage, &*armor_storage).join() {
+            health[1] = int.0 as u8 + armor[0];
+        }
+    }
+
+    world.delete_entity(entities[2]).unwrap();
+
+    let dynamic = world.read_resource::<DynamicComponents>();
+    let health_storage = dynamic.storage(health);
+    assert_eq!(health_storage.len(), 4);
+    assert_eq!(dynamic.storage(armor).len(), 2);
+    assert_eq!(health_storage.get(entities[0]), Some(&[7, 1][..]));
+    assert_eq!(health_storage.get(entities[1]), Some(&[1, 0][..]));
+    assert_eq!(health_storage.get(entities[2]), None);
+    assert_eq!(health_storage.get(entities[4]), Some(&[4, 5][..]));
+    drop(dynamic);
+
+    // Joins skip disabled entities unless opted in.
+    world.entities().set_enabled(entities[1], false).unwrap();
+    world.maintain();
+    let mut dynamic = world.write_resource::<DynamicComponents>();
+    for value in dynamic.storage_mut(health).join() {
+        value[0] = 9;
+    }
+    for value in dynamic.storage_mut(health).with_disabled_mut().join() {
+        value[1] = 9;
+    }
+    let health_storage = dynamic.storage(health);
+    assert_eq!(health_storage.get(entities[1]), Some(&[1, 9][..]));
+    assert_eq!(health_storage.get(entities[4]), Some(&[9, 9][..]));
+    assert_eq!(health_storage.join().count(), 3);
+    assert_eq!(health_storage.with_disabled().join().count(), 4);
+}
+
+#[test]
+#[cfg(feature = "parallel")]
+fn par_join_dynamic() {
+    use rayon::iter::ParallelIterator;
+    use specs::storage::DynamicComponents;
+
+    let mut world = create_world();
+    let counter = world
+        .write_resource::<DynamicComponents>()
+        .register("counter", 4);
+    {
+        let entities = world.entities();
+        let mut dynamic = world.write_resource::<DynamicComponents>();
+        for i in 0..1000u32 {
+            let e = entities.create();
+            dynamic
+                .storage_mut(counter)
+                .insert(&entities, e, &i.to_le_bytes())
+                .unwrap();
+        }
+    }
+
+    let mut dynamic = world.write_resource::<DynamicComponents>();
+    dynamic.storage_mut(counter).par_join().for_each(|bytes| {
+        let mut value = [0; 4];
+        value.copy_from_slice(bytes);
+        bytes.copy_from_slice(&(u32::from_le_bytes(value) * 2).to_le_bytes());
+    });
+
+    let sum: u32 = dynamic
+        .storage(counter)
+        .join()
+        .map(|bytes| u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+        .sum();
+    assert_eq!(sum, 999_000);
+}
+
+#[test]
+fn any_storage_type_erased() {
+    use specs::{shred::MetaTable, storage::AnyStorage};
+
+    let mut world = create_world();
+    let e = world
+        .create_entity()
+        .with(CompInt(1))
+        .with(CompBool(true))
+        .build();
+    let other = world.create_entity().with(CompInt(2)).build();
+    let dead = world.create_entity().build();
+    world.delete_entity(dead).unwrap();
+
+    {
+        let entities = world.entities();
+        let table = world.fetch_mut::<MetaTable<dyn AnyStorage>>();
+        let mut names: Vec<_> = table
+            .iter(&world)
+            .filter(|storage| storage.has(e))
+            .map(|storage| storage.type_name())
+            .collect();
+        names.sort();
+        assert_eq!(names, vec!["tests::CompBool", "tests::CompInt"]);
+
+        for storage in table.iter_mut(&world) {
+            if storage.type_name() == "tests::CompInt" {
+                assert!(storage.remove(&entities, e).unwrap());
+                assert!(!storage.remove(&entities, e).unwrap());
+                assert!(storage.remove(&entities, dead).is_err());
+            }
+        }
+    }
+
+    assert!(world.read_storage::<CompInt>().get(e).is_none());
+    assert_eq!(world.read_storage::<CompInt>().get(other), Some(&CompInt(2)));
+    assert_eq!(world.read_storage::<CompBool>().get(e), Some(&CompBool(true)));
+}
+
+#[cfg(feature = "reflect")]
+#[test]
+fn any_storage_reflect() {
+    use serde::{Deserialize, Serialize};
+    use specs::{error::ReflectError, shred::MetaTable, storage::AnyStorage};
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Named {
+        name: String,
+    }
+
+    impl Component for Named {
+        type Storage = DenseVecStorage<Self>;
+    }
+
+    let mut world = create_world();
+    world.register::<Named>();
+    world.register_reflect::<Named>();
+
+    let a = world
+        .create_entity()
+        .with(Named {
+            name: "a".to_owned(),
+        })
+        .with(CompInt(1))
+        .build();
+    let b = world.create_entity().build();
+    let dead = world.create_entity().build();
+    world.delete_entity(dead).unwrap();
+
+    {
+        let entities = world.entities();
+        let table = world.fetch_mut::<MetaTable<dyn AnyStorage>>();
+        for storage in table.iter_mut(&world) {
+            if storage.type_name().ends_with("Named") {
+                let value = storage.serialize_for(a).unwrap().unwrap();
+                assert_eq!(value, serde_json::json!({ "name": "a" }));
+                assert!(storage.serialize_for(b).unwrap().is_none());
+
+                storage
+                    .deserialize_into(&entities, b, serde_json::json!({ "name": "b" }))
+                    .unwrap();
+                match storage.deserialize_into(&entities, a, serde_json::json!(3)) {
+                    Err(ReflectError::Serde(_)) => {}
+                    other => panic!("Expected a serde error, got {:?}", other),
+                }
+                match storage.deserialize_into(&entities, dead, serde_json::json!({ "name": "c" })) {
+                    Err(ReflectError::WrongGeneration(_)) => {}
+                    other => panic!("Expected `WrongGeneration`, got {:?}", other),
+                }
+            } else if storage.type_name().ends_with("CompInt") {
+                match storage.serialize_for(a) {
+                    Err(ReflectError::NotReflectable(_)) => {}
+                    other => panic!("Expected `NotReflectable`, got {:?}", other),
+                }
+            }
+        }
+    }
+
+    let named = world.read_storage::<Named>();
+    assert_eq!(named.get(a).unwrap().name, "a");
+    assert_eq!(named.get(b).unwrap().name, "b");
+}
+
+#[derive(Default)]
+struct HookLog(Vec<(Entity, i32)>);
+
+#[derive(specs_derive::Component)]
+#[storage(VecStorage)]
+#[on_insert(Handle::acquire)]
+#[on_remove(Handle::release)]
+struct Handle(i32);
+
+impl Handle {
+    fn acquire(&mut self, entity: Entity, lazy: &LazyUpdate) {
+        let id = self.0;
+        lazy.exec_mut(move |world| world.fetch_mut::<HookLog>().0.push((entity, id)));
+    }
+
+    fn release(&mut self, entity: Entity, lazy: &LazyUpdate) {
+        let id = self.0;
+        lazy.exec_mut(move |world| world.fetch_mut::<HookLog>().0.push((entity, -id)));
+    }
+}
+
+#[test]
+fn component_hooks() {
+    let mut world = World::new();
+    world.register::<Handle>();
+    world.insert(HookLog::default());
+
+    let a = world.create_entity().with(Handle(1)).build();
+    world.write_storage::<Handle>().insert(a, Handle(2)).unwrap();
+    assert!(world.write_storage::<Handle>().remove(a).is_some());
+    world.write_storage::<Handle>().insert(a, Handle(3)).unwrap();
+    world.delete_entity(a).unwrap();
+
+    let b = world.create_entity().with(Handle(4)).build();
+    world.read_resource::<LazyUpdate>().remove::<Handle>(b);
+
+    // Nothing runs before `maintain`.
+    assert!(world.fetch::<HookLog>().0.is_empty());
+    world.maintain();
+
+    assert_eq!(
+        world.fetch::<HookLog>().0,
+        vec![(a, 1), (a, 2), (a, -1), (a, -2), (a, 3), (a, -3), (b, 4)]
+    );
+
+    // The hook of the lazy removal queued its update during `maintain`, so
+    // it runs on the next one.
+    world.maintain();
+    assert_eq!(world.fetch::<HookLog>().0.last(), Some(&(b, -4)));
+}